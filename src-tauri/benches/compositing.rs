@@ -0,0 +1,109 @@
+//! Benchmarks de composition sur une image 12 MP (4000×3000).
+//!
+//! `cargo bench --bench compositing`
+//!
+//! `sequential_reference` reproduit l'ancienne boucle pixel par pixel
+//! (`get_pixel` / `put_pixel`, `match` du fond dans la boucle) pour mesurer
+//! le gain des versions parallèles par lignes.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage, RgbaImage};
+use pure_remove_lib::image_processor::{apply_mask, refine_mask, BackgroundColor, CompositingSpace};
+
+const WIDTH: u32 = 4000;
+const HEIGHT: u32 = 3000;
+
+fn sample_image() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+    }))
+}
+
+/// Disque flou centré : intérieur opaque, bords en dégradé.
+fn sample_mask() -> GrayImage {
+    let (cx, cy, r) = (WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0, HEIGHT as f32 / 3.0);
+    GrayImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        let d = ((x as f32 - cx).powi(2) + (y as f32 - cy).powi(2)).sqrt();
+        Luma([((r - d) * 4.0).clamp(0.0, 255.0) as u8])
+    })
+}
+
+fn sequential_blur(mask: &GrayImage) -> GrayImage {
+    let (w, h) = mask.dimensions();
+    let kernel = [1.0, 2.0, 1.0, 2.0, 4.0, 2.0, 1.0, 2.0, 1.0].map(|k: f32| k / 16.0);
+    let mut out = GrayImage::new(w, h);
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = 0.0f32;
+            for ky in 0..3i32 {
+                for kx in 0..3i32 {
+                    let px = (x as i32 + kx - 1).clamp(0, w as i32 - 1) as u32;
+                    let py = (y as i32 + ky - 1).clamp(0, h as i32 - 1) as u32;
+                    sum += mask.get_pixel(px, py)[0] as f32 * kernel[(ky * 3 + kx) as usize];
+                }
+            }
+            out.put_pixel(x, y, Luma([sum as u8]));
+        }
+    }
+    out
+}
+
+fn sequential_apply_mask(img: &DynamicImage, mask: &GrayImage, bg: &BackgroundColor) -> RgbaImage {
+    let (w, h) = (img.width(), img.height());
+    let rgba_src = img.to_rgba8();
+    let blurred = sequential_blur(mask);
+    let mut output = RgbaImage::new(w, h);
+    for y in 0..h {
+        for x in 0..w {
+            let src = rgba_src.get_pixel(x, y);
+            let alpha = blurred.get_pixel(x, y)[0];
+            let a = alpha as f32 / 255.0;
+            let blend = |fg: u8, bg_c: u8| (fg as f32 * a + bg_c as f32 * (1.0 - a)) as u8;
+            let out = match bg {
+                BackgroundColor::Transparent => [src[0], src[1], src[2], alpha],
+                BackgroundColor::White => [blend(src[0], 255), blend(src[1], 255), blend(src[2], 255), 255],
+                BackgroundColor::Black => [blend(src[0], 0), blend(src[1], 0), blend(src[2], 0), 255],
+                BackgroundColor::Color { r, g, b } => {
+                    [blend(src[0], *r), blend(src[1], *g), blend(src[2], *b), 255]
+                }
+            };
+            output.put_pixel(x, y, image::Rgba(out));
+        }
+    }
+    output
+}
+
+fn bench_blur(c: &mut Criterion) {
+    let mask = sample_mask();
+    let mut group = c.benchmark_group("blur_mask_12mp");
+    group.sample_size(10);
+    group.bench_function("sequential_reference", |b| b.iter(|| sequential_blur(&mask)));
+    group.bench_function("parallel_rows", |b| b.iter(|| refine_mask(&mask)));
+    group.finish();
+}
+
+fn bench_apply_mask(c: &mut Criterion) {
+    let img = sample_image();
+    let mask = sample_mask();
+    let mut group = c.benchmark_group("apply_mask_12mp");
+    group.sample_size(10);
+
+    for (name, bg) in [
+        ("transparent", BackgroundColor::Transparent),
+        ("white", BackgroundColor::White),
+    ] {
+        group.bench_function(format!("{name}/sequential_reference"), |b| {
+            b.iter(|| sequential_apply_mask(&img, &mask, &bg))
+        });
+        group.bench_function(format!("{name}/parallel_gamma"), |b| {
+            b.iter(|| apply_mask(&img, &mask, &bg, CompositingSpace::Gamma))
+        });
+        group.bench_function(format!("{name}/parallel_linear"), |b| {
+            b.iter(|| apply_mask(&img, &mask, &bg, CompositingSpace::Linear))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_blur, bench_apply_mask);
+criterion_main!(benches);
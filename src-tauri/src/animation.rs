@@ -0,0 +1,368 @@
+//! animation.rs — GIF et WebP animés : décodage de toutes les images avec
+//! leurs délais, lissage temporel des masques et réencodage avec transparence.
+
+use anyhow::Result;
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Delay, Frame, GrayImage, ImageDecoder, RgbaImage};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::image_processor::{check_megapixels, sniff_format};
+use crate::tr;
+
+/// Une image de l'animation, déjà composée sur le canevas complet.
+pub struct AnimationFrame {
+    pub image: RgbaImage,
+    pub delay_ms: u32,
+}
+
+/// Format du fichier animé produit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+pub enum AnimationFormat {
+    Gif,
+    WebP,
+}
+
+impl AnimationFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::WebP => "webp",
+        }
+    }
+
+    /// Format d'une animation d'après sa signature (`GIF8…` / `RIFF….WEBP`).
+    pub fn sniff(path: &Path) -> Result<Self> {
+        let mut header = [0u8; 12];
+        std::fs::File::open(path)
+            .and_then(|mut f| f.read_exact(&mut header))
+            .map_err(|e| AppError::io(path, &e, tr!("io.read", path = path.display(), error = e)))?;
+
+        if header.starts_with(b"GIF8") {
+            Ok(AnimationFormat::Gif)
+        } else if header.starts_with(b"RIFF") && &header[8..12] == b"WEBP" {
+            Ok(AnimationFormat::WebP)
+        } else {
+            // Autre type : rapporté par son nom (GIF ou WebP attendu)
+            Err(AppError::UnsupportedFormat { detected: sniff_format(&header).name() }.into())
+        }
+    }
+
+    /// Corrige l'extension de `dest` si elle ne correspond pas au format.
+    pub fn with_extension(self, dest: &Path) -> PathBuf {
+        let matches = dest
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case(self.extension()));
+        if matches {
+            dest.to_path_buf()
+        } else {
+            dest.with_extension(self.extension())
+        }
+    }
+}
+
+/// Options du traitement des animations (toutes facultatives côté frontend).
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct AnimationOptions {
+    /// Format de sortie ; par défaut celui de la source.
+    pub format: Option<AnimationFormat>,
+    /// Lissage temporel des masques, 0 (aucun) … 1 ([1 2 1] / 4 sur 3 images).
+    pub temporal_smoothing: f32,
+    /// WebP : sans perte, sinon compression avec perte à `quality`.
+    pub lossless: bool,
+    pub quality: u8,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            temporal_smoothing: 0.0,
+            lossless: true,
+            quality: 90,
+        }
+    }
+}
+
+// ─── Décodage ────────────────────────────────────────────────────────────────
+
+/// Pixels décodés au plus pour toute l'animation (images × canevas, RGBA
+/// 8 bits : 1,6 Go), en plus de la limite par image.
+pub const MAX_ANIMATION_MEGAPIXELS: f32 = 400.0;
+
+/// Collecte les images décodées, en s'arrêtant dès que le total dépasse
+/// `MAX_ANIMATION_MEGAPIXELS` (avant de décoder la suivante).
+fn collect_limited(frames: image::Frames<'_>, width: u32, height: u32) -> Result<Vec<Frame>> {
+    let canvas = width as f64 * height as f64 / 1_000_000.0;
+    let max_frames = ((MAX_ANIMATION_MEGAPIXELS as f64 / canvas.max(1e-6)) as usize).max(1);
+
+    let mut collected = Vec::new();
+    for frame in frames {
+        if collected.len() == max_frames {
+            return Err(AppError::ImageTooLarge {
+                width,
+                height,
+                megapixels: canvas * (max_frames + 1) as f64,
+                max_megapixels: MAX_ANIMATION_MEGAPIXELS,
+            }
+            .into());
+        }
+        collected.push(frame.map_err(|e| AppError::Decode(tr!("animation.decode", error = e)))?);
+    }
+    Ok(collected)
+}
+
+/// Décode toutes les images d'un GIF ou WebP animé (une image fixe donne une
+/// animation d'une seule image). `ImageTooLarge` si le canevas dépasse
+/// `max_megapixels` ou si l'ensemble dépasse `MAX_ANIMATION_MEGAPIXELS`.
+pub fn decode_frames(path: &Path, max_megapixels: f32) -> Result<Vec<AnimationFrame>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| AppError::io(path, &e, tr!("io.open", path = path.display(), error = e)))?;
+    let reader = BufReader::new(file);
+
+    let frames = match AnimationFormat::sniff(path)? {
+        AnimationFormat::Gif => {
+            let decoder = GifDecoder::new(reader).map_err(|e| {
+                AppError::Decode(tr!("codec.decode", format = "GIF", error = e))
+            })?;
+            let (w, h) = decoder.dimensions();
+            check_megapixels(w, h, max_megapixels)?;
+            collect_limited(decoder.into_frames(), w, h)?
+        }
+        AnimationFormat::WebP => {
+            let decoder = WebPDecoder::new(reader).map_err(|e| {
+                AppError::Decode(tr!("codec.decode", format = "WebP", error = e))
+            })?;
+            let (w, h) = decoder.dimensions();
+            check_megapixels(w, h, max_megapixels)?;
+            if decoder.has_animation() {
+                collect_limited(decoder.into_frames(), w, h)?
+            } else {
+                image::DynamicImage::from_decoder(decoder)
+                    .map(|img| vec![Frame::new(img.to_rgba8())])
+                    .map_err(|e| AppError::Decode(tr!("animation.decode", error = e)))?
+            }
+        }
+    };
+
+    if frames.is_empty() {
+        return Err(AppError::Decode(tr!("animation.empty")).into());
+    }
+
+    Ok(frames
+        .into_iter()
+        .map(|frame| {
+            let (num, den) = frame.delay().numer_denom_ms();
+            AnimationFrame {
+                delay_ms: num / den.max(1),
+                image: frame.into_buffer(),
+            }
+        })
+        .collect())
+}
+
+// ─── Lissage temporel ────────────────────────────────────────────────────────
+
+/// Mélange un masque avec ses voisins (précédent et suivant) pour atténuer
+/// le scintillement des bords : `(m + s/2·(préc + suiv)) / (1 + s)`.
+pub fn smooth_mask(
+    prev: &GrayImage,
+    current: &GrayImage,
+    next: &GrayImage,
+    strength: f32,
+) -> GrayImage {
+    let s = strength.clamp(0.0, 1.0);
+    let mut out = current.clone();
+    let dims = current.dimensions();
+    if s == 0.0 || prev.dimensions() != dims || next.dimensions() != dims {
+        return out;
+    }
+
+    for (((o, &c), &p), &n) in out
+        .iter_mut()
+        .zip(current.iter())
+        .zip(prev.iter())
+        .zip(next.iter())
+    {
+        let v = (c as f32 + s / 2.0 * (p as f32 + n as f32)) / (1.0 + s);
+        *o = v.round() as u8;
+    }
+    out
+}
+
+/// `smooth_mask` sur toute la séquence ; aux extrémités, le voisin manquant
+/// est remplacé par le masque lui-même.
+pub fn smooth_masks(masks: &[GrayImage], strength: f32) -> Vec<GrayImage> {
+    (0..masks.len())
+        .map(|i| {
+            let prev = &masks[i.saturating_sub(1)];
+            let next = &masks[(i + 1).min(masks.len() - 1)];
+            smooth_mask(prev, &masks[i], next, strength)
+        })
+        .collect()
+}
+
+// ─── Encodage ────────────────────────────────────────────────────────────────
+
+/// Encode les images détourées en animation bouclant indéfiniment.
+pub fn encode_animation(
+    frames: &[AnimationFrame],
+    format: AnimationFormat,
+    options: &AnimationOptions,
+) -> Result<Vec<u8>> {
+    match format {
+        AnimationFormat::Gif => encode_gif(frames),
+        AnimationFormat::WebP => encode_webp_animation(frames, options),
+    }
+}
+
+/// GIF : transparence sur 1 bit, l'alpha est donc seuillé à mi-hauteur avant
+/// quantification de la palette.
+fn encode_gif(frames: &[AnimationFrame]) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut buf, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| AppError::Encode(tr!("codec.encode", format = "GIF", error = e)))?;
+
+        for frame in frames {
+            let mut image = frame.image.clone();
+            for px in image.pixels_mut() {
+                px[3] = if px[3] >= 128 { 255 } else { 0 };
+            }
+            let delay = Delay::from_numer_denom_ms(frame.delay_ms, 1);
+            encoder
+                .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                .map_err(|e| AppError::Encode(tr!("codec.encode", format = "GIF", error = e)))?;
+        }
+    }
+    Ok(buf)
+}
+
+#[cfg(feature = "webp-lossy")]
+fn encode_webp_animation(frames: &[AnimationFrame], options: &AnimationOptions) -> Result<Vec<u8>> {
+    let (w, h) = frames[0].image.dimensions();
+    if frames.iter().any(|f| f.image.dimensions() != (w, h)) {
+        return Err(AppError::Encode(tr!("animation.size_mismatch")).into());
+    }
+
+    let mut config =
+        webp::WebPConfig::new().map_err(|_| AppError::Encode(tr!("animation.webp_config")))?;
+    config.lossless = options.lossless as i32;
+    config.quality = options.quality.clamp(1, 100) as f32;
+
+    // Le crate `webp` ne transmet pas l'horodatage de fin : libwebp estime
+    // seule la durée de la dernière image.
+    let mut encoder = webp::AnimEncoder::new(w, h, &config);
+    encoder.set_loop_count(0);
+    let mut timestamp = 0i32;
+    for frame in frames {
+        encoder.add_frame(webp::AnimFrame::from_rgba(&frame.image, w, h, timestamp));
+        timestamp = timestamp.saturating_add(frame.delay_ms as i32);
+    }
+
+    let encoded = encoder
+        .try_encode()
+        .map_err(|e| {
+            AppError::Encode(tr!("codec.encode", format = "WebP", error = format!("{e:?}")))
+        })?;
+    Ok(encoded.to_vec())
+}
+
+#[cfg(not(feature = "webp-lossy"))]
+fn encode_webp_animation(_frames: &[AnimationFrame], _options: &AnimationOptions) -> Result<Vec<u8>> {
+    Err(AppError::Encode(tr!("feature.animated_webp")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(shade: u8, delay_ms: u32) -> AnimationFrame {
+        // Alpha de part et d'autre du seuil GIF (128)
+        let image = RgbaImage::from_fn(8, 6, |x, y| {
+            image::Rgba([shade, x as u8 * 30, y as u8 * 40, (x * 36) as u8])
+        });
+        AnimationFrame { image, delay_ms }
+    }
+
+    fn temp_path(extension: &str) -> PathBuf {
+        let name = format!("pureremove-animation-{}.{extension}", std::process::id());
+        std::env::temp_dir().join(name)
+    }
+
+    #[test]
+    fn gif_round_trip() {
+        let frames = [frame(0, 40), frame(120, 100), frame(240, 70)];
+        let bytes = encode_animation(&frames, AnimationFormat::Gif, &Default::default()).unwrap();
+        let path = temp_path("gif");
+        std::fs::write(&path, bytes).unwrap();
+        let decoded = decode_frames(&path, 100.0);
+        std::fs::remove_file(&path).unwrap();
+
+        let decoded = decoded.unwrap();
+        assert_eq!(decoded.len(), frames.len());
+        for (read, written) in decoded.iter().zip(&frames) {
+            assert_eq!(read.delay_ms, written.delay_ms);
+            assert_eq!(read.image.dimensions(), written.image.dimensions());
+            for (p, q) in read.image.pixels().zip(written.image.pixels()) {
+                assert_eq!(p[3], if q[3] >= 128 { 255 } else { 0 });
+            }
+        }
+    }
+
+    #[cfg(feature = "webp-lossy")]
+    #[test]
+    fn lossless_webp_round_trip() {
+        let frames = [frame(0, 40), frame(120, 100), frame(240, 70)];
+        let bytes = encode_animation(&frames, AnimationFormat::WebP, &Default::default()).unwrap();
+        let path = temp_path("webp");
+        std::fs::write(&path, bytes).unwrap();
+        let decoded = decode_frames(&path, 100.0);
+        std::fs::remove_file(&path).unwrap();
+
+        let decoded = decoded.unwrap();
+        assert_eq!(decoded.len(), frames.len());
+        // Durée de la dernière image estimée par libwebp (voir `encode_webp_animation`)
+        for (read, written) in decoded.iter().zip(&frames[..2]) {
+            assert_eq!(read.delay_ms, written.delay_ms);
+        }
+        for (read, written) in decoded.iter().zip(&frames) {
+            let alpha = |img: &RgbaImage| img.pixels().map(|p| p[3]).collect::<Vec<_>>();
+            assert_eq!(alpha(&read.image), alpha(&written.image));
+        }
+    }
+
+    #[test]
+    fn collect_limited_stops_at_the_cap() {
+        // 200 Mpx par image : deux images tiennent dans MAX_ANIMATION_MEGAPIXELS
+        let (w, h) = (20_000, 10_000);
+        let frames = |n: usize| {
+            let iter = (0..n).map(|_| Ok(Frame::new(RgbaImage::new(1, 1))));
+            image::Frames::new(Box::new(iter))
+        };
+
+        assert_eq!(collect_limited(frames(2), w, h).unwrap().len(), 2);
+        let error = collect_limited(frames(3), w, h).err().expect("plafond ignoré");
+        assert!(matches!(AppError::from(error), AppError::ImageTooLarge { max_megapixels, .. }
+            if max_megapixels == MAX_ANIMATION_MEGAPIXELS));
+    }
+
+    #[test]
+    fn masks_smoothed_with_their_neighbours() {
+        let masks: Vec<GrayImage> = [0u8, 200, 100]
+            .iter()
+            .map(|&v| GrayImage::from_pixel(2, 2, image::Luma([v])))
+            .collect();
+        let smoothed = smooth_masks(&masks, 1.0);
+        let values: Vec<u8> = smoothed.iter().map(|m| m.get_pixel(0, 0)[0]).collect();
+        // (m + (préc + suiv) / 2) / 2, le masque lui-même aux extrémités
+        assert_eq!(values, [50, 125, 125]);
+        assert_eq!(smooth_masks(&masks, 0.0)[1], masks[1]);
+    }
+}
@@ -1,26 +1,43 @@
 /// commands.rs — Commandes Tauri exposées au frontend.
-/// Toutes les commandes retournent Result<T, String> pour que les erreurs
-/// arrivent comme des strings simples côté TypeScript (pas [object Object]).
+/// Toutes les commandes retournent AppResult<T> : les erreurs arrivent côté
+/// TypeScript en `{ code, message, details }` (voir error.rs).
 
 use crate::{
+    animation::{decode_frames, encode_animation, smooth_masks, AnimationFormat, AnimationOptions},
+    error::{AppError, AppResult},
+    i18n,
     image_processor::{
-        apply_mask, encode_base64_png, encode_png, load_image, load_image_from_bytes,
-        save_png, BackgroundColor,
+        apply_mask, apply_refined_mask, encode_png, inference_copy, load_image_from_bytes,
+        load_image_with_options, load_mask, refine_mask, save_mask,
+        save_output, upscale_mask,
+        BackgroundColor, CompositingSpace, ImageMetadata, MaskBitDepth, OutputFormat,
+        SvgRenderOptions, DEFAULT_MAX_MEGAPIXELS,
     },
+    jobs::{self, JobContext, JobId, JobInfo, JobKind, Stage, StageTiming},
+    layers::{save_layers, LayerOptions},
     ml_engine,
+    pages::{encode_multipage, load_pages, page_path, PageOptions, PageOutput},
+    results::{self, ProcessedResult, ResultId, ResultSource, StoredResult},
+    scan::{self, ScanOptions, ScanReport},
+    tr,
+    vectorizer::{save_mask_svg, SvgExportOptions},
+    video::{
+        check_output_dir, fps_ratio, FrameSource, SequenceWriter, TemporalSmoother, VideoOptions,
+        VideoSummary,
+    },
+    watch::{self, WatchConfig, WatchInfo, WatchLogEntry},
 };
-use base64::{engine::general_purpose::STANDARD, Engine};
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Emitter, Manager};
 
 // ─── Stockage de l'image clipboard originale (pour retraitement fond) ─────────
 
-static CLIPBOARD_ORIGINAL: OnceCell<Mutex<Option<Vec<u8>>>> = OnceCell::new();
+static CLIPBOARD_ORIGINAL: OnceCell<Mutex<Option<Arc<Vec<u8>>>>> = OnceCell::new();
 
-fn clipboard_store() -> &'static Mutex<Option<Vec<u8>>> {
+fn clipboard_store() -> &'static Mutex<Option<Arc<Vec<u8>>>> {
     CLIPBOARD_ORIGINAL.get_or_init(|| Mutex::new(None))
 }
 
@@ -29,101 +46,538 @@ fn clipboard_store() -> &'static Mutex<Option<Vec<u8>>> {
 #[derive(Debug, Clone, Deserialize)]
 pub struct ProcessOptions {
     pub background: BackgroundColor,
+    /// Joint un aperçu du masque affiné aux résultats.
+    #[serde(default)]
+    pub include_mask: bool,
+    /// Limite de taille au chargement ; au-delà, l'image est refusée.
+    #[serde(default = "default_max_megapixels")]
+    pub max_megapixels: f32,
+    /// Upscale du masque guidé par l'image pleine résolution.
+    #[serde(default)]
+    pub refine_edges: bool,
+    /// Retire EXIF/XMP des sorties (le profil ICC est toujours conservé).
+    #[serde(default)]
+    pub strip_metadata: bool,
+    /// Mélange avec le fond en lumière linéaire (défaut) ou à l'ancienne.
+    #[serde(default)]
+    pub compositing: CompositingSpace,
+    /// Taille de rasterisation des sources SVG.
+    #[serde(default)]
+    pub svg: SvgRenderOptions,
+}
+
+impl ProcessOptions {
+    fn output_metadata(&self, metadata: ImageMetadata) -> ImageMetadata {
+        if self.strip_metadata {
+            metadata.stripped()
+        } else {
+            metadata
+        }
+    }
+}
+
+fn default_max_megapixels() -> f32 {
+    DEFAULT_MAX_MEGAPIXELS
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct BatchProgress {
+    pub job_id: JobId,
     pub index: usize,
     pub total: usize,
     pub name: String,
-    pub result_data_url: Option<String>,
-    pub error: Option<String>,
+    pub result: Option<ProcessedResult>,
+    pub error: Option<AppError>,
+    /// Durée de chaque étape pour cette image.
+    pub timings: Vec<StageTiming>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnimationProgress {
+    pub frame: usize,
+    pub total: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoProgress {
+    pub frame: usize,
+    pub total: Option<usize>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PageProgress {
+    pub page: usize,
+    pub total: usize,
+    pub name: String,
+}
+
+/// Langue des messages du backend.
+#[derive(Debug, Clone, Serialize)]
+pub struct LocaleInfo {
+    /// Locale en vigueur (`fr-FR`, `en`…).
+    pub locale: String,
+    /// Locale de l'OS, utilisée sans réglage explicite.
+    pub system: String,
+    /// Catalogues livrés.
+    pub available: Vec<&'static str>,
+}
+
+fn locale_info() -> LocaleInfo {
+    LocaleInfo {
+        locale: i18n::current_locale(),
+        system: i18n::system_locale(),
+        available: i18n::available_locales(),
+    }
 }
 
 // ─── Helper : init modèle ─────────────────────────────────────────────────────
 
-fn ensure_model(app: &AppHandle) -> Result<(), String> {
+fn ensure_model(app: &AppHandle) -> AppResult<()> {
     let resource_dir = app
         .path()
         .resource_dir()
-        .map_err(|e| format!("Répertoire resources introuvable : {e}"))?;
+        .map_err(|e| AppError::Internal(tr!("app.resource_dir", error = e)))?;
 
     let model_path = resource_dir.join("model.onnx");
 
-    ml_engine::init_model(&model_path).map_err(|e| e.to_string())
+    Ok(ml_engine::init_model(&model_path)?)
+}
+
+/// Inférence sur une copie réduite, masque ramené à la résolution de `img`,
+/// en étapes mesurées (prétraitement, inférence, affinage).
+fn predict_mask_staged(
+    img: &image::DynamicImage,
+    refine_edges: bool,
+    ctx: &JobContext,
+) -> anyhow::Result<image::GrayImage> {
+    let (small, input) = ctx.stage(Stage::Preprocess, || {
+        let small = inference_copy(img);
+        let input = ml_engine::prepare_input(&small);
+        (small, input)
+    });
+    let mask = ctx.stage(Stage::Inference, || ml_engine::run_prepared(input?))?;
+    Ok(ctx.stage(Stage::Refine, || upscale_mask(&mask, &small, img, refine_edges)))
 }
 
 // ─── Commandes ────────────────────────────────────────────────────────────────
 
-/// Traite UNE image depuis son chemin fichier.
-/// Retourne un data URL base64 PNG.
+/// Traite UNE image depuis son chemin fichier (prioritaire sur les lots).
+/// Le résultat reste côté Rust : retourne son identifiant et un aperçu.
 #[tauri::command]
 pub async fn process_single_image(
     app: AppHandle,
     path: String,
     options: ProcessOptions,
-) -> Result<String, String> {
+) -> AppResult<ProcessedResult> {
     ensure_model(&app)?;
 
     let file_path = PathBuf::from(&path);
     if !file_path.exists() {
-        return Err(format!("Fichier introuvable : {path}"));
+        return Err(AppError::FileNotFound { path });
     }
 
-    let img = load_image(&file_path).map_err(|e| e.to_string())?;
-    let mask = ml_engine::run_inference(&img).map_err(|e| e.to_string())?;
-    let result = apply_mask(&img, &mask, &options.background);
+    let name = file_name(&file_path);
+    jobs::submit(JobKind::Single, name, Some(1), move |ctx| {
+        Ok(process_one_file(&file_path, PathBuf::new(), &options, ctx)?)
+    })
+    .wait()
+    .await
+}
 
-    encode_base64_png(&result).map_err(|e| e.to_string())
+/// Liste les images d'un dossier selon `scan` (voir scan.rs). Les chemins
+/// retournés se passent à `process_batch_images`, avec `root` = `report.root`
+/// pour que `save_batch_to_folder` recrée les sous-dossiers.
+#[tauri::command]
+pub async fn scan_folder(folder: String, scan: Option<ScanOptions>) -> AppResult<ScanReport> {
+    let scan = scan.unwrap_or_default();
+    tokio::task::spawn_blocking(move || Ok(scan::scan_folder(Path::new(&folder), &scan)?))
+        .await?
 }
 
-/// Traite PLUSIEURS images en batch.
-/// Émet l'événement `batch-progress` pour chaque image.
+/// Met PLUSIEURS images en file et retourne aussitôt l'identifiant du lot
+/// (voir `cancel_job`, `pause_job`). Émet `batch-progress` pour chaque image.
+/// Avec `root`, chaque résultat retient son sous-dossier relatif à `root`.
 #[tauri::command]
 pub async fn process_batch_images(
     app: AppHandle,
     paths: Vec<String>,
     options: ProcessOptions,
-) -> Result<(), String> {
+    root: Option<String>,
+) -> AppResult<JobId> {
     ensure_model(&app)?;
 
     let total = paths.len();
-    for (index, path_str) in paths.iter().enumerate() {
-        let file_path = PathBuf::from(path_str);
-        let name = file_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("inconnu")
-            .to_string();
-
-        let progress = match process_one_file(&file_path, &options) {
-            Ok(data_url) => BatchProgress {
-                index,
-                total,
-                name,
-                result_data_url: Some(data_url),
-                error: None,
-            },
-            Err(e) => BatchProgress {
-                index,
-                total,
-                name,
-                result_data_url: None,
-                error: Some(e.to_string()),
-            },
-        };
+    let name = tr!("jobs.batch", count = total);
+    let job = jobs::submit(JobKind::Batch, name, Some(total), move |ctx| {
+        for (index, path_str) in paths.iter().enumerate() {
+            ctx.checkpoint()?;
+            let file_path = PathBuf::from(path_str);
+            let name = file_name(&file_path);
+            let subfolder = root
+                .as_deref()
+                .map(|root| subfolder_of(&file_path, Path::new(root)))
+                .unwrap_or_default();
+
+            let (result, error) = match process_one_file(&file_path, subfolder, &options, ctx) {
+                Ok(result) => (Some(result), None),
+                Err(e) => match AppError::from(e) {
+                    AppError::Cancelled => return Err(AppError::Cancelled),
+                    e => (None, Some(e)),
+                },
+            };
+            let timings = ctx.take_timings();
+            let progress =
+                BatchProgress { job_id: ctx.id(), index, total, name, result, error, timings };
+
+            let _ = app.emit("batch-progress", &progress);
+            ctx.progress(index + 1, None);
+        }
+        Ok(())
+    });
+
+    Ok(job.id)
+}
+
+/// Dossier de `path` relatif à `root` (vide hors de `root`).
+fn subfolder_of(path: &Path, root: &Path) -> PathBuf {
+    path.parent()
+        .and_then(|parent| parent.strip_prefix(root).ok())
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// Détoure le fichier et range le résultat dans le store.
+fn process_one_file(
+    path: &Path,
+    subfolder: PathBuf,
+    options: &ProcessOptions,
+    ctx: &JobContext,
+) -> anyhow::Result<ProcessedResult> {
+    let (img, metadata) = ctx.stage(Stage::Decode, || {
+        load_image_with_options(path, options.max_megapixels, &options.svg)
+    })?;
+    ctx.checkpoint()?;
+    let mask = predict_mask_staged(&img, options.refine_edges, ctx)?;
+    ctx.checkpoint()?;
+    let (result, mask) = ctx.stage(Stage::Composite, || {
+        let result = apply_mask(&img, &mask, &options.background, options.compositing);
+        (result, refine_mask(&mask))
+    });
+
+    let stored = StoredResult {
+        name: file_name(path),
+        image: result,
+        mask,
+        metadata: options.output_metadata(metadata),
+        source: ResultSource::File(path.to_path_buf()),
+        max_megapixels: options.max_megapixels,
+        svg: options.svg.clone(),
+        subfolder,
+    };
+    Ok(results::store_result(stored, options.include_mask))
+}
+
+/// Détoure le fichier et écrit `{stem}_nobg.{ext}` (et `{stem}_mask.png`
+/// avec `include_mask`) dans `folder`, sans passer par le store ni écraser :
+/// `{stem}_nobg (2).{ext}` si besoin. Retourne le chemin du détourage.
+pub(crate) fn process_to_folder(
+    path: &Path,
+    options: &ProcessOptions,
+    format: &OutputFormat,
+    folder: &Path,
+    ctx: &JobContext,
+) -> anyhow::Result<PathBuf> {
+    let (img, metadata) = ctx.stage(Stage::Decode, || {
+        load_image_with_options(path, options.max_megapixels, &options.svg)
+    })?;
+    ctx.checkpoint()?;
+    let mask = predict_mask_staged(&img, options.refine_edges, ctx)?;
+    ctx.checkpoint()?;
+    let result = ctx.stage(Stage::Composite, || {
+        apply_mask(&img, &mask, &options.background, options.compositing)
+    });
+
+    let stem = output_stem(&file_name(path));
+    ctx.stage(Stage::Save, || {
+        let mut outputs = vec![(format!("{stem}_nobg"), format.extension())];
+        if options.include_mask {
+            outputs.push((format!("{stem}_mask"), "png"));
+        }
+        let dests = free_paths(folder, &outputs);
+        let written =
+            save_output(&result, &dests[0], format, &options.output_metadata(metadata))?;
+        if let Some(mask_dest) = dests.get(1) {
+            save_mask(&refine_mask(&mask), mask_dest, MaskBitDepth::Eight)?;
+        }
+        Ok(written)
+    })
+}
+
+/// Chemins `{base}.{ext}` de `folder`, numérotés ensemble (`{base} (2).{ext}`…)
+/// jusqu'à ce qu'aucun n'existe : le détourage et son masque gardent la même paire.
+fn free_paths(folder: &Path, outputs: &[(String, &str)]) -> Vec<PathBuf> {
+    let mut n = 1;
+    loop {
+        let paths: Vec<PathBuf> = outputs
+            .iter()
+            .map(|(base, extension)| match n {
+                1 => folder.join(format!("{base}.{extension}")),
+                n => folder.join(format!("{base} ({n}).{extension}")),
+            })
+            .collect();
+        if paths.iter().all(|p| !p.exists()) {
+            return paths;
+        }
+        n += 1;
+    }
+}
 
-        let _ = app.emit("batch-progress", &progress);
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| tr!("file.unknown"))
+}
+
+/// Détoure un GIF ou WebP animé image par image et écrit l'animation dans
+/// `dest_path` (extension corrigée selon le format). Émet `animation-progress`
+/// après l'inférence de chaque image. Retourne le chemin réellement écrit.
+#[tauri::command]
+pub async fn process_animated_image(
+    app: AppHandle,
+    path: String,
+    dest_path: String,
+    options: ProcessOptions,
+    animation: Option<AnimationOptions>,
+) -> AppResult<String> {
+    ensure_model(&app)?;
+
+    let file_path = PathBuf::from(&path);
+    if !file_path.exists() {
+        return Err(AppError::FileNotFound { path });
+    }
+    let name = file_name(&file_path);
+
+    let animation = animation.unwrap_or_default();
+    let format = match animation.format {
+        Some(format) => format,
+        None => AnimationFormat::sniff(&file_path)?,
+    };
+
+    jobs::submit(JobKind::Animation, name.clone(), None, move |ctx| {
+        let mut frames =
+            ctx.stage(Stage::Decode, || decode_frames(&file_path, options.max_megapixels))?;
+
+        let total = frames.len();
+        let mut masks = Vec::with_capacity(total);
+        for (index, frame) in frames.iter_mut().enumerate() {
+            ctx.checkpoint()?;
+            let img = image::DynamicImage::ImageRgba8(std::mem::take(&mut frame.image));
+            masks.push(predict_mask_staged(&img, options.refine_edges, ctx)?);
+            frame.image = img.into_rgba8();
+
+            let progress = AnimationProgress { frame: index, total, name: name.clone() };
+            let _ = app.emit("animation-progress", &progress);
+            ctx.progress(index + 1, Some(total));
+        }
+
+        // Le lissage porte sur les masques bruts ; apply_mask adoucit ensuite les bords
+        ctx.stage(Stage::Composite, || {
+            let masks = smooth_masks(&masks, animation.temporal_smoothing);
+            for (frame, mask) in frames.iter_mut().zip(&masks) {
+                let img = image::DynamicImage::ImageRgba8(std::mem::take(&mut frame.image));
+                frame.image =
+                    apply_mask(&img, mask, &options.background, options.compositing).into_rgba8();
+            }
+        });
+
+        let bytes = ctx.stage(Stage::Encode, || encode_animation(&frames, format, &animation))?;
+        let dest = format.with_extension(Path::new(&dest_path));
+        ctx.stage(Stage::Save, || std::fs::write(&dest, bytes))?;
+        Ok(dest.to_string_lossy().to_string())
+    })
+    .wait()
+    .await
+}
+
+/// Détoure une courte vidéo : dossier d'images numérotées ou fichier Y4M.
+/// Écrit `{nom}_nobg_00000.png`… dans `output_dir`, autre que le dossier
+/// d'images lu (plus Y4M / APNG selon `video`), avec des masques lissés dans
+/// le temps. Émet `video-progress` après chaque image ; `cancel_job` (ou
+/// `cancel_video_processing`) arrête entre deux images en gardant celles
+/// déjà écrites.
+#[tauri::command]
+pub async fn process_video_frames(
+    app: AppHandle,
+    input: String,
+    output_dir: String,
+    options: ProcessOptions,
+    video: Option<VideoOptions>,
+) -> AppResult<VideoSummary> {
+    ensure_model(&app)?;
+
+    let input_path = PathBuf::from(&input);
+    if !input_path.exists() {
+        return Err(AppError::FileNotFound { path: input });
     }
 
+    check_output_dir(&input_path, Path::new(&output_dir))?;
+
+    let video = video.unwrap_or_default();
+    let name = output_stem(&input);
+
+    jobs::submit(JobKind::Video, name.clone(), None, move |ctx| {
+        let mut source = FrameSource::open(&input_path, options.max_megapixels)?;
+        let fps = source.fps().unwrap_or_else(|| fps_ratio(video.fps));
+        let alpha = matches!(options.background, BackgroundColor::Transparent);
+        let mut writer = SequenceWriter::new(Path::new(&output_dir), &name, fps, &video, alpha)?;
+
+        let total = source.len_hint();
+        let mut smoother = TemporalSmoother::new(video.temporal_smoothing);
+        let (mut done, mut cancelled) = (false, false);
+        while !done {
+            match ctx.checkpoint() {
+                Ok(()) => {}
+                Err(AppError::Cancelled) => {
+                    cancelled = true;
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+
+            // Le modèle reste chargé (session unique) : seule l'inférence est relancée
+            let ready = match ctx.stage(Stage::Decode, || source.next_frame())? {
+                Some(img) => {
+                    let mask = predict_mask_staged(&img, options.refine_edges, ctx)?;
+                    smoother.push(img, mask)
+                }
+                None => {
+                    done = true;
+                    smoother.finish()
+                }
+            };
+
+            if let Some((img, mask)) = ready {
+                let result = ctx.stage(Stage::Composite, || {
+                    apply_mask(&img, &mask, &options.background, options.compositing)
+                });
+                ctx.stage(Stage::Save, || writer.write(&result))?;
+
+                let progress = VideoProgress {
+                    frame: writer.frames_written() - 1,
+                    total,
+                    name: name.clone(),
+                };
+                let _ = app.emit("video-progress", &progress);
+                ctx.progress(writer.frames_written(), total);
+            }
+        }
+
+        Ok(writer.finish(cancelled)?)
+    })
+    .wait()
+    .await
+}
+
+/// Demande l'arrêt des traitements vidéo (pris en compte entre deux images).
+/// Équivaut à `cancel_job` sur chacun d'eux.
+#[tauri::command]
+pub async fn cancel_video_processing() -> AppResult<()> {
+    jobs::cancel_kind(JobKind::Video);
     Ok(())
 }
 
-fn process_one_file(path: &Path, options: &ProcessOptions) -> anyhow::Result<String> {
-    let img = load_image(path)?;
-    let mask = ml_engine::run_inference(&img)?;
-    let result = apply_mask(&img, &mask, &options.background);
-    encode_base64_png(&result).map_err(Into::into)
+/// Annule un traitement : retiré de la file, ou arrêté entre deux unités
+/// (fichier, image, page) s'il a démarré.
+#[tauri::command]
+pub async fn cancel_job(id: JobId) -> AppResult<()> {
+    jobs::cancel(id)
+}
+
+/// Suspend un traitement entre deux unités ; les traitements prioritaires
+/// continuent de passer pendant la pause.
+#[tauri::command]
+pub async fn pause_job(id: JobId) -> AppResult<()> {
+    jobs::pause(id)
+}
+
+#[tauri::command]
+pub async fn resume_job(id: JobId) -> AppResult<()> {
+    jobs::resume(id)
+}
+
+/// Traitements en attente ou en cours.
+#[tauri::command]
+pub async fn list_jobs() -> AppResult<Vec<JobInfo>> {
+    Ok(jobs::list())
+}
+
+/// Détoure chaque page d'un TIFF multipage ou chaque taille d'une icône ICO.
+/// Selon `pages.output`, écrit un fichier multipage dans le format source ou
+/// `{nom}_p1.{ext}`… à côté de `dest_path`. Émet `page-progress` après chaque
+/// page. Retourne les chemins réellement écrits.
+#[tauri::command]
+pub async fn process_multipage_image(
+    app: AppHandle,
+    path: String,
+    dest_path: String,
+    options: ProcessOptions,
+    pages: Option<PageOptions>,
+) -> AppResult<Vec<String>> {
+    ensure_model(&app)?;
+
+    let file_path = PathBuf::from(&path);
+    if !file_path.exists() {
+        return Err(AppError::FileNotFound { path });
+    }
+    let name = file_name(&file_path);
+
+    let pages = pages.unwrap_or_default();
+
+    jobs::submit(JobKind::Pages, name.clone(), None, move |ctx| {
+        let (container, sources) =
+            ctx.stage(Stage::Decode, || load_pages(&file_path, options.max_megapixels))?;
+
+        let total = sources.len();
+        let mut results = Vec::with_capacity(total);
+        for (index, (img, metadata)) in sources.into_iter().enumerate() {
+            ctx.checkpoint()?;
+            let mask = predict_mask_staged(&img, options.refine_edges, ctx)?;
+            let result = ctx.stage(Stage::Composite, || {
+                apply_mask(&img, &mask, &options.background, options.compositing)
+            });
+            results.push((result, options.output_metadata(metadata)));
+
+            let progress = PageProgress { page: index, total, name: name.clone() };
+            let _ = app.emit("page-progress", &progress);
+            ctx.progress(index + 1, Some(total));
+        }
+
+        let dest = Path::new(&dest_path);
+        match pages.output {
+            PageOutput::MultiPage => {
+                let bytes = ctx.stage(Stage::Encode, || encode_multipage(container, &results))?;
+                let dest = dest.with_extension(container.extension());
+                ctx.stage(Stage::Save, || std::fs::write(&dest, bytes))?;
+                Ok(vec![dest.to_string_lossy().to_string()])
+            }
+            // Encodage et écriture page par page : une seule étape `Save` chacune
+            PageOutput::Separate => results
+                .iter()
+                .enumerate()
+                .map(|(index, (img, metadata))| {
+                    let path = page_path(dest, index, &pages.format);
+                    ctx.stage(Stage::Save, || save_output(img, &path, &pages.format, metadata))
+                        .map(|p| p.to_string_lossy().to_string())
+                        .map_err(AppError::from)
+                })
+                .collect(),
+        }
+    })
+    .wait()
+    .await
 }
 
 /// Lit l'image depuis le presse-papier et la traite.
@@ -131,29 +585,30 @@ fn process_one_file(path: &Path, options: &ProcessOptions) -> anyhow::Result<Str
 pub async fn process_clipboard_image(
     app: AppHandle,
     options: ProcessOptions,
-) -> Result<String, String> {
+) -> AppResult<ProcessedResult> {
     ensure_model(&app)?;
 
-    let bytes = tokio::task::spawn_blocking(|| -> Result<Vec<u8>, String> {
+    let bytes = tokio::task::spawn_blocking(|| -> AppResult<Vec<u8>> {
         let mut clipboard = arboard::Clipboard::new()
-            .map_err(|e| format!("Clipboard init : {e}"))?;
+            .map_err(|e| AppError::Clipboard(e.to_string()))?;
 
-        let img_data = clipboard
-            .get_image()
-            .map_err(|e| format!("Pas d'image dans le presse-papier : {e}"))?;
+        let img_data = clipboard.get_image().map_err(|e| match e {
+            arboard::Error::ContentNotAvailable => AppError::ClipboardEmpty,
+            e => AppError::Clipboard(e.to_string()),
+        })?;
 
         let rgba = image::RgbaImage::from_raw(
             img_data.width as u32,
             img_data.height as u32,
             img_data.bytes.into_owned(),
         )
-        .ok_or_else(|| "Buffer clipboard invalide".to_string())?;
+        .ok_or_else(|| AppError::Clipboard(tr!("clipboard.invalid_buffer")))?;
 
         let dyn_img = image::DynamicImage::ImageRgba8(rgba);
-        encode_png(&dyn_img).map_err(|e| e.to_string())
+        Ok(encode_png(&dyn_img)?)
     })
-    .await
-    .map_err(|e| e.to_string())??;
+    .await??;
+    let bytes = Arc::new(bytes);
 
     // Mémorise les bytes originaux pour retraitement si le fond change
     {
@@ -161,11 +616,7 @@ pub async fn process_clipboard_image(
         *store = Some(bytes.clone());
     }
 
-    let img = load_image_from_bytes(&bytes).map_err(|e| e.to_string())?;
-    let mask = ml_engine::run_inference(&img).map_err(|e| e.to_string())?;
-    let result = apply_mask(&img, &mask, &options.background);
-
-    encode_base64_png(&result).map_err(|e| e.to_string())
+    submit_clipboard(bytes, options).await
 }
 
 /// Retraite l'image clipboard mémorisée avec un nouveau fond (sans relire le presse-papier).
@@ -173,98 +624,412 @@ pub async fn process_clipboard_image(
 pub async fn reprocess_clipboard_image(
     app: AppHandle,
     options: ProcessOptions,
-) -> Result<String, String> {
+) -> AppResult<ProcessedResult> {
     ensure_model(&app)?;
 
     let bytes = {
         let store = clipboard_store().lock().unwrap_or_else(|e| e.into_inner());
-        store.clone().ok_or_else(|| "Aucune image clipboard mémorisée".to_string())?
+        store.clone().ok_or(AppError::ClipboardEmpty)?
     };
 
-    let img = load_image_from_bytes(&bytes).map_err(|e| e.to_string())?;
-    let mask = ml_engine::run_inference(&img).map_err(|e| e.to_string())?;
-    let result = apply_mask(&img, &mask, &options.background);
+    submit_clipboard(bytes, options).await
+}
 
-    encode_base64_png(&result).map_err(|e| e.to_string())
+async fn submit_clipboard(
+    bytes: Arc<Vec<u8>>,
+    options: ProcessOptions,
+) -> AppResult<ProcessedResult> {
+    jobs::submit(JobKind::Clipboard, tr!("clipboard.name"), Some(1), move |ctx| {
+        Ok(process_clipboard_bytes(bytes, &options, ctx)?)
+    })
+    .wait()
+    .await
 }
 
-/// Copie un résultat PNG (base64 data URL) dans le presse-papier.
-#[tauri::command]
-pub async fn copy_result_to_clipboard(data_url: String) -> Result<(), String> {
-    let b64 = data_url
-        .strip_prefix("data:image/png;base64,")
-        .unwrap_or(&data_url);
+fn process_clipboard_bytes(
+    bytes: Arc<Vec<u8>>,
+    options: &ProcessOptions,
+    ctx: &JobContext,
+) -> anyhow::Result<ProcessedResult> {
+    let img = ctx.stage(Stage::Decode, || {
+        load_image_from_bytes(&bytes, options.max_megapixels, &options.svg)
+    })?;
+    ctx.checkpoint()?;
+    let mask = predict_mask_staged(&img, options.refine_edges, ctx)?;
+    ctx.checkpoint()?;
+    let (result, mask) = ctx.stage(Stage::Composite, || {
+        let result = apply_mask(&img, &mask, &options.background, options.compositing);
+        (result, refine_mask(&mask))
+    });
 
-    let png_bytes = STANDARD.decode(b64).map_err(|e| e.to_string())?;
+    // Le presse-papier ne transporte que des pixels RGBA : pas de métadonnées
+    let stored = StoredResult {
+        name: tr!("clipboard.name"),
+        image: result,
+        mask,
+        metadata: ImageMetadata::default(),
+        source: ResultSource::Bytes(bytes),
+        max_megapixels: options.max_megapixels,
+        svg: options.svg.clone(),
+        subfolder: PathBuf::new(),
+    };
+    Ok(results::store_result(stored, options.include_mask))
+}
 
-    let img = image::load_from_memory(&png_bytes).map_err(|e| e.to_string())?;
-    let rgba = img.to_rgba8();
-    let (w, h) = rgba.dimensions();
+/// Copie un résultat du store dans le presse-papier.
+#[tauri::command]
+pub async fn copy_result_to_clipboard(id: ResultId) -> AppResult<()> {
+    let entry = results::get(id)?;
 
-    tokio::task::spawn_blocking(move || -> Result<(), String> {
+    tokio::task::spawn_blocking(move || -> AppResult<()> {
+        let rgba = entry.image.to_rgba8();
+        let (w, h) = rgba.dimensions();
         let mut clipboard = arboard::Clipboard::new()
-            .map_err(|e| format!("Clipboard init : {e}"))?;
+            .map_err(|e| AppError::Clipboard(e.to_string()))?;
 
         let img_data = arboard::ImageData {
             width: w as usize,
             height: h as usize,
             bytes: std::borrow::Cow::Owned(rgba.into_raw()),
         };
-        clipboard.set_image(img_data).map_err(|e| e.to_string())
+        clipboard
+            .set_image(img_data)
+            .map_err(|e| AppError::Clipboard(e.to_string()))
     })
-    .await
-    .map_err(|e| e.to_string())?
+    .await?
 }
 
-/// Sauvegarde un résultat PNG (base64 data URL) vers un fichier.
+/// Sauvegarde un résultat du store vers un fichier, dans le format demandé
+/// (PNG par défaut), depuis l'image en pleine qualité. Retourne le chemin
+/// réellement écrit, l'extension étant corrigée si elle ne correspond pas au
+/// format. Avec `layers`, écrit plutôt un fichier en calques (.ora / TIFF / PSD)
+/// construit depuis l'original et le masque du résultat.
 #[tauri::command]
-pub async fn save_result_to_file(data_url: String, dest_path: String) -> Result<(), String> {
-    let b64 = data_url
-        .strip_prefix("data:image/png;base64,")
-        .unwrap_or(&data_url);
+pub async fn save_result_to_file(
+    id: ResultId,
+    dest_path: String,
+    format: Option<OutputFormat>,
+    layers: Option<LayerOptions>,
+) -> AppResult<String> {
+    let entry = results::get(id)?;
+    let dest = Path::new(&dest_path);
 
-    let png_bytes = STANDARD.decode(b64).map_err(|e| e.to_string())?;
+    let written = match layers {
+        Some(layers) => export_layers(&entry, &layers, dest)?,
+        None => save_output(&entry.image, dest, &format.unwrap_or_default(), &entry.metadata)?,
+    };
+    Ok(written.to_string_lossy().to_string())
+}
 
-    let img = image::load_from_memory(&png_bytes).map_err(|e| e.to_string())?;
+/// Original relu depuis sa source + masque du résultat → calques.
+fn export_layers(entry: &StoredResult, options: &LayerOptions, dest: &Path) -> AppResult<PathBuf> {
+    let (img, mask) = load_source(entry)?;
+    Ok(save_layers(&img, &mask, options, &entry.metadata, dest)?)
+}
 
-    save_png(&img, Path::new(&dest_path)).map_err(|e| e.to_string())
+/// Relit l'original d'un résultat depuis sa source, avec les réglages du
+/// traitement, et le masque du résultat à sa taille.
+fn load_source(
+    entry: &StoredResult,
+) -> AppResult<(image::DynamicImage, std::borrow::Cow<'_, image::GrayImage>)> {
+    let img = match &entry.source {
+        ResultSource::File(path) => {
+            load_image_with_options(path, entry.max_megapixels, &entry.svg)?.0
+        }
+        ResultSource::Bytes(bytes) => {
+            load_image_from_bytes(bytes, entry.max_megapixels, &entry.svg)?
+        }
+    };
+
+    // Source modifiée sur le disque depuis le traitement : masque ramené à sa taille
+    let mask = if entry.mask.dimensions() == (img.width(), img.height()) {
+        std::borrow::Cow::Borrowed(&entry.mask)
+    } else {
+        std::borrow::Cow::Owned(image::imageops::resize(
+            &entry.mask,
+            img.width(),
+            img.height(),
+            image::imageops::FilterType::Triangle,
+        ))
+    };
+    Ok((img, mask))
 }
 
-/// Sauvegarde plusieurs résultats dans un dossier.
+fn output_stem(name: &str) -> String {
+    PathBuf::from(name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output")
+        .to_string()
+}
+
+/// Sauvegarde plusieurs résultats du store dans un dossier (`{stem}_nobg.{ext}`).
+/// `masks` (optionnel) liste les résultats dont le masque affiné est écrit
+/// en `{stem}_mask.png` à côté du détourage.
+/// Avec `layers`, chaque résultat devient `{stem}_layers.{ora|tiff|psd}`.
+/// Les résultats d'un dossier scanné sont rangés dans leur sous-dossier.
+/// Rien n'est écrasé : `{stem}_nobg (2).{ext}` si le nom est pris (deux
+/// sources `photo.jpg` et `photo.png`, export précédent).
+/// Passe par la file de traitements (annulable, progression `job-event`) ;
+/// un identifiant libéré fait échouer l'appel avant toute écriture.
 #[tauri::command]
 pub async fn save_batch_to_folder(
-    items: Vec<(String, String)>, // (nom_fichier, data_url)
+    ids: Vec<ResultId>,
     folder: String,
-) -> Result<(), String> {
+    masks: Option<Vec<ResultId>>,
+    format: Option<OutputFormat>,
+    layers: Option<LayerOptions>,
+) -> AppResult<()> {
+    let masks = masks.unwrap_or_default();
+    results::check(&ids)?;
+    results::check(&masks)?;
+
     let folder_path = PathBuf::from(&folder);
-    std::fs::create_dir_all(&folder_path).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&folder_path)?;
+    let format = format.unwrap_or_default();
+    // Masques seuls : résultats de `masks` absents de `ids`
+    let mask_only: Vec<ResultId> = masks.iter().copied().filter(|id| !ids.contains(id)).collect();
+    let total = ids.len() + mask_only.len();
+
+    let name = tr!("jobs.save", count = total);
+    jobs::submit(JobKind::Batch, name, Some(total), move |ctx| {
+        for (index, &id) in ids.iter().chain(&mask_only).enumerate() {
+            ctx.checkpoint()?;
+            let entry = results::get(id)?;
+            let stem = output_stem(&entry.name);
+            let dir = output_dir(&folder_path, &entry)?;
+
+            let mut outputs = Vec::new();
+            if index < ids.len() {
+                outputs.push(match &layers {
+                    Some(layers) => (format!("{stem}_layers"), layers.format.extension()),
+                    None => (format!("{stem}_nobg"), format.extension()),
+                });
+            }
+            if masks.contains(&id) {
+                outputs.push((format!("{stem}_mask"), "png"));
+            }
+            let mut dests = free_paths(&dir, &outputs).into_iter();
+            let result_dest = if index < ids.len() { dests.next() } else { None };
+            let mask_dest = dests.next();
+
+            ctx.stage(Stage::Save, || -> AppResult<()> {
+                if let Some(dest) = &result_dest {
+                    match &layers {
+                        Some(layers) => export_layers(&entry, layers, dest)?,
+                        None => save_output(&entry.image, dest, &format, &entry.metadata)?,
+                    };
+                }
+                if let Some(dest) = &mask_dest {
+                    save_mask(&entry.mask, dest, MaskBitDepth::Eight)?;
+                }
+                Ok(())
+            })?;
+            ctx.progress(index + 1, None);
+        }
+        Ok(())
+    })
+    .wait()
+    .await
+}
+
+/// `folder` + sous-dossier du résultat, créé au besoin.
+fn output_dir(folder: &Path, entry: &StoredResult) -> AppResult<PathBuf> {
+    if entry.subfolder.as_os_str().is_empty() {
+        return Ok(folder.to_path_buf());
+    }
+    let dir = folder.join(&entry.subfolder);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Libère des résultats du store (éléments retirés de la liste).
+#[tauri::command]
+pub async fn release_results(ids: Vec<ResultId>) -> AppResult<()> {
+    results::release(&ids);
+    Ok(())
+}
 
-    for (name, data_url) in items {
-        let stem = PathBuf::from(&name)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("output")
-            .to_string();
+/// Libère tous les résultats (réinitialisation de l'interface).
+#[tauri::command]
+pub async fn clear_results() -> AppResult<()> {
+    results::clear();
+    Ok(())
+}
 
-        let dest = folder_path.join(format!("{stem}_nobg.png"));
-        save_result_to_file(data_url, dest.to_string_lossy().to_string()).await?;
+/// Masque à exporter : celui, affiné, du résultat `id` s'il est donné (pas
+/// d'inférence), sinon celui prédit pour `path` avec les réglages `process`
+/// (par défaut sans). L'original, avec ses métadonnées, n'est chargé que si
+/// `with_image`.
+async fn export_source(
+    app: &AppHandle,
+    path: Option<String>,
+    id: Option<ResultId>,
+    process: Option<ProcessOptions>,
+    with_image: bool,
+) -> AppResult<(Option<(image::DynamicImage, ImageMetadata)>, image::GrayImage)> {
+    if let Some(id) = id {
+        let entry = results::get(id)?;
+        let name = entry.name.clone();
+        return jobs::submit(JobKind::Single, name, Some(1), move |ctx| {
+            if !with_image {
+                return Ok((None, entry.mask.clone()));
+            }
+            let (img, mask) = ctx.stage(Stage::Decode, || load_source(&entry))?;
+            Ok((Some((img, entry.metadata.clone())), mask.into_owned()))
+        })
+        .wait()
+        .await;
     }
 
+    let path = path.ok_or_else(|| AppError::InvalidInput(tr!("mask.no_source")))?;
+    ensure_model(app)?;
+    let file_path = PathBuf::from(&path);
+    if !file_path.exists() {
+        return Err(AppError::FileNotFound { path });
+    }
+
+    let (max_megapixels, refine_edges) =
+        process.as_ref().map_or((DEFAULT_MAX_MEGAPIXELS, false), |o| {
+            (o.max_megapixels, o.refine_edges)
+        });
+    let svg = process.map(|o| o.svg).unwrap_or_default();
+    let name = file_name(&file_path);
+    jobs::submit(JobKind::Single, name, Some(1), move |ctx| {
+        let (img, metadata) = ctx.stage(Stage::Decode, || {
+            load_image_with_options(&file_path, max_megapixels, &svg)
+        })?;
+        ctx.checkpoint()?;
+        let mask = predict_mask_staged(&img, refine_edges, ctx)?;
+        let mask = ctx.stage(Stage::Composite, || refine_mask(&mask));
+        Ok((with_image.then_some((img, metadata)), mask))
+    })
+    .wait()
+    .await
+}
+
+/// Exporte un masque affiné (voir `export_source`) en PNG/TIFF niveaux de gris
+/// (8 ou 16 bits). Le format est déduit de l'extension de `dest_path`.
+#[tauri::command]
+pub async fn export_mask(
+    app: AppHandle,
+    path: Option<String>,
+    id: Option<ResultId>,
+    dest_path: String,
+    bit_depth: Option<MaskBitDepth>,
+    process: Option<ProcessOptions>,
+) -> AppResult<()> {
+    let (_, mask) = export_source(&app, path, id, process, false).await?;
+    save_mask(&mask, Path::new(&dest_path), bit_depth.unwrap_or_default())?;
     Ok(())
 }
 
+/// Exporte le contour d'un masque (voir `export_source`) en SVG (clipPath),
+/// avec l'image originale intégrée et découpée si `options.embed_image`.
+#[tauri::command]
+pub async fn export_mask_svg(
+    app: AppHandle,
+    path: Option<String>,
+    id: Option<ResultId>,
+    dest_path: String,
+    options: Option<SvgExportOptions>,
+    process: Option<ProcessOptions>,
+) -> AppResult<()> {
+    let options = options.unwrap_or_default();
+    let (img, mask) = export_source(&app, path, id, process, options.embed_image).await?;
+
+    let raster = img.as_ref().map(|(img, metadata)| (img, metadata));
+    Ok(save_mask_svg(&mask, raster, &options, Path::new(&dest_path))?)
+}
+
+/// Recompose une image avec un masque retouché à l'extérieur (pas d'inférence).
+/// Le résultat, avec ce masque, est rangé dans le store.
+#[tauri::command]
+pub async fn import_mask(
+    path: String,
+    mask_path: String,
+    options: ProcessOptions,
+) -> AppResult<ProcessedResult> {
+    let file_path = PathBuf::from(&path);
+    if !file_path.exists() {
+        return Err(AppError::FileNotFound { path });
+    }
+
+    let (img, metadata) =
+        load_image_with_options(&file_path, options.max_megapixels, &options.svg)?;
+    // Masque exporté déjà affiné : le flouter de nouveau adoucirait le bord à
+    // chaque aller-retour
+    let mask =
+        load_mask(Path::new(&mask_path), img.width(), img.height(), options.max_megapixels)?;
+    let result = apply_refined_mask(&img, &mask, &options.background, options.compositing);
+
+    let stored = StoredResult {
+        name: file_name(&file_path),
+        image: result,
+        mask,
+        metadata: options.output_metadata(metadata),
+        source: ResultSource::File(file_path),
+        max_megapixels: options.max_megapixels,
+        svg: options.svg.clone(),
+        subfolder: PathBuf::new(),
+    };
+    Ok(results::store_result(stored, options.include_mask))
+}
+
+// ─── Dossiers surveillés ──────────────────────────────────────────────────────
+
+/// Surveille un dossier (voir watch.rs) ; remplace la surveillance existante
+/// du même dossier. Émet `watch-event`.
+#[tauri::command]
+pub async fn start_watch(app: AppHandle, config: WatchConfig) -> AppResult<WatchInfo> {
+    ensure_model(&app)?;
+    Ok(watch::start(config)?)
+}
+
+/// Arrête la surveillance ; les fichiers pas encore traités restent en place.
+#[tauri::command]
+pub async fn stop_watch(input: String) -> AppResult<()> {
+    watch::stop(Path::new(&input))
+}
+
+#[tauri::command]
+pub async fn list_watches() -> AppResult<Vec<WatchInfo>> {
+    Ok(watch::list())
+}
+
+/// Dernières entrées (100 par défaut) du journal d'un dossier surveillé,
+/// même arrêté.
+#[tauri::command]
+pub async fn read_watch_log(input: String, limit: Option<usize>) -> AppResult<Vec<WatchLogEntry>> {
+    Ok(watch::read_log(Path::new(&input), limit.unwrap_or(100))?)
+}
+
 /// Vérifie que le modèle est présent.
 #[tauri::command]
-pub async fn check_model(app: AppHandle) -> Result<String, String> {
+pub async fn check_model(app: AppHandle) -> AppResult<String> {
     let resource_dir = app
         .path()
         .resource_dir()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| AppError::Internal(tr!("app.resource_dir", error = e)))?;
 
     let model_path = resource_dir.join("model.onnx");
     if model_path.exists() {
         Ok(model_path.to_string_lossy().to_string())
     } else {
-        Err("Modèle RMBG-1.4 introuvable. Placez model.onnx dans resources/".to_string())
+        Err(AppError::ModelNotFound { path: model_path.to_string_lossy().to_string() })
     }
 }
+
+/// Langue des messages du backend (erreurs, noms de calques…).
+#[tauri::command]
+pub async fn get_locale() -> AppResult<LocaleInfo> {
+    Ok(locale_info())
+}
+
+/// Choisit la langue depuis les réglages ; `None` suit la locale de l'OS.
+/// Une langue sans catalogue retombe sur l'anglais, clé par clé.
+#[tauri::command]
+pub async fn set_locale(locale: Option<String>) -> AppResult<LocaleInfo> {
+    i18n::set_locale(locale.as_deref());
+    Ok(locale_info())
+}
@@ -0,0 +1,177 @@
+//! error.rs — Erreur commune aux commandes, au chargement des images et au
+//! moteur ML. Sérialisée en `{ code, message, details }` : le frontend et les
+//! scripts réagissent au `code` (stable), l'utilisateur lit le `message`,
+//! traduit dans la langue courante.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::tr;
+
+/// Message dans la langue courante (voir `localized`).
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{}", self.localized())]
+pub enum AppError {
+    ModelNotFound { path: String },
+    ModelNotLoaded,
+    Inference(String),
+    FileNotFound { path: String },
+    UnsupportedFormat { detected: String },
+    ImageTooLarge { width: u32, height: u32, megapixels: f64, max_megapixels: f32 },
+    /// Résultat libéré (réinitialisation, remplacement) ou inconnu.
+    ResultNotFound { id: u64 },
+    /// Traitement annulé (voir jobs.rs).
+    Cancelled,
+    /// Traitement terminé ou inconnu.
+    JobNotFound { id: u64 },
+    ClipboardEmpty,
+    Clipboard(String),
+    Decode(String),
+    Encode(String),
+    InvalidInput(String),
+    Io(String),
+    Internal(String),
+}
+
+impl AppError {
+    /// Code stable, à comparer côté frontend (jamais traduit ni renommé).
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::ModelNotFound { .. } => "MODEL_NOT_FOUND",
+            AppError::ModelNotLoaded => "MODEL_NOT_LOADED",
+            AppError::Inference(_) => "INFERENCE_FAILED",
+            AppError::FileNotFound { .. } => "FILE_NOT_FOUND",
+            AppError::UnsupportedFormat { .. } => "UNSUPPORTED_FORMAT",
+            AppError::ImageTooLarge { .. } => "IMAGE_TOO_LARGE",
+            AppError::ResultNotFound { .. } => "RESULT_NOT_FOUND",
+            AppError::Cancelled => "CANCELLED",
+            AppError::JobNotFound { .. } => "JOB_NOT_FOUND",
+            AppError::ClipboardEmpty => "CLIPBOARD_EMPTY",
+            AppError::Clipboard(_) => "CLIPBOARD_ERROR",
+            AppError::Decode(_) => "DECODE_FAILED",
+            AppError::Encode(_) => "ENCODE_FAILED",
+            AppError::InvalidInput(_) => "INVALID_INPUT",
+            AppError::Io(_) => "IO_ERROR",
+            AppError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    /// Échec d'accès à `path` : `FileNotFound` s'il n'existe pas, sinon `Io`
+    /// avec le message déjà traduit.
+    pub fn io(path: &Path, e: &std::io::Error, message: String) -> Self {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            AppError::FileNotFound { path: path.display().to_string() }
+        } else {
+            AppError::Io(message)
+        }
+    }
+
+    /// Champs exploitables par programme (chemin, format détecté, tailles…).
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            AppError::ModelNotFound { path } | AppError::FileNotFound { path } => {
+                Some(json!({ "path": path }))
+            }
+            AppError::UnsupportedFormat { detected } => Some(json!({ "detected": detected })),
+            AppError::ResultNotFound { id } | AppError::JobNotFound { id } => {
+                Some(json!({ "id": id }))
+            }
+            AppError::ImageTooLarge { width, height, megapixels, max_megapixels } => Some(json!({
+                "width": width,
+                "height": height,
+                "megapixels": megapixels,
+                "max_megapixels": max_megapixels,
+            })),
+            _ => None,
+        }
+    }
+
+    /// Message dans la langue courante (voir i18n.rs) ; les détails entre
+    /// parenthèses sont déjà localisés par le module qui les a produits.
+    fn localized(&self) -> String {
+        match self {
+            AppError::ModelNotFound { path } => tr!("error.model_not_found", path = path),
+            AppError::ModelNotLoaded => tr!("error.model_not_loaded"),
+            AppError::Inference(detail) => tr!("error.inference", detail = detail),
+            AppError::FileNotFound { path } => tr!("error.file_not_found", path = path),
+            AppError::UnsupportedFormat { detected } => {
+                tr!("error.unsupported_format", detected = detected)
+            }
+            AppError::ImageTooLarge { width, height, megapixels, max_megapixels } => tr!(
+                "error.image_too_large",
+                width = width,
+                height = height,
+                megapixels = format!("{megapixels:.1}"),
+                max = max_megapixels,
+            ),
+            AppError::ResultNotFound { id } => tr!("error.result_not_found", id = id),
+            AppError::Cancelled => tr!("error.cancelled"),
+            AppError::JobNotFound { id } => tr!("error.job_not_found", id = id),
+            AppError::ClipboardEmpty => tr!("error.clipboard_empty"),
+            AppError::Clipboard(detail) => tr!("error.clipboard", detail = detail),
+            AppError::Decode(detail) => tr!("error.decode", detail = detail),
+            AppError::Encode(detail) => tr!("error.encode", detail = detail),
+            AppError::InvalidInput(detail) => tr!("error.invalid_input", detail = detail),
+            AppError::Io(detail) => tr!("error.io", detail = detail),
+            AppError::Internal(detail) => detail.clone(),
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+/// Les modules internes restent en `anyhow` : une `AppError` qui y a été
+/// placée est retrouvée telle quelle, le reste devient `Internal`.
+impl From<anyhow::Error> for AppError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<AppError>() {
+            Ok(app) => app,
+            Err(e) => match e.downcast_ref::<std::io::Error>() {
+                Some(io) => AppError::Io(io.to_string()),
+                None => AppError::Internal(e.to_string()),
+            },
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        AppError::Internal(tr!("error.task_aborted", error = e))
+    }
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_survive_anyhow() {
+        let path = Path::new("/introuvable/image.png");
+        let missing = std::fs::read(path).unwrap_err();
+        let e: anyhow::Error = AppError::io(path, &missing, String::new()).into();
+        assert_eq!(AppError::from(e).code(), "FILE_NOT_FOUND");
+
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(AppError::io(Path::new("a.png"), &denied, String::new()).code(), "IO_ERROR");
+
+        let e: anyhow::Error = AppError::Decode("GIF".into()).into();
+        assert_eq!(AppError::from(e).code(), "DECODE_FAILED");
+    }
+}
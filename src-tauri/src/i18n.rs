@@ -0,0 +1,122 @@
+//! i18n.rs — Catalogue des messages destinés à l'utilisateur (erreurs, noms
+//! par défaut, noms de calques). Un fichier JSON plat par langue dans
+//! `locales/`, embarqué à la compilation ; les messages utilisent des
+//! paramètres nommés `{nom}`.
+//!
+//! Chaîne de repli pour une clé : locale exacte (`fr-CA`), sa langue (`fr`),
+//! l'anglais, le français (langue source), puis la clé elle-même.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::RwLock;
+
+/// Langue de dernier recours avant la langue source.
+pub const FALLBACK_LOCALE: &str = "en";
+const SOURCE_LOCALE: &str = "fr";
+
+/// Catalogues livrés : ajouter une langue = un fichier JSON et une ligne ici.
+const CATALOG_SOURCES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("fr", include_str!("../locales/fr.json")),
+];
+
+type Catalog = HashMap<String, String>;
+
+static CATALOGS: Lazy<HashMap<&'static str, Catalog>> = Lazy::new(|| {
+    CATALOG_SOURCES
+        .iter()
+        .map(|(locale, json)| {
+            let catalog = serde_json::from_str(json)
+                .unwrap_or_else(|e| panic!("locales/{locale}.json invalide : {e}"));
+            (*locale, catalog)
+        })
+        .collect()
+});
+
+/// Locale courante, normalisée (`fr-CA`, `en`…) ; celle du système au départ.
+static LOCALE: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new(system_locale()));
+
+/// Locale de l'OS, ou `FALLBACK_LOCALE` si elle est introuvable.
+pub fn system_locale() -> String {
+    sys_locale::get_locale()
+        .and_then(|l| normalize(&l))
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_string())
+}
+
+/// `fr_FR.UTF-8`, `fr-fr@euro` → `fr-FR` ; `None` si ce n'est pas une locale.
+pub fn normalize(locale: &str) -> Option<String> {
+    let tag = locale.split(['.', '@']).next()?.trim().replace('_', "-");
+    let mut parts = tag.split('-').filter(|p| !p.is_empty());
+    let language = parts.next()?;
+    if !language.chars().all(|c| c.is_ascii_alphabetic()) || !(2..=3).contains(&language.len()) {
+        return None;
+    }
+    let mut normalized = language.to_ascii_lowercase();
+    if let Some(region) = parts.next() {
+        normalized.push('-');
+        normalized.push_str(&region.to_ascii_uppercase());
+    }
+    Some(normalized)
+}
+
+pub fn current_locale() -> String {
+    LOCALE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Change la langue des messages ; `None` (ou une locale illisible) revient
+/// à celle du système. Retourne la locale retenue.
+pub fn set_locale(locale: Option<&str>) -> String {
+    let locale = locale.and_then(normalize).unwrap_or_else(system_locale);
+    *LOCALE.write().unwrap_or_else(|e| e.into_inner()) = locale.clone();
+    locale
+}
+
+/// Langues livrées (codes des catalogues).
+pub fn available_locales() -> Vec<&'static str> {
+    CATALOG_SOURCES.iter().map(|(locale, _)| *locale).collect()
+}
+
+/// Locales consultées pour `locale`, dans l'ordre, sans doublon.
+fn fallback_chain(locale: &str) -> Vec<String> {
+    let mut chain = vec![locale.to_string()];
+    if let Some((language, _)) = locale.split_once('-') {
+        chain.push(language.to_string());
+    }
+    for locale in [FALLBACK_LOCALE, SOURCE_LOCALE] {
+        if !chain.iter().any(|l| l == locale) {
+            chain.push(locale.to_string());
+        }
+    }
+    chain
+}
+
+/// Message `key` dans la langue courante, paramètres `{nom}` remplacés.
+/// Préférer la macro `tr!`.
+pub fn translate(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let template = fallback_chain(&current_locale())
+        .iter()
+        .find_map(|locale| CATALOGS.get(locale.as_str())?.get(key))
+        .map(String::as_str)
+        .unwrap_or(key);
+
+    let mut message = template.to_string();
+    for (name, value) in args {
+        message = message.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    message
+}
+
+/// `tr!("cle")` ou `tr!("cle", path = path.display(), error = e)`.
+#[macro_export]
+macro_rules! tr {
+    ($key:literal) => {
+        $crate::i18n::translate($key, &[])
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::translate(
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
@@ -1,92 +1,772 @@
 /// image_processor.rs — Chargement, manipulation et encodage des images.
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use base64::{engine::general_purpose::STANDARD, Engine};
-use image::{DynamicImage, GrayImage, Luma, RgbaImage};
-use std::io::Cursor;
+use image::{
+    metadata::Orientation, DynamicImage, GrayImage, ImageBuffer, ImageDecoder, ImageEncoder,
+    Rgba, RgbaImage,
+};
+use rayon::prelude::*;
+use std::io::{BufRead, Cursor, Read, Seek};
 use std::path::Path;
 
+use crate::error::AppError;
+use crate::tr;
+
 // ─── Formats supportés ────────────────────────────────────────────────────────
 
 const SUPPORTED_EXTENSIONS: &[&str] = &[
     "png", "jpg", "jpeg", "webp", "svg",
     "bmp", "gif", "tif", "tiff", "ico",
     "tga", "pnm", "pbm", "pgm", "ppm",
-    "hdr", "ff", "qoi",
+    "hdr", "exr", "ff", "qoi",
+];
+
+/// Formats décodés uniquement si la feature cargo correspondante est active.
+const FEATURE_EXTENSIONS: &[&str] = &[
+    #[cfg(feature = "jxl")]
+    "jxl",
+    #[cfg(feature = "avif-decode")]
+    "avif",
 ];
 
+/// Contenu décodable d'après sa signature ; l'extension ne sert que pour
+/// les formats sans signature (TGA) ou si le fichier est illisible.
 pub fn is_supported(path: &Path) -> bool {
+    match sniff_file(path) {
+        Ok(SniffedFormat::Unknown) | Err(_) => extension_supported(path),
+        Ok(format) => format.is_decodable(),
+    }
+}
+
+/// Dimensions lues dans l'en-tête, sans décoder les pixels (orientation EXIF
+/// non appliquée). `None` si `image` ne les expose pas (SVG, JPEG XL, TGA…).
+pub fn read_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let SniffedFormat::Image(format) = sniff_format(&read_header(path).ok()?) else {
+        return None;
+    };
+    let file = std::io::BufReader::new(std::fs::File::open(path).ok()?);
+    image::ImageReader::with_format(file, format).into_dimensions().ok()
+}
+
+fn extension_is(path: &Path, ext: &str) -> bool {
     path.extension()
         .and_then(|e| e.to_str())
-        .map(|e| SUPPORTED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
+fn extension_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .map(|e| SUPPORTED_EXTENSIONS.contains(&e.as_str()) || FEATURE_EXTENSIONS.contains(&e.as_str()))
         .unwrap_or(false)
 }
 
+/// Octets lus pour reconnaître un format (prologue XML d'un SVG compris).
+const SNIFF_LEN: usize = 4096;
+
+/// Format reconnu au contenu (signature binaire ou racine XML).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffedFormat {
+    Image(image::ImageFormat),
+    Jxl,
+    /// SVG texte, ou SVGZ (gzip).
+    Svg,
+    /// Reconnu mais jamais pris en charge (PDF, PSD, HEIC…).
+    Other(&'static str),
+    Unknown,
+}
+
+impl SniffedFormat {
+    /// Nom lisible du format, pour les messages d'erreur.
+    pub fn name(&self) -> String {
+        match self {
+            SniffedFormat::Image(format) => format!("{format:?}").to_uppercase(),
+            SniffedFormat::Jxl => "JPEG XL".into(),
+            SniffedFormat::Svg => "SVG".into(),
+            SniffedFormat::Other(name) => (*name).into(),
+            SniffedFormat::Unknown => tr!("format.unknown"),
+        }
+    }
+
+    /// Vrai si ce build sait décoder le format (selon les features cargo).
+    pub fn is_decodable(&self) -> bool {
+        match self {
+            // `image` déclare l'AVIF lisible dès sa feature d'encodage
+            SniffedFormat::Image(image::ImageFormat::Avif) => cfg!(feature = "avif-decode"),
+            SniffedFormat::Image(format) => format.reading_enabled(),
+            SniffedFormat::Jxl => cfg!(feature = "jxl"),
+            SniffedFormat::Svg => true,
+            SniffedFormat::Other(_) | SniffedFormat::Unknown => false,
+        }
+    }
+}
+
+/// Reconnaît le format d'après les premiers octets (`SNIFF_LEN` suffisent).
+pub fn sniff_format(bytes: &[u8]) -> SniffedFormat {
+    const JXL_CODESTREAM: &[u8] = &[0xFF, 0x0A];
+    const JXL_CONTAINER: &[u8] = b"\0\0\0\x0CJXL \r\n\x87\n";
+
+    if bytes.starts_with(JXL_CODESTREAM) || bytes.starts_with(JXL_CONTAINER) {
+        return SniffedFormat::Jxl;
+    }
+    if bytes.starts_with(&[0x1F, 0x8B]) {
+        return if is_svg_text(&gunzip_prefix(bytes)) {
+            SniffedFormat::Svg
+        } else {
+            SniffedFormat::Other("GZIP")
+        };
+    }
+    if let Ok(format) = image::guess_format(bytes) {
+        return SniffedFormat::Image(format);
+    }
+    if is_svg_text(bytes) {
+        return SniffedFormat::Svg;
+    }
+
+    let brand = bytes.get(4..12);
+    match bytes {
+        [b'%', b'P', b'D', b'F', ..] => SniffedFormat::Other("PDF"),
+        [b'8', b'B', b'P', b'S', ..] => SniffedFormat::Other("PSD"),
+        [0, 0, 0, 0x0C, b'j', b'P', b' ', b' ', ..] | [0xFF, 0x4F, 0xFF, 0x51, ..] => {
+            SniffedFormat::Other("JPEG 2000")
+        }
+        [b'P', b'K', 3, 4, ..] => SniffedFormat::Other("ZIP"),
+        _ if matches!(brand, Some(b"ftypheic" | b"ftypheix" | b"ftypmif1" | b"ftyphevc")) => {
+            SniffedFormat::Other("HEIC")
+        }
+        _ => SniffedFormat::Unknown,
+    }
+}
+
+/// Premiers octets (`SNIFF_LEN` au plus) d'un flux gzip, même tronqué.
+fn gunzip_prefix(bytes: &[u8]) -> Vec<u8> {
+    let mut decoder = flate2::read::GzDecoder::new(bytes);
+    let mut out = vec![0; SNIFF_LEN];
+    let mut len = 0;
+    while len < out.len() {
+        match decoder.read(&mut out[len..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => len += n,
+        }
+    }
+    out.truncate(len);
+    out
+}
+
+/// Format d'un fichier : signature, puis l'extension pour un SVG au prologue
+/// plus long que l'en-tête lu ou un SVGZ qui ne se laisse pas reconnaître.
+fn sniff_file(path: &Path) -> std::io::Result<SniffedFormat> {
+    Ok(match sniff_format(&read_header(path)?) {
+        SniffedFormat::Unknown if extension_is(path, "svg") => SniffedFormat::Svg,
+        SniffedFormat::Other("GZIP") if extension_is(path, "svgz") => SniffedFormat::Svg,
+        format => format,
+    })
+}
+
+/// Texte XML dont le premier élément est `<svg` : BOM, espaces, prologue,
+/// commentaires et DOCTYPE sont sautés.
+fn is_svg_text(bytes: &[u8]) -> bool {
+    let mut rest = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    loop {
+        rest = rest.trim_ascii_start();
+        let skip_to = |rest: &[u8], end: &[u8]| {
+            rest.windows(end.len())
+                .position(|w| w == end)
+                .map(|i| i + end.len())
+        };
+        let next = if rest.starts_with(b"<?") {
+            skip_to(rest, b"?>")
+        } else if rest.starts_with(b"<!--") {
+            skip_to(rest, b"-->")
+        } else if rest.starts_with(b"<!") {
+            skip_to(rest, b">")
+        } else if rest.starts_with(b"]>") {
+            Some(2) // fin du sous-ensemble interne d'un DOCTYPE (entités Illustrator)
+        } else {
+            return rest.starts_with(b"<svg")
+                && rest.get(4).is_none_or(|c| c.is_ascii_whitespace() || *c == b'>');
+        };
+        match next {
+            Some(i) => rest = &rest[i..],
+            None => return false,
+        }
+    }
+}
+
+/// Branche les décodeurs externes sur `image` (détection par signature et
+/// décodage), une seule fois par processus.
+fn register_decoders() {
+    static REGISTER: std::sync::Once = std::sync::Once::new();
+    REGISTER.call_once(|| {
+        #[cfg(feature = "jxl")]
+        jxl_oxide::integration::register_image_decoding_hook();
+    });
+}
+
 // ─── Chargement ──────────────────────────────────────────────────────────────
 
-/// Charge une image depuis un chemin fichier.
-/// SVG → rasterisé à 2048px de large minimum.
-/// Images > 4K → Smart-resize avant envoi au moteur ML.
-pub fn load_image(path: &Path) -> Result<DynamicImage> {
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+/// Limite mémoire par défaut au chargement (≈ 400 MB en RGBA 8 bits).
+pub const DEFAULT_MAX_MEGAPIXELS: f32 = 100.0;
 
-    if ext == "svg" {
-        let data = std::fs::read(path)?;
-        return rasterize_svg(&data);
+/// Refuse explicitement les images au-delà de la limite (plus de downscale silencieux).
+pub(crate) fn check_megapixels(w: u32, h: u32, max_megapixels: f32) -> Result<()> {
+    let mp = w as f64 * h as f64 / 1_000_000.0;
+    if mp > max_megapixels as f64 {
+        return Err(AppError::ImageTooLarge {
+            width: w,
+            height: h,
+            megapixels: mp,
+            max_megapixels,
+        }
+        .into());
     }
+    Ok(())
+}
 
-    let img = image::open(path).map_err(|e| anyhow!("Impossible d'ouvrir {} : {e}", path.display()))?;
-    Ok(smart_downscale(img))
+/// Lit les dimensions depuis l'en-tête, vérifie la limite, puis décode.
+/// Les limites internes du crate `image` (512 MB) sont levées : c'est
+/// `max_megapixels` qui fait foi.
+/// L'orientation EXIF (8 cas) est appliquée ici, avant inférence et composition.
+fn decode_checked<R: BufRead + Seek>(
+    mut reader: image::ImageReader<R>,
+    max_megapixels: f32,
+) -> Result<(DynamicImage, ImageMetadata)> {
+    // Le décodeur TIFF de `image` ne retrouve pas les tags ICC/XMP : lecture
+    // directe avec le crate `tiff`, puis on rembobine le flux.
+    let mut tiff_metadata = None;
+    if reader.format() == Some(image::ImageFormat::Tiff) {
+        let mut inner = reader.into_inner();
+        let start = inner.stream_position()?;
+        tiff_metadata = ImageMetadata::read_from_tiff(&mut inner);
+        inner.seek(std::io::SeekFrom::Start(start))?;
+        reader = image::ImageReader::with_format(inner, image::ImageFormat::Tiff);
+    }
+
+    reader.no_limits();
+    let mut decoder = reader.into_decoder()?;
+
+    let (w, h) = decoder.dimensions();
+    check_megapixels(w, h, max_megapixels)?;
+
+    // EXIF illisible → on garde l'image telle quelle plutôt que d'échouer
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let metadata = tiff_metadata.unwrap_or_else(|| ImageMetadata::read_from(&mut decoder));
+
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    Ok((img, metadata))
+}
+
+/// Charge une image depuis un chemin fichier, en pleine résolution et
+/// correctement orientée (tag EXIF Orientation).
+/// SVG → rasterisé à 2048px de large minimum (voir `load_image_with_options`).
+/// Au-delà de `max_megapixels` → erreur explicite.
+pub fn load_image(path: &Path, max_megapixels: f32) -> Result<DynamicImage> {
+    load_image_with_metadata(path, max_megapixels).map(|(img, _)| img)
+}
+
+/// Comme `load_image`, en conservant le profil ICC et les métadonnées source.
+pub fn load_image_with_metadata(
+    path: &Path,
+    max_megapixels: f32,
+) -> Result<(DynamicImage, ImageMetadata)> {
+    load_image_with_options(path, max_megapixels, &SvgRenderOptions::default())
+}
+
+/// Comme `load_image_with_metadata`, avec la taille de rasterisation SVG
+/// demandée. Les `<image href>` relatifs sont résolus depuis le dossier du SVG.
+pub fn load_image_with_options(
+    path: &Path,
+    max_megapixels: f32,
+    svg: &SvgRenderOptions,
+) -> Result<(DynamicImage, ImageMetadata)> {
+    let open_err = |e: std::io::Error| open_error(path, e.into());
+
+    // Format choisi d'après le contenu ; l'extension ne sert qu'en dernier recours
+    let format = sniff_file(path).map_err(open_err)?;
+    if format == SniffedFormat::Svg {
+        let data = std::fs::read(path).map_err(open_err)?;
+        return load_svg(&data, path.parent(), svg, max_megapixels);
+    }
+
+    let reader = image::ImageReader::open(path).map_err(open_err)?;
+    let reader = with_sniffed_format(reader, format)?;
+    decode_checked(reader, max_megapixels).map_err(|e| open_error(path, e))
+}
+
+/// Les `AppError` (limite de taille, format) passent telles quelles ; un
+/// fichier absent devient `FileNotFound`, le reste une erreur de décodage.
+fn open_error(path: &Path, e: anyhow::Error) -> anyhow::Error {
+    if e.is::<AppError>() {
+        return e;
+    }
+    let not_found = e
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|io| io.kind() == std::io::ErrorKind::NotFound);
+    if not_found {
+        return AppError::FileNotFound { path: path.display().to_string() }.into();
+    }
+    AppError::Decode(format!("{} : {e}", path.display())).into()
 }
 
 /// Charge depuis un buffer brut en mémoire (ex: clipboard, drag&drop données).
-pub fn load_image_from_bytes(bytes: &[u8]) -> Result<DynamicImage> {
-    let img = image::load_from_memory(bytes).map_err(|e| anyhow!("Décodage image : {e}"))?;
-    Ok(smart_downscale(img))
+pub fn load_image_from_bytes(
+    bytes: &[u8],
+    max_megapixels: f32,
+    svg: &SvgRenderOptions,
+) -> Result<DynamicImage> {
+    load_image_from_bytes_with_metadata(bytes, max_megapixels, svg).map(|(img, _)| img)
+}
+
+/// Comme `load_image_from_bytes`, en conservant le profil ICC et les métadonnées.
+/// Le SVG est reconnu et rasterisé selon `svg` comme depuis un fichier (sans
+/// dossier pour résoudre les `<image href>` relatifs).
+pub fn load_image_from_bytes_with_metadata(
+    bytes: &[u8],
+    max_megapixels: f32,
+    svg: &SvgRenderOptions,
+) -> Result<(DynamicImage, ImageMetadata)> {
+    let format = sniff_format(bytes);
+    if format == SniffedFormat::Svg {
+        return load_svg(bytes, None, svg, max_megapixels);
+    }
+
+    let reader = with_sniffed_format(image::ImageReader::new(Cursor::new(bytes)), format)?;
+    decode_checked(reader, max_megapixels).map_err(|e| {
+        if e.is::<AppError>() {
+            e
+        } else {
+            AppError::Decode(e.to_string()).into()
+        }
+    })
+}
+
+fn read_header(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(path)
+        .and_then(|f| f.take(SNIFF_LEN as u64).read_to_end(&mut header))?;
+    Ok(header)
+}
+
+fn load_svg(
+    data: &[u8],
+    resources_dir: Option<&Path>,
+    options: &SvgRenderOptions,
+    max_megapixels: f32,
+) -> Result<(DynamicImage, ImageMetadata)> {
+    let img = rasterize_svg(data, resources_dir, options)?;
+    check_megapixels(img.width(), img.height(), max_megapixels)?;
+    Ok((img, ImageMetadata::default()))
+}
+
+/// Impose au lecteur le format détecté. Sans signature reconnue, le format
+/// déduit de l'extension (s'il y en a une) est gardé ; sinon `UnsupportedFormat`
+/// avec le type détecté (« inconnu » si aucune signature).
+fn with_sniffed_format<R: BufRead + Seek>(
+    mut reader: image::ImageReader<R>,
+    format: SniffedFormat,
+) -> Result<image::ImageReader<R>> {
+    register_decoders();
+    match format {
+        SniffedFormat::Image(f) if format.is_decodable() => {
+            reader.set_format(f);
+            Ok(reader)
+        }
+        // Décodeur externe branché par hook : seule la détection le retrouve
+        SniffedFormat::Jxl if format.is_decodable() => Ok(reader.with_guessed_format()?),
+        SniffedFormat::Unknown if reader.format().is_some_and(|f| f.reading_enabled()) => Ok(reader),
+        _ => Err(AppError::UnsupportedFormat { detected: format.name() }.into()),
+    }
+}
+
+// ─── Métadonnées (ICC / EXIF / XMP) ──────────────────────────────────────────
+
+/// Métadonnées source transportées du chargement jusqu'à l'encodage, pour que
+/// les sources Adobe RGB / Display P3 ne changent pas de couleur.
+#[derive(Debug, Clone, Default)]
+pub struct ImageMetadata {
+    pub icc_profile: Option<Vec<u8>>,
+    pub exif: Option<Vec<u8>>,
+    pub xmp: Option<Vec<u8>>,
+}
+
+impl ImageMetadata {
+    fn read_from(decoder: &mut impl ImageDecoder) -> Self {
+        let mut exif = decoder.exif_metadata().ok().flatten();
+
+        // L'orientation est déjà appliquée aux pixels : la laisser dans l'EXIF
+        // ferait tourner l'image une seconde fois dans les visionneuses.
+        if let Some(chunk) = exif.as_mut() {
+            let _ = Orientation::remove_from_exif_chunk(chunk);
+        }
+
+        Self {
+            icc_profile: decoder.icc_profile().ok().flatten(),
+            exif,
+            xmp: decoder.xmp_metadata().ok().flatten(),
+        }
+    }
+
+    fn read_from_tiff<R: std::io::Read + Seek>(reader: R) -> Option<Self> {
+        use tiff::tags::Tag;
+
+        let mut decoder = tiff::decoder::Decoder::new(reader).ok()?;
+        Some(Self {
+            icc_profile: decoder.get_tag_u8_vec(Tag::IccProfile).ok(),
+            exif: None, // l'IFD EXIF d'un TIFF n'est pas un bloc autonome
+            xmp: decoder.get_tag_u8_vec(Tag::Unknown(700)).ok(),
+        })
+    }
+
+    /// Retire EXIF et XMP (auteur, GPS, date de prise de vue…) pour la
+    /// confidentialité. Le profil ICC est gardé : il ne décrit que les couleurs.
+    pub fn stripped(self) -> Self {
+        Self {
+            icc_profile: self.icc_profile,
+            exif: None,
+            xmp: None,
+        }
+    }
+}
+
+// ─── Profondeur et plage dynamique ───────────────────────────────────────────
+
+/// Images 16 bits par canal (PNG / TIFF / PNM 16 bits).
+fn is_16bit(img: &DynamicImage) -> bool {
+    matches!(
+        img,
+        DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_)
+    )
+}
+
+/// Images flottantes (Radiance HDR, OpenEXR, TIFF float) : valeurs en
+/// lumière linéaire, potentiellement supérieures à 1.
+fn is_float(img: &DynamicImage) -> bool {
+    matches!(img, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_))
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Ramène une image flottante linéaire en sRGB affichable dans [0, 1]
+/// (Reinhard étendu sur la luminance, blanc = luminance max de l'image).
+/// Les images entières sont retournées telles quelles.
+pub fn tone_map(img: &DynamicImage) -> std::borrow::Cow<'_, DynamicImage> {
+    if !is_float(img) {
+        return std::borrow::Cow::Borrowed(img);
+    }
+
+    let luminance = |p: &Rgba<f32>| {
+        0.2126 * p[0].max(0.0) + 0.7152 * p[1].max(0.0) + 0.0722 * p[2].max(0.0)
+    };
+    let mut rgba = img.to_rgba32f();
+    let white = rgba.pixels().map(luminance).fold(1.0f32, f32::max);
+
+    for p in rgba.pixels_mut() {
+        let l = luminance(p);
+        // L' = L (1 + L / Lw²) / (1 + L), appliqué aux trois canaux via L'/L
+        let scale = (1.0 + l / (white * white)) / (1.0 + l);
+        for c in &mut p.0[..3] {
+            *c = linear_to_srgb((c.max(0.0) * scale).min(1.0));
+        }
+    }
+
+    let mapped = DynamicImage::ImageRgba32F(rgba);
+    std::borrow::Cow::Owned(if img.color().has_alpha() {
+        mapped
+    } else {
+        DynamicImage::ImageRgb32F(mapped.to_rgb32f())
+    })
 }
 
-/// Réduit intelligemment si > 4096px sur un côté (VRAM protection).
-fn smart_downscale(img: DynamicImage) -> DynamicImage {
-    const MAX_DIM: u32 = 4096;
+/// Conversion en 16 bits pour les formats entiers (PNG) ; l'alpha est
+/// conservé seulement s'il existe.
+fn to_16bit(img: &DynamicImage) -> DynamicImage {
+    if img.color().has_alpha() {
+        DynamicImage::ImageRgba16(img.to_rgba16())
+    } else {
+        DynamicImage::ImageRgb16(img.to_rgb16())
+    }
+}
+
+/// Image en flottant linéaire pour OpenEXR : les sources entières (sRGB)
+/// sont linéarisées, les sources flottantes passent telles quelles.
+fn to_linear_f32(img: &DynamicImage) -> DynamicImage {
+    if is_float(img) {
+        return img.clone();
+    }
+    let mut rgba = img.to_rgba32f();
+    for p in rgba.pixels_mut() {
+        for c in &mut p.0[..3] {
+            *c = srgb_to_linear(*c);
+        }
+    }
+    let linear = DynamicImage::ImageRgba32F(rgba);
+    if img.color().has_alpha() {
+        linear
+    } else {
+        DynamicImage::ImageRgb32F(linear.to_rgb32f())
+    }
+}
+
+// ─── Copie d'inférence ───────────────────────────────────────────────────────
+
+/// Côté max de la copie envoyée au moteur ML (le modèle travaille en 1024²).
+const INFERENCE_MAX_DIM: u32 = 1024;
+
+/// Copie réduite de l'image pour l'inférence ; l'original reste intact pour
+/// la composition. Retourne l'image telle quelle si elle est déjà petite.
+/// Les sources HDR sont tone-mappées en 8 bits : seule cette copie l'est.
+pub fn inference_copy(img: &DynamicImage) -> std::borrow::Cow<'_, DynamicImage> {
     let (w, h) = (img.width(), img.height());
-    if w <= MAX_DIM && h <= MAX_DIM {
-        return img;
+    let small = if w <= INFERENCE_MAX_DIM && h <= INFERENCE_MAX_DIM {
+        std::borrow::Cow::Borrowed(img)
+    } else {
+        let scale = INFERENCE_MAX_DIM as f32 / w.max(h) as f32;
+        let nw = ((w as f32 * scale).round() as u32).max(1);
+        let nh = ((h as f32 * scale).round() as u32).max(1);
+        std::borrow::Cow::Owned(img.resize_exact(nw, nh, image::imageops::FilterType::Triangle))
+    };
+
+    if !is_float(&small) {
+        return small;
+    }
+    std::borrow::Cow::Owned(DynamicImage::ImageRgb8(tone_map(&small).to_rgb8()))
+}
+
+/// Ramène le masque prédit sur la copie réduite à la taille de l'original.
+/// `refine` : upscale guidé par l'image pleine résolution (fast guided filter),
+/// qui recale les bords du masque sur les contours réels de l'image.
+pub fn upscale_mask(
+    mask: &GrayImage,
+    small: &DynamicImage,
+    full: &DynamicImage,
+    refine: bool,
+) -> GrayImage {
+    let (w, h) = (full.width(), full.height());
+    if mask.dimensions() == (w, h) {
+        return mask.clone();
+    }
+    if !refine {
+        return image::imageops::resize(mask, w, h, image::imageops::FilterType::Lanczos3);
+    }
+    guided_upscale(mask, &small.to_luma8(), &tone_map(full).to_luma8())
+}
+
+/// Box filter (moyenne sur une fenêtre (2r+1)²) via image intégrale.
+fn box_mean(src: &[f32], w: usize, h: usize, r: usize) -> Vec<f32> {
+    let mut integral = vec![0.0f64; (w + 1) * (h + 1)];
+    for y in 0..h {
+        let mut row = 0.0f64;
+        for x in 0..w {
+            row += src[y * w + x] as f64;
+            integral[(y + 1) * (w + 1) + x + 1] = integral[y * (w + 1) + x + 1] + row;
+        }
+    }
+
+    let mut out = vec![0.0f32; w * h];
+    for y in 0..h {
+        let (y0, y1) = (y.saturating_sub(r), (y + r + 1).min(h));
+        for x in 0..w {
+            let (x0, x1) = (x.saturating_sub(r), (x + r + 1).min(w));
+            let sum = integral[y1 * (w + 1) + x1] - integral[y0 * (w + 1) + x1]
+                - integral[y1 * (w + 1) + x0]
+                + integral[y0 * (w + 1) + x0];
+            out[y * w + x] = (sum / ((y1 - y0) * (x1 - x0)) as f64) as f32;
+        }
+    }
+    out
+}
+
+/// Fast guided filter (He & Sun) : coefficients linéaires calculés en basse
+/// résolution, interpolés puis appliqués au guide pleine résolution.
+fn guided_upscale(mask: &GrayImage, guide_small: &GrayImage, guide_full: &GrayImage) -> GrayImage {
+    const RADIUS: usize = 4;
+    const EPS: f32 = 1e-3;
+
+    let (sw, sh) = guide_small.dimensions();
+    let mask = if mask.dimensions() == (sw, sh) {
+        mask.clone()
+    } else {
+        image::imageops::resize(mask, sw, sh, image::imageops::FilterType::Triangle)
+    };
+    let (sw, sh) = (sw as usize, sh as usize);
+
+    let i: Vec<f32> = guide_small.as_raw().iter().map(|&v| v as f32 / 255.0).collect();
+    let p: Vec<f32> = mask.as_raw().iter().map(|&v| v as f32 / 255.0).collect();
+    let ii: Vec<f32> = i.iter().map(|v| v * v).collect();
+    let ip: Vec<f32> = i.iter().zip(&p).map(|(a, b)| a * b).collect();
+
+    let mean_i = box_mean(&i, sw, sh, RADIUS);
+    let mean_p = box_mean(&p, sw, sh, RADIUS);
+    let mean_ii = box_mean(&ii, sw, sh, RADIUS);
+    let mean_ip = box_mean(&ip, sw, sh, RADIUS);
+
+    let mut a = vec![0.0f32; sw * sh];
+    let mut b = vec![0.0f32; sw * sh];
+    for k in 0..sw * sh {
+        let var_i = mean_ii[k] - mean_i[k] * mean_i[k];
+        let cov_ip = mean_ip[k] - mean_i[k] * mean_p[k];
+        a[k] = cov_ip / (var_i + EPS);
+        b[k] = mean_p[k] - a[k] * mean_i[k];
     }
-    let scale = MAX_DIM as f32 / w.max(h) as f32;
-    let nw = (w as f32 * scale) as u32;
-    let nh = (h as f32 * scale) as u32;
-    img.resize_exact(nw, nh, image::imageops::FilterType::Lanczos3)
+    let mean_a = box_mean(&a, sw, sh, RADIUS);
+    let mean_b = box_mean(&b, sw, sh, RADIUS);
+
+    // Interpolation bilinéaire des coefficients (centres de pixels alignés)
+    let (fw, fh) = guide_full.dimensions();
+    let sample = |map: &[f32], fx: f32, fy: f32| -> f32 {
+        let x = (fx.max(0.0)).min((sw - 1) as f32);
+        let y = (fy.max(0.0)).min((sh - 1) as f32);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(sw - 1), (y0 + 1).min(sh - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+        let top = map[y0 * sw + x0] * (1.0 - tx) + map[y0 * sw + x1] * tx;
+        let bottom = map[y1 * sw + x0] * (1.0 - tx) + map[y1 * sw + x1] * tx;
+        top * (1.0 - ty) + bottom * ty
+    };
+
+    let (sx, sy) = (sw as f32 / fw as f32, sh as f32 / fh as f32);
+    let mut out = GrayImage::new(fw, fh);
+    for (x, y, px) in out.enumerate_pixels_mut() {
+        let fx = (x as f32 + 0.5) * sx - 0.5;
+        let fy = (y as f32 + 0.5) * sy - 0.5;
+        let guide = guide_full.get_pixel(x, y)[0] as f32 / 255.0;
+        let q = sample(&mean_a, fx, fy) * guide + sample(&mean_b, fx, fy);
+        px[0] = (q.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    out
 }
 
 // ─── SVG → Bitmap ────────────────────────────────────────────────────────────
 
-fn rasterize_svg(svg_data: &[u8]) -> Result<DynamicImage> {
-    let options = resvg::usvg::Options::default();
+/// Taille de rasterisation des SVG (toutes facultatives côté frontend).
+/// Sans rien : 2048 px de large au minimum, comme auparavant.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct SvgRenderOptions {
+    /// Largeur voulue en pixels ; la hauteur suit le ratio.
+    pub width: Option<u32>,
+    /// Hauteur voulue ; avec `width`, l'image tient dans le cadre (ratio gardé).
+    pub height: Option<u32>,
+    /// Résolution, 96 = taille nominale du SVG (si ni largeur ni hauteur).
+    pub dpi: Option<f32>,
+}
+
+impl SvgRenderOptions {
+    const MIN_WIDTH: f32 = 2048.0;
+    const MAX_DIMENSION: f32 = 8192.0; // Cap anti-DoS (~256 MB max)
+
+    /// Échelle appliquée à la taille nominale `w`×`h`, plafonnée sur le plus
+    /// grand côté pour conserver le ratio.
+    fn scale(&self, w: f32, h: f32) -> f32 {
+        let scale = match (self.width, self.height, self.dpi) {
+            (Some(tw), Some(th), _) => (tw as f32 / w).min(th as f32 / h),
+            (Some(tw), None, _) => tw as f32 / w,
+            (None, Some(th), _) => th as f32 / h,
+            (None, None, Some(dpi)) if dpi > 0.0 => dpi / 96.0,
+            _ => (Self::MIN_WIDTH / w).max(1.0),
+        };
+        scale.min(Self::MAX_DIMENSION / w.max(h))
+    }
+}
+
+/// Dossier des polices livrées avec l'application, à fixer au démarrage
+/// (avant le premier SVG) ; elles complètent les polices système.
+static BUNDLED_FONTS_DIR: once_cell::sync::OnceCell<std::path::PathBuf> =
+    once_cell::sync::OnceCell::new();
+
+pub fn set_bundled_fonts_dir(dir: std::path::PathBuf) {
+    let _ = BUNDLED_FONTS_DIR.set(dir);
+}
+
+/// Base de polices partagée, chargée au premier SVG (le scan système est lent).
+/// Les familles génériques absentes (Times New Roman sous Linux…) pointent
+/// vers une police embarquée pour que le texte ne disparaisse jamais.
+fn font_database() -> std::sync::Arc<resvg::usvg::fontdb::Database> {
+    static FONTS: once_cell::sync::Lazy<std::sync::Arc<resvg::usvg::fontdb::Database>> =
+        once_cell::sync::Lazy::new(|| {
+            let mut db = resvg::usvg::fontdb::Database::new();
+            db.load_system_fonts();
+            if let Some(dir) = BUNDLED_FONTS_DIR.get() {
+                db.load_fonts_dir(dir);
+            }
+
+            let has_family = |db: &resvg::usvg::fontdb::Database, name: &str| {
+                db.faces().any(|f| f.families.iter().any(|(n, _)| n == name))
+            };
+            let fallback = ["DejaVu Sans", "Arial", "Helvetica", "Liberation Sans"]
+                .into_iter()
+                .find(|name| has_family(&db, name))
+                .map(str::to_string)
+                .or_else(|| db.faces().next().and_then(|f| f.families.first()).map(|(n, _)| n.clone()));
+
+            if let Some(fallback) = fallback {
+                if !has_family(&db, "Times New Roman") {
+                    db.set_serif_family(fallback.clone());
+                }
+                if !has_family(&db, "Arial") {
+                    db.set_sans_serif_family(fallback.clone());
+                }
+                if !has_family(&db, "Courier New") {
+                    db.set_monospace_family(fallback);
+                }
+            }
+            std::sync::Arc::new(db)
+        });
+    FONTS.clone()
+}
+
+fn rasterize_svg(
+    svg_data: &[u8],
+    resources_dir: Option<&Path>,
+    render: &SvgRenderOptions,
+) -> Result<DynamicImage> {
+    // Texte sans police trouvée : usvg se rabat sur la famille serif de la base
+    let options = resvg::usvg::Options {
+        resources_dir: resources_dir.map(Path::to_path_buf),
+        fontdb: font_database(),
+        ..Default::default()
+    };
     let tree = resvg::usvg::Tree::from_data(svg_data, &options)
-        .map_err(|e| anyhow!("SVG parse error : {e}"))?;
+        .map_err(|e| AppError::Decode(tr!("svg.parse", error = e)))?;
 
     let size = tree.size();
 
     // Guard : SVG à dimensions nulles → panic division/zéro + DoS
     if size.width() <= 0.0 || size.height() <= 0.0 {
-        return Err(anyhow!("SVG invalide : dimensions nulles ou négatives"));
+        return Err(AppError::Decode(tr!("svg.invalid_size")).into());
     }
 
-    const MIN_SVG_WIDTH: f32 = 2048.0;
-    const MAX_SVG_PIXELS: u32 = 8192; // Cap anti-DoS (~256 MB max)
-    let scale = (MIN_SVG_WIDTH / size.width()).max(1.0);
-
-    let px_w = ((size.width() * scale) as u32).min(MAX_SVG_PIXELS);
-    let px_h = ((size.height() * scale) as u32).min(MAX_SVG_PIXELS);
+    let scale = render.scale(size.width(), size.height());
+    let px_w = ((size.width() * scale).round() as u32).max(1);
+    let px_h = ((size.height() * scale).round() as u32).max(1);
 
     let mut pixmap = resvg::tiny_skia::Pixmap::new(px_w, px_h)
-        .ok_or_else(|| anyhow!("Impossible de créer le Pixmap SVG ({px_w}×{px_h})"))?;
+        .ok_or_else(|| AppError::Decode(tr!("svg.pixmap", width = px_w, height = px_h)))?;
 
-    let transform = resvg::tiny_skia::Transform::from_scale(scale, scale);
+    let transform = resvg::tiny_skia::Transform::from_scale(
+        px_w as f32 / size.width(),
+        px_h as f32 / size.height(),
+    );
     resvg::render(&tree, transform, &mut pixmap.as_mut());
 
     // tiny_skia Pixmap est en RGBA prémultiplié — on dé-multiplie pour image crate
@@ -109,9 +789,9 @@ fn rasterize_svg(svg_data: &[u8]) -> Result<DynamicImage> {
         .collect();
 
     let rgba_img = RgbaImage::from_raw(px_w, px_h, rgba_data)
-        .ok_or_else(|| anyhow!("Conversion SVG→RgbaImage échouée"))?;
+        .ok_or_else(|| AppError::Decode(tr!("svg.convert")))?;
 
-    Ok(smart_downscale(DynamicImage::ImageRgba8(rgba_img)))
+    Ok(DynamicImage::ImageRgba8(rgba_img))
 }
 
 // ─── Application du masque alpha ─────────────────────────────────────────────
@@ -127,100 +807,1111 @@ pub enum BackgroundColor {
     Color { r: u8, g: u8, b: u8 },
 }
 
+/// Espace de mélange entre l'image et le fond.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+pub enum CompositingSpace {
+    /// Lumière linéaire, alpha prémultiplié, arrondi correct (défaut).
+    #[default]
+    Linear,
+    /// Ancien comportement : mélange des valeurs sRGB brutes, tronquées ;
+    /// l'alpha propre de la source est ignoré.
+    Gamma,
+}
+
+/// sRGB 8 bits → linéaire, précalculé.
+static SRGB8_TO_LINEAR: once_cell::sync::Lazy<[f32; 256]> =
+    once_cell::sync::Lazy::new(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)));
+
+/// Seuils linéaires entre deux valeurs sRGB 8 bits consécutives : la valeur
+/// arrondie de `v` est le nombre de seuils inférieurs ou égaux à `v`.
+static LINEAR_TO_SRGB8_THRESHOLDS: once_cell::sync::Lazy<[f32; 255]> =
+    once_cell::sync::Lazy::new(|| {
+        std::array::from_fn(|i| srgb_to_linear((i as f32 + 0.5) / 255.0))
+    });
+
+/// Canal manipulé par la composition : u8, u16 ou f32 (linéaire, HDR).
+trait Channel: image::Primitive + Into<f32> + Send + Sync {
+    /// Conversion tronquée (mode `Gamma`).
+    fn from_f32(v: f32) -> Self;
+
+    /// Composante de fond (sRGB 8 bits) dans l'échelle du canal (mode `Gamma`).
+    fn background(c: u8) -> f32 {
+        c as f32 / 255.0 * Self::DEFAULT_MAX_VALUE.into()
+    }
+
+    /// Valeur en lumière linéaire (1.0 = blanc).
+    fn to_linear(self) -> f32;
+
+    /// Retour depuis la lumière linéaire, arrondi au plus proche.
+    fn from_linear(v: f32) -> Self;
+
+    /// Valeur d'alpha normalisée [0, 1] → canal, arrondie.
+    fn from_unit(v: f32) -> Self;
+}
+
+impl Channel for u8 {
+    fn from_f32(v: f32) -> Self {
+        v as u8
+    }
+
+    fn to_linear(self) -> f32 {
+        SRGB8_TO_LINEAR[self as usize]
+    }
+
+    fn from_linear(v: f32) -> Self {
+        LINEAR_TO_SRGB8_THRESHOLDS.partition_point(|&t| t <= v) as u8
+    }
+
+    fn from_unit(v: f32) -> Self {
+        (v * 255.0).round() as u8
+    }
+}
+
+impl Channel for u16 {
+    fn from_f32(v: f32) -> Self {
+        v as u16
+    }
+
+    fn to_linear(self) -> f32 {
+        srgb_to_linear(self as f32 / 65535.0)
+    }
+
+    fn from_linear(v: f32) -> Self {
+        (linear_to_srgb(v.clamp(0.0, 1.0)) * 65535.0).round() as u16
+    }
+
+    fn from_unit(v: f32) -> Self {
+        (v * 65535.0).round() as u16
+    }
+}
+
+impl Channel for f32 {
+    fn from_f32(v: f32) -> Self {
+        v
+    }
+
+    fn background(c: u8) -> f32 {
+        srgb_to_linear(c as f32 / 255.0)
+    }
+
+    // Les images flottantes sont déjà en lumière linéaire.
+    fn to_linear(self) -> f32 {
+        self
+    }
+
+    fn from_linear(v: f32) -> Self {
+        v
+    }
+
+    fn from_unit(v: f32) -> Self {
+        v
+    }
+}
+
 /// Applique le masque alpha (avec flou de bords) sur l'image originale.
-/// Retourne une RgbaImage avec le fond choisi.
+/// La profondeur source est conservée : RGBA 8 bits, RGBA 16 bits ou RGBA
+/// flottant pour les sources HDR.
 pub fn apply_mask(
     img: &DynamicImage,
     mask: &GrayImage,
     bg: &BackgroundColor,
+    space: CompositingSpace,
 ) -> DynamicImage {
-    let (w, h) = (img.width(), img.height());
-    let rgba_src = img.to_rgba8();
-
     // Flou 1px sur le masque pour éviter l'effet "coupé au ciseau"
-    let blurred_mask = blur_mask(mask);
+    apply_refined_mask(img, &refine_mask(mask), bg, space)
+}
 
-    let mut output = RgbaImage::new(w, h);
+/// Comme `apply_mask`, avec un masque déjà affiné (`refine_mask`, masque
+/// exporté puis réimporté, masque du store) : appliqué tel quel.
+pub fn apply_refined_mask(
+    img: &DynamicImage,
+    mask: &GrayImage,
+    bg: &BackgroundColor,
+    space: CompositingSpace,
+) -> DynamicImage {
+    if is_16bit(img) {
+        DynamicImage::ImageRgba16(composite(&img.to_rgba16(), mask, bg, space))
+    } else if is_float(img) {
+        DynamicImage::ImageRgba32F(composite(&img.to_rgba32f(), mask, bg, space))
+    } else {
+        DynamicImage::ImageRgba8(composite(&img.to_rgba8(), mask, bg, space))
+    }
+}
 
-    for y in 0..h {
-        for x in 0..w {
-            let src = rgba_src.get_pixel(x, y);
-            let alpha = blurred_mask.get_pixel(x, y)[0];
-            let alpha_f = alpha as f32 / 255.0;
+fn composite<T: Channel>(
+    src: &ImageBuffer<Rgba<T>, Vec<T>>,
+    mask: &GrayImage,
+    bg: &BackgroundColor,
+    space: CompositingSpace,
+) -> ImageBuffer<Rgba<T>, Vec<T>>
+where
+    Rgba<T>: image::Pixel<Subpixel = T>,
+{
+    match space {
+        CompositingSpace::Linear => composite_linear(src, mask, bg),
+        CompositingSpace::Gamma => composite_gamma(src, mask, bg),
+    }
+}
 
-            let out = match bg {
-                BackgroundColor::Transparent => {
-                    [src[0], src[1], src[2], alpha]
-                }
-                BackgroundColor::White => {
-                    let blend = |fg: u8, bg_c: u8| -> u8 {
-                        (fg as f32 * alpha_f + bg_c as f32 * (1.0 - alpha_f)) as u8
-                    };
-                    [blend(src[0], 255), blend(src[1], 255), blend(src[2], 255), 255]
-                }
-                BackgroundColor::Black => {
-                    let blend = |fg: u8| -> u8 { (fg as f32 * alpha_f) as u8 };
-                    [blend(src[0]), blend(src[1]), blend(src[2]), 255]
-                }
-                BackgroundColor::Color { r, g, b } => {
-                    let blend = |fg: u8, bg_c: u8| -> u8 {
-                        (fg as f32 * alpha_f + bg_c as f32 * (1.0 - alpha_f)) as u8
-                    };
-                    [blend(src[0], *r), blend(src[1], *g), blend(src[2], *b), 255]
+/// Parcourt l'image ligne par ligne en parallèle : `kernel` reçoit la ligne
+/// RGBA source, la ligne du masque et la ligne RGBA de sortie.
+fn composite_rows<T: Channel>(
+    src: &ImageBuffer<Rgba<T>, Vec<T>>,
+    mask: &GrayImage,
+    kernel: impl Fn(&[T], &[u8], &mut [T]) + Sync,
+) -> ImageBuffer<Rgba<T>, Vec<T>>
+where
+    Rgba<T>: image::Pixel<Subpixel = T>,
+{
+    let (w, h) = src.dimensions();
+    let mut output = ImageBuffer::new(w, h);
+    if w == 0 || h == 0 {
+        return output;
+    }
+
+    let row_len = w as usize * 4;
+    output
+        .par_chunks_mut(row_len)
+        .zip(src.par_chunks(row_len))
+        .zip(mask.par_chunks(w as usize))
+        .for_each(|((out, src), mask)| kernel(src, mask, out));
+    output
+}
+
+/// Mélange en lumière linéaire, couleur prémultipliée par l'alpha effectif
+/// (masque × alpha source) : `out = fg·α + fond·(1 − α)`, puis retour en sRGB
+/// arrondi. Sans fond, les couleurs restent non prémultipliées (convention PNG).
+fn composite_linear<T: Channel>(
+    src: &ImageBuffer<Rgba<T>, Vec<T>>,
+    mask: &GrayImage,
+    bg: &BackgroundColor,
+) -> ImageBuffer<Rgba<T>, Vec<T>>
+where
+    Rgba<T>: image::Pixel<Subpixel = T>,
+{
+    let max: f32 = T::DEFAULT_MAX_VALUE.into();
+    let coverage = |m: u8, src_alpha: T| {
+        let src_alpha: f32 = src_alpha.into();
+        m as f32 / 255.0 * (src_alpha / max).clamp(0.0, 1.0)
+    };
+
+    let linear = |c: u8| srgb_to_linear(c as f32 / 255.0);
+    let bg_linear = match bg {
+        BackgroundColor::Transparent => None,
+        BackgroundColor::White => Some([1.0; 3]),
+        BackgroundColor::Black => Some([0.0; 3]),
+        BackgroundColor::Color { r, g, b } => Some([linear(*r), linear(*g), linear(*b)]),
+    };
+
+    match bg_linear {
+        None => composite_rows(src, mask, |src, mask, out| {
+            for ((o, s), &m) in out.chunks_exact_mut(4).zip(src.chunks_exact(4)).zip(mask) {
+                o[..3].copy_from_slice(&s[..3]);
+                o[3] = T::from_unit(coverage(m, s[3]));
+            }
+        }),
+        Some(bg_c) => {
+            let bg_px = bg_c.map(T::from_linear);
+            composite_rows(src, mask, |src, mask, out| {
+                for ((o, s), &m) in out.chunks_exact_mut(4).zip(src.chunks_exact(4)).zip(mask) {
+                    let alpha = coverage(m, s[3]);
+                    // Pixels entièrement couverts ou découverts : pas d'aller-retour linéaire
+                    if alpha >= 1.0 {
+                        o[..3].copy_from_slice(&s[..3]);
+                    } else if alpha <= 0.0 {
+                        o[..3].copy_from_slice(&bg_px);
+                    } else {
+                        for i in 0..3 {
+                            let blended = s[i].to_linear() * alpha + bg_c[i] * (1.0 - alpha);
+                            o[i] = T::from_linear(blended);
+                        }
+                    }
+                    o[3] = T::DEFAULT_MAX_VALUE;
                 }
-            };
-            output.put_pixel(x, y, image::Rgba(out));
+            })
+        }
+    }
+}
+
+/// Ancien mélange direct des valeurs sRGB, tronqué.
+fn composite_gamma<T: Channel>(
+    src: &ImageBuffer<Rgba<T>, Vec<T>>,
+    mask: &GrayImage,
+    bg: &BackgroundColor,
+) -> ImageBuffer<Rgba<T>, Vec<T>>
+where
+    Rgba<T>: image::Pixel<Subpixel = T>,
+{
+    let max: f32 = T::DEFAULT_MAX_VALUE.into();
+
+    let bg_rgb = match bg {
+        BackgroundColor::Transparent => None,
+        BackgroundColor::White => Some([T::background(255); 3]),
+        BackgroundColor::Black => Some([0.0; 3]),
+        BackgroundColor::Color { r, g, b } => {
+            Some([T::background(*r), T::background(*g), T::background(*b)])
         }
+    };
+
+    match bg_rgb {
+        None => composite_rows(src, mask, |src, mask, out| {
+            for ((o, s), &m) in out.chunks_exact_mut(4).zip(src.chunks_exact(4)).zip(mask) {
+                o[..3].copy_from_slice(&s[..3]);
+                o[3] = T::from_f32(m as f32 / 255.0 * max);
+            }
+        }),
+        Some(bg_c) => composite_rows(src, mask, |src, mask, out| {
+            for ((o, s), &m) in out.chunks_exact_mut(4).zip(src.chunks_exact(4)).zip(mask) {
+                let alpha_f = m as f32 / 255.0;
+                for i in 0..3 {
+                    let fg: f32 = s[i].into();
+                    o[i] = T::from_f32(fg * alpha_f + bg_c[i] * (1.0 - alpha_f));
+                }
+                o[3] = T::DEFAULT_MAX_VALUE;
+            }
+        }),
     }
+}
 
-    DynamicImage::ImageRgba8(output)
+/// Masque tel qu'il est réellement appliqué par `apply_mask` (bords adoucis).
+/// C'est cette version qui est exportée pour les retoucheurs.
+pub fn refine_mask(mask: &GrayImage) -> GrayImage {
+    blur_mask(mask)
 }
 
 /// Gaussian blur 3×3 léger sur le masque pour adoucir les contours.
+/// Noyau séparable [1 2 1] ⊗ [1 2 1] / 16 en entiers, lignes en parallèle ;
+/// les bords sont répliqués.
 fn blur_mask(mask: &GrayImage) -> GrayImage {
     let (w, h) = mask.dimensions();
-    let kernel: [f32; 9] = [
-        1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0,
-        2.0 / 16.0, 4.0 / 16.0, 2.0 / 16.0,
-        1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0,
-    ];
     let mut out = GrayImage::new(w, h);
+    if w == 0 || h == 0 {
+        return out;
+    }
 
-    for y in 0..h {
-        for x in 0..w {
-            let mut sum = 0.0f32;
-            for ky in 0..3i32 {
-                for kx in 0..3i32 {
-                    let px = (x as i32 + kx - 1).clamp(0, w as i32 - 1) as u32;
-                    let py = (y as i32 + ky - 1).clamp(0, h as i32 - 1) as u32;
-                    sum += mask.get_pixel(px, py)[0] as f32
-                        * kernel[(ky * 3 + kx) as usize];
+    let (w, h) = (w as usize, h as usize);
+    let src = mask.as_raw();
+    out.par_chunks_mut(w)
+        .enumerate()
+        .for_each_init(
+            || vec![0u16; w],
+            |vertical, (y, out_row)| {
+                let above = &src[y.saturating_sub(1) * w..][..w];
+                let row = &src[y * w..][..w];
+                let below = &src[(y + 1).min(h - 1) * w..][..w];
+                for x in 0..w {
+                    vertical[x] = above[x] as u16 + 2 * row[x] as u16 + below[x] as u16;
                 }
-            }
-            out.put_pixel(x, y, Luma([sum as u8]));
+                for x in 0..w {
+                    let left = vertical[x.saturating_sub(1)];
+                    let right = vertical[(x + 1).min(w - 1)];
+                    out_row[x] = ((left + 2 * vertical[x] + right) >> 4) as u8;
+                }
+            },
+        );
+    out
+}
+
+// ─── Masque autonome (export / import) ───────────────────────────────────────
+
+/// Profondeur du fichier masque exporté.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+pub enum MaskBitDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+/// Sauvegarde le masque en niveaux de gris. Le format (PNG ou TIFF) est déduit
+/// de l'extension de `dest`.
+pub fn save_mask(mask: &GrayImage, dest: &Path, depth: MaskBitDepth) -> Result<()> {
+    let ext = dest
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let format = match ext.as_str() {
+        "png" => image::ImageFormat::Png,
+        "tif" | "tiff" => image::ImageFormat::Tiff,
+        _ => {
+            let message = tr!("mask.unsupported_format", extension = ext);
+            return Err(AppError::InvalidInput(message).into());
+        }
+    };
+
+    let gray = DynamicImage::ImageLuma8(mask.clone());
+    let out = match depth {
+        MaskBitDepth::Eight => gray,
+        // 0..255 → 0..65535 (×257), pas de perte ni de décalage
+        MaskBitDepth::Sixteen => DynamicImage::ImageLuma16(gray.to_luma16()),
+    };
+
+    out.save_with_format(dest, format)
+        .map_err(|e| AppError::Io(tr!("mask.save", path = dest.display(), error = e)).into())
+}
+
+/// Charge un masque retouché à l'extérieur et vérifie qu'il correspond à l'image.
+/// Format reconnu au contenu et limite `max_megapixels`, comme pour une image ;
+/// la taille est comparée dès l'en-tête, avant de décoder les pixels.
+/// Les masques 16 bits ou couleur sont ramenés en niveaux de gris 8 bits.
+pub fn load_mask(path: &Path, width: u32, height: u32, max_megapixels: f32) -> Result<GrayImage> {
+    let open_err = |e: anyhow::Error| -> anyhow::Error {
+        if e.is::<AppError>() {
+            return e;
+        }
+        match e.downcast::<std::io::Error>() {
+            Ok(io) => AppError::io(path, &io, tr!("mask.open", path = path.display(), error = io)),
+            Err(e) => AppError::Decode(tr!("mask.open", path = path.display(), error = e)),
+        }
+        .into()
+    };
+    let mismatch = |(mask_width, mask_height): (u32, u32)| -> anyhow::Error {
+        AppError::InvalidInput(tr!(
+            "mask.size_mismatch",
+            mask_width = mask_width,
+            mask_height = mask_height,
+            width = width,
+            height = height,
+        ))
+        .into()
+    };
+
+    let format = sniff_file(path).map_err(|e| open_err(e.into()))?;
+    let reader = || -> Result<_> {
+        let reader = image::ImageReader::open(path).map_err(|e| open_err(e.into()))?;
+        with_sniffed_format(reader, format)
+    };
+
+    // Côtés inversés admis ici : l'orientation EXIF n'est appliquée qu'au décodage
+    if let Ok((w, h)) = reader()?.into_dimensions() {
+        if (w, h) != (width, height) && (h, w) != (width, height) {
+            return Err(mismatch((w, h)));
         }
     }
-    out
+    let mask = decode_checked(reader()?, max_megapixels).map_err(open_err)?.0.to_luma8();
+    if mask.dimensions() != (width, height) {
+        return Err(mismatch(mask.dimensions()));
+    }
+    Ok(mask)
 }
 
 // ─── Encodage ────────────────────────────────────────────────────────────────
 
-/// Encode une DynamicImage en PNG dans un Vec<u8>.
-pub fn encode_png(img: &DynamicImage) -> Result<Vec<u8>> {
+/// Transmet ICC et EXIF à un encodeur `image` ; ignorés si le format ne sait
+/// pas les porter.
+fn apply_metadata(encoder: &mut impl ImageEncoder, metadata: &ImageMetadata) {
+    if let Some(icc) = &metadata.icc_profile {
+        let _ = encoder.set_icc_profile(icc.clone());
+    }
+    if let Some(exif) = &metadata.exif {
+        let _ = encoder.set_exif_metadata(exif.clone());
+    }
+}
+
+/// Niveau de compression PNG.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_lossy_quality() -> u8 {
+    90
+}
+
+fn default_avif_speed() -> u8 {
+    6
+}
+
+/// Format de sortie des résultats.
+/// `#[serde(tag = "type")]` : `{ type: "WebP", lossless: false, quality: 85 }` etc.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum OutputFormat {
+    Png {
+        #[serde(default)]
+        compression: PngCompression,
+    },
+    WebP {
+        #[serde(default = "default_true")]
+        lossless: bool,
+        #[serde(default = "default_lossy_quality")]
+        quality: u8,
+    },
+    Tiff,
+    /// Pas de canal alpha : exige un fond opaque.
+    Jpeg {
+        #[serde(default = "default_lossy_quality")]
+        quality: u8,
+    },
+    Qoi,
+    Avif {
+        #[serde(default = "default_lossy_quality")]
+        quality: u8,
+        /// 1 (lent, meilleur) … 10 (rapide).
+        #[serde(default = "default_avif_speed")]
+        speed: u8,
+    },
+    /// OpenEXR flottant linéaire, pour les sources HDR.
+    Exr,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Png {
+            compression: PngCompression::Default,
+        }
+    }
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png { .. } => "png",
+            OutputFormat::WebP { .. } => "webp",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Jpeg { .. } => "jpg",
+            OutputFormat::Qoi => "qoi",
+            OutputFormat::Avif { .. } => "avif",
+            OutputFormat::Exr => "exr",
+        }
+    }
+
+    /// Extensions acceptées telles quelles pour ce format (ex. `.jpeg`, `.tif`).
+    fn accepts_extension(&self, ext: &str) -> bool {
+        match self {
+            OutputFormat::Tiff => matches!(ext, "tif" | "tiff"),
+            OutputFormat::Jpeg { .. } => matches!(ext, "jpg" | "jpeg"),
+            _ => ext == self.extension(),
+        }
+    }
+
+    pub fn supports_alpha(&self) -> bool {
+        !matches!(self, OutputFormat::Jpeg { .. })
+    }
+
+    /// Formats capables de stocker des valeurs flottantes (HDR) sans tone mapping.
+    fn supports_float(&self) -> bool {
+        matches!(self, OutputFormat::Tiff | OutputFormat::Exr)
+    }
+
+    /// Corrige l'extension de `dest` si elle ne correspond pas au format.
+    pub fn with_extension(&self, dest: &Path) -> std::path::PathBuf {
+        let ext = dest
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        if self.accepts_extension(&ext) {
+            dest.to_path_buf()
+        } else {
+            dest.with_extension(self.extension())
+        }
+    }
+}
+
+/// Vrai si au moins un pixel n'est pas totalement opaque.
+fn has_transparency(img: &DynamicImage) -> bool {
+    match img {
+        DynamicImage::ImageRgba8(rgba) => rgba.pixels().any(|p| p[3] < u8::MAX),
+        _ if img.color().has_alpha() => img.to_rgba16().pixels().any(|p| p[3] < u16::MAX),
+        _ => false,
+    }
+}
+
+/// Encode une DynamicImage dans le format de sortie demandé, avec ses métadonnées.
+/// PNG / WebP : ICC + EXIF + XMP · TIFF : ICC + XMP · JPEG : ICC + EXIF.
+/// Refuse un format sans alpha si l'image contient de la transparence.
+/// PNG et TIFF restent en 16 bits pour les sources 16 bits ; TIFF et EXR
+/// gardent les flottants, les autres formats reçoivent une version tone-mappée.
+pub fn encode_output(
+    img: &DynamicImage,
+    format: &OutputFormat,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>> {
+    if !format.supports_alpha() && has_transparency(img) {
+        let message = tr!("output.no_alpha", extension = format.extension());
+        return Err(AppError::InvalidInput(message).into());
+    }
+
+    let tone_mapped;
+    let img = if is_float(img) && !format.supports_float() {
+        tone_mapped = tone_map(img);
+        &*tone_mapped
+    } else {
+        img
+    };
+
     let mut buf = Cursor::new(Vec::new());
-    img.write_to(&mut buf, image::ImageFormat::Png)
-        .map_err(|e| anyhow!("Encodage PNG : {e}"))?;
+
+    match *format {
+        OutputFormat::Png { compression } => {
+            use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+            let compression = match compression {
+                PngCompression::Fast => CompressionType::Fast,
+                PngCompression::Default => CompressionType::Default,
+                PngCompression::Best => CompressionType::Best,
+            };
+            let mut encoder = PngEncoder::new_with_quality(&mut buf, compression, FilterType::Adaptive);
+            apply_metadata(&mut encoder, metadata);
+            let img16;
+            let img = if is_float(img) {
+                img16 = to_16bit(img);
+                &img16
+            } else {
+                img
+            };
+            img.write_with_encoder(encoder)
+                .map_err(|e| AppError::Encode(tr!("codec.encode", format = "PNG", error = e)))?;
+            if let Some(xmp) = &metadata.xmp {
+                insert_png_xmp(buf.get_mut(), xmp);
+            }
+        }
+        OutputFormat::WebP { lossless, quality } => {
+            let webp = encode_webp(img, lossless, quality)?;
+            return Ok(mux_webp_metadata(webp, img, metadata));
+        }
+        OutputFormat::Tiff => {
+            encode_tiff(img, metadata, &mut buf).map_err(|e| {
+                AppError::Encode(tr!("codec.encode", format = "TIFF", error = e))
+            })?;
+        }
+        OutputFormat::Jpeg { quality } => {
+            let mut encoder =
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, quality.clamp(1, 100));
+            apply_metadata(&mut encoder, metadata);
+            DynamicImage::ImageRgb8(img.to_rgb8())
+                .write_with_encoder(encoder)
+                .map_err(|e| AppError::Encode(tr!("codec.encode", format = "JPEG", error = e)))?;
+        }
+        OutputFormat::Qoi => {
+            let qoi = match img {
+                DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => img.clone(),
+                _ if img.color().has_alpha() => DynamicImage::ImageRgba8(img.to_rgba8()),
+                _ => DynamicImage::ImageRgb8(img.to_rgb8()),
+            };
+            qoi.write_to(&mut buf, image::ImageFormat::Qoi)
+                .map_err(|e| AppError::Encode(tr!("codec.encode", format = "QOI", error = e)))?;
+        }
+        OutputFormat::Avif { quality, speed } => {
+            encode_avif(img, quality, speed, metadata, &mut buf)?;
+        }
+        OutputFormat::Exr => {
+            to_linear_f32(img)
+                .write_with_encoder(image::codecs::openexr::OpenExrEncoder::new(&mut buf))
+                .map_err(|e| AppError::Encode(tr!("codec.encode", format = "OpenEXR", error = e)))?;
+        }
+    }
+
     Ok(buf.into_inner())
 }
 
+#[cfg(feature = "avif")]
+fn encode_avif(
+    img: &DynamicImage,
+    quality: u8,
+    speed: u8,
+    metadata: &ImageMetadata,
+    buf: &mut Cursor<Vec<u8>>,
+) -> Result<()> {
+    let mut encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(
+        buf,
+        speed.clamp(1, 10),
+        quality.clamp(1, 100),
+    );
+    apply_metadata(&mut encoder, metadata);
+    DynamicImage::ImageRgba8(img.to_rgba8())
+        .write_with_encoder(encoder)
+        .map_err(|e| AppError::Encode(tr!("codec.encode", format = "AVIF", error = e)).into())
+}
+
+#[cfg(not(feature = "avif"))]
+fn encode_avif(
+    _img: &DynamicImage,
+    _quality: u8,
+    _speed: u8,
+    _metadata: &ImageMetadata,
+    _buf: &mut Cursor<Vec<u8>>,
+) -> Result<()> {
+    Err(AppError::Encode(tr!("feature.avif")).into())
+}
+
+/// WebP sans métadonnées ; elles sont ajoutées ensuite par `mux_webp_metadata`.
+fn encode_webp(img: &DynamicImage, lossless: bool, quality: u8) -> Result<Vec<u8>> {
+    let rgba = DynamicImage::ImageRgba8(img.to_rgba8());
+
+    if lossless {
+        let mut buf = Cursor::new(Vec::new());
+        rgba.write_with_encoder(image::codecs::webp::WebPEncoder::new_lossless(&mut buf))
+            .map_err(|e| AppError::Encode(tr!("codec.encode", format = "WebP", error = e)))?;
+        return Ok(buf.into_inner());
+    }
+
+    encode_webp_lossy(&rgba, quality)
+}
+
+#[cfg(feature = "webp-lossy")]
+fn encode_webp_lossy(rgba: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let encoded = webp::Encoder::from_rgba(rgba.as_bytes(), rgba.width(), rgba.height())
+        .encode(quality.clamp(1, 100) as f32);
+    Ok(encoded.to_vec())
+}
+
+#[cfg(not(feature = "webp-lossy"))]
+fn encode_webp_lossy(_rgba: &DynamicImage, _quality: u8) -> Result<Vec<u8>> {
+    Err(AppError::Encode(tr!("feature.lossy_webp")).into())
+}
+
+/// Réécrit le conteneur RIFF WebP en format étendu (VP8X) pour y joindre
+/// ICC, EXIF et XMP, dans l'ordre imposé par la spécification.
+fn mux_webp_metadata(webp: Vec<u8>, img: &DynamicImage, metadata: &ImageMetadata) -> Vec<u8> {
+    if metadata.icc_profile.is_none() && metadata.exif.is_none() && metadata.xmp.is_none() {
+        return webp;
+    }
+    if webp.len() < 12 || &webp[0..4] != b"RIFF" || &webp[8..12] != b"WEBP" {
+        return webp;
+    }
+
+    // Chunks image (ALPH, VP8, VP8L) conservés tels quels ; VP8X et anciennes
+    // métadonnées sont reconstruits.
+    let mut image_chunks = Vec::new();
+    let mut at = 12;
+    while at + 8 <= webp.len() {
+        let fourcc = &webp[at..at + 4];
+        let len = u32::from_le_bytes([webp[at + 4], webp[at + 5], webp[at + 6], webp[at + 7]]) as usize;
+        let end = (at + 8 + len + (len & 1)).min(webp.len());
+        if matches!(fourcc, b"ALPH" | b"VP8 " | b"VP8L") {
+            image_chunks.extend_from_slice(&webp[at..end]);
+        }
+        at = end;
+    }
+
+    fn push_chunk(out: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(fourcc);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+
+    let mut flags = 0u8;
+    if metadata.icc_profile.is_some() {
+        flags |= 0x20;
+    }
+    if img.color().has_alpha() {
+        flags |= 0x10;
+    }
+    if metadata.exif.is_some() {
+        flags |= 0x08;
+    }
+    if metadata.xmp.is_some() {
+        flags |= 0x04;
+    }
+
+    let mut vp8x = vec![flags, 0, 0, 0];
+    vp8x.extend_from_slice(&(img.width() - 1).to_le_bytes()[..3]);
+    vp8x.extend_from_slice(&(img.height() - 1).to_le_bytes()[..3]);
+
+    let mut body = b"WEBP".to_vec();
+    push_chunk(&mut body, b"VP8X", &vp8x);
+    if let Some(icc) = &metadata.icc_profile {
+        push_chunk(&mut body, b"ICCP", icc);
+    }
+    body.extend_from_slice(&image_chunks);
+    if let Some(exif) = &metadata.exif {
+        push_chunk(&mut body, b"EXIF", exif);
+    }
+    if let Some(xmp) = &metadata.xmp {
+        push_chunk(&mut body, b"XMP ", xmp);
+    }
+
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(&body);
+    out
+}
+
+/// Ajoute le paquet XMP en chunk iTXt `XML:com.adobe.xmp` avant le premier
+/// IDAT, là où les lecteurs le cherchent (l'encodeur PNG du crate `image` ne
+/// sait pas l'écrire).
+fn insert_png_xmp(png: &mut Vec<u8>, xmp: &[u8]) {
+    // Signature (8 octets) puis chunks : longueur (4) | type (4) | données | CRC (4)
+    let mut at = 8;
+    while at + 8 <= png.len() && &png[at + 4..at + 8] != b"IDAT" {
+        let len = u32::from_be_bytes([png[at], png[at + 1], png[at + 2], png[at + 3]]) as usize;
+        at += len + 12;
+    }
+    if at + 8 > png.len() {
+        return;
+    }
+
+    // keyword \0 | compression 0 | méthode 0 | langue "" \0 | mot-clé traduit "" \0 | texte
+    let mut data = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+    data.extend_from_slice(xmp);
+
+    let mut chunk = Vec::with_capacity(data.len() + 12);
+    chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(b"iTXt");
+    chunk.extend_from_slice(&data);
+    chunk.extend_from_slice(&crc32fast::hash(&chunk[4..]).to_be_bytes());
+
+    png.splice(at..at, chunk);
+}
+
+/// Valeur TIFF de type UNDEFINED (7), requise pour le tag ICCProfile.
+struct TiffUndefined<'a>(&'a [u8]);
+
+impl tiff::encoder::TiffValue for TiffUndefined<'_> {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: tiff::tags::Type = tiff::tags::Type::UNDEFINED;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> std::borrow::Cow<'_, [u8]> {
+        std::borrow::Cow::Borrowed(self.0)
+    }
+}
+
+fn write_tiff_image<W, C>(
+    encoder: &mut tiff::encoder::TiffEncoder<W>,
+    (w, h): (u32, u32),
+    data: &[C::Inner],
+    has_alpha: bool,
+    metadata: &ImageMetadata,
+    page_name: Option<&str>,
+) -> tiff::TiffResult<()>
+where
+    W: std::io::Write + Seek,
+    C: tiff::encoder::colortype::ColorType,
+    [C::Inner]: tiff::encoder::TiffValue,
+{
+    use tiff::tags::Tag;
+
+    let mut image = encoder.new_image::<C>(w, h)?;
+    let dir = image.encoder();
+    if has_alpha {
+        dir.write_tag(Tag::ExtraSamples, 2u16)?; // 2 = alpha non prémultiplié
+    }
+    if let Some(icc) = &metadata.icc_profile {
+        dir.write_tag(Tag::IccProfile, TiffUndefined(icc))?;
+    }
+    if let Some(xmp) = &metadata.xmp {
+        dir.write_tag(Tag::Unknown(700), xmp.as_slice())?; // XMLPacket
+    }
+    if let Some(name) = page_name {
+        // PageName, nom du calque pour GIMP : ASCII seulement (noms traduits)
+        let name: String = name.chars().map(|c| if c.is_ascii() { c } else { '_' }).collect();
+        dir.write_tag(Tag::Unknown(285), name.as_str())?;
+    }
+    image.write_data(data)
+}
+
+/// TIFF via le crate `tiff` : contrairement à l'encodeur de `image`, il permet
+/// d'écrire le profil ICC et de déclarer le canal alpha (ExtraSamples).
+/// 8 bits, 16 bits ou flottant 32 bits selon la source.
+fn encode_tiff<W: std::io::Write + Seek>(
+    img: &DynamicImage,
+    metadata: &ImageMetadata,
+    writer: W,
+) -> tiff::TiffResult<()> {
+    let mut encoder = tiff::encoder::TiffEncoder::new(writer)?;
+    write_tiff_page(&mut encoder, img, metadata, None)
+}
+
+/// Ajoute une page (IFD) au TIFF en cours d'écriture, nommée si `page_name`.
+pub(crate) fn write_tiff_page<W: std::io::Write + Seek>(
+    encoder: &mut tiff::encoder::TiffEncoder<W>,
+    img: &DynamicImage,
+    metadata: &ImageMetadata,
+    page_name: Option<&str>,
+) -> tiff::TiffResult<()> {
+    use tiff::encoder::colortype;
+
+    let dims = (img.width(), img.height());
+
+    match img {
+        DynamicImage::ImageLuma8(gray) => {
+            write_tiff_image::<_, colortype::Gray8>(encoder, dims, gray, false, metadata, page_name)
+        }
+        DynamicImage::ImageLuma16(gray) => {
+            write_tiff_image::<_, colortype::Gray16>(encoder, dims, gray, false, metadata, page_name)
+        }
+        DynamicImage::ImageRgb32F(rgb) => {
+            write_tiff_image::<_, colortype::RGB32Float>(encoder, dims, rgb, false, metadata, page_name)
+        }
+        DynamicImage::ImageRgba32F(rgba) => {
+            write_tiff_image::<_, colortype::RGBA32Float>(encoder, dims, rgba, true, metadata, page_name)
+        }
+        _ if is_16bit(img) && img.color().has_alpha() => {
+            let rgba = img.to_rgba16();
+            write_tiff_image::<_, colortype::RGBA16>(encoder, dims, &rgba, true, metadata, page_name)
+        }
+        _ if is_16bit(img) => {
+            let rgb = img.to_rgb16();
+            write_tiff_image::<_, colortype::RGB16>(encoder, dims, &rgb, false, metadata, page_name)
+        }
+        _ if img.color().has_alpha() => {
+            let rgba = img.to_rgba8();
+            write_tiff_image::<_, colortype::RGBA8>(encoder, dims, &rgba, true, metadata, page_name)
+        }
+        _ => {
+            let rgb = img.to_rgb8();
+            write_tiff_image::<_, colortype::RGB8>(encoder, dims, &rgb, false, metadata, page_name)
+        }
+    }
+}
+
+/// Encode une DynamicImage en PNG dans un Vec<u8> (sans métadonnées).
+pub fn encode_png(img: &DynamicImage) -> Result<Vec<u8>> {
+    encode_output(img, &OutputFormat::default(), &ImageMetadata::default())
+}
+
 /// Encode en PNG puis encode en base64 (pour transfert frontend ↔ backend).
-pub fn encode_base64_png(img: &DynamicImage) -> Result<String> {
-    let png_bytes = encode_png(img)?;
+/// Les métadonnées voyagent dans le PNG et sont relues à la sauvegarde.
+pub fn encode_base64_png(img: &DynamicImage, metadata: &ImageMetadata) -> Result<String> {
+    let png_bytes = encode_output(img, &OutputFormat::default(), metadata)?;
     Ok(format!("data:image/png;base64,{}", STANDARD.encode(&png_bytes)))
 }
 
-/// Sauvegarde une DynamicImage en PNG sur le disque.
-pub fn save_png(img: &DynamicImage, dest: &Path) -> Result<()> {
-    img.save_with_format(dest, image::ImageFormat::Png)
-        .map_err(|e| anyhow!("Sauvegarde PNG vers {} : {e}", dest.display()))
+/// Sauvegarde une DynamicImage sur le disque dans le format demandé.
+/// L'extension de `dest` est corrigée si besoin ; retourne le chemin écrit.
+pub fn save_output(
+    img: &DynamicImage,
+    dest: &Path,
+    format: &OutputFormat,
+    metadata: &ImageMetadata,
+) -> Result<std::path::PathBuf> {
+    let dest = format.with_extension(dest);
+    let bytes = encode_output(img, format, metadata)?;
+    std::fs::write(&dest, bytes)
+        .map_err(|e| AppError::Io(tr!("io.save", path = dest.display(), error = e)))?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ─── Import de masque ───────────────────────────────────────────────────
+
+    #[test]
+    fn mask_import_checks_format_size_and_limit() {
+        let dir = std::env::temp_dir().join(format!("pureremove-mask-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mask = dir.join("mask.png");
+        GrayImage::from_pixel(40, 30, image::Luma([200])).save(&mask).unwrap();
+        // Extension trompeuse : le contenu fait foi
+        let pdf = dir.join("mask_pdf.png");
+        std::fs::write(&pdf, b"%PDF-1.7\n").unwrap();
+
+        let loaded = load_mask(&mask, 40, 30, DEFAULT_MAX_MEGAPIXELS);
+        let mismatch = load_mask(&mask, 30, 30, DEFAULT_MAX_MEGAPIXELS);
+        let too_large = load_mask(&mask, 40, 30, 0.001);
+        let unsupported = load_mask(&pdf, 40, 30, DEFAULT_MAX_MEGAPIXELS);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.unwrap().get_pixel(0, 0)[0], 200);
+        let code = |r: Result<GrayImage>| AppError::from(r.expect_err("erreur attendue")).code();
+        assert_eq!(code(mismatch), "INVALID_INPUT");
+        assert_eq!(code(too_large), "IMAGE_TOO_LARGE");
+        assert_eq!(code(unsupported), "UNSUPPORTED_FORMAT");
+    }
+
+    // ─── Orientation EXIF ───────────────────────────────────────────────────
+
+    /// JPEG 32×16 noir avec un carré rouge 8×8 dans le coin haut-gauche,
+    /// et un segment APP1 EXIF ne contenant que le tag Orientation.
+    fn jpeg_with_orientation(orientation: u16) -> Vec<u8> {
+        let img = image::RgbImage::from_fn(32, 16, |x, y| {
+            image::Rgb(if x < 8 && y < 8 { [255, 0, 0] } else { [0, 0, 0] })
+        });
+        let mut jpeg = Vec::new();
+        img.write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg).unwrap();
+
+        // TIFF big-endian : en-tête, un IFD d'une entrée (0x0112, SHORT, 1)
+        let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend_from_slice(&tiff);
+
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&(app1.len() as u16 + 2).to_be_bytes());
+        out.extend_from_slice(&app1);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    fn decode(bytes: Vec<u8>) -> DynamicImage {
+        let reader = image::ImageReader::new(Cursor::new(bytes)).with_guessed_format().unwrap();
+        decode_checked(reader, DEFAULT_MAX_MEGAPIXELS).unwrap().0
+    }
+
+    #[test]
+    fn exif_orientations() {
+        // (orientation, dimensions attendues, centre du carré rouge)
+        let cases = [
+            (1, (32, 16), (4, 4)),
+            (2, (32, 16), (27, 4)),
+            (3, (32, 16), (27, 11)),
+            (4, (32, 16), (4, 11)),
+            (5, (16, 32), (4, 4)),
+            (6, (16, 32), (11, 4)),
+            (7, (16, 32), (11, 27)),
+            (8, (16, 32), (4, 27)),
+        ];
+        for (orientation, dimensions, (x, y)) in cases {
+            let img = decode(jpeg_with_orientation(orientation)).to_rgb8();
+            assert_eq!(img.dimensions(), dimensions, "orientation {orientation}");
+            let px = img.get_pixel(x, y);
+            assert!(
+                px[0] > 200 && px[1] < 60 && px[2] < 60,
+                "orientation {orientation} : {px:?} en ({x}, {y})"
+            );
+        }
+    }
+
+    // ─── Composition linéaire ───────────────────────────────────────────────
+
+    /// Fonctions de transfert sRGB de référence, en f64, indépendantes des
+    /// tables de conversion du module.
+    fn reference_to_linear(c: f64) -> f64 {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    fn reference_to_srgb(l: f64) -> f64 {
+        if l <= 0.003_130_8 {
+            l * 12.92
+        } else {
+            1.055 * l.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    const ALPHAS: [u8; 4] = [0, 1, 128, 255];
+    const BG: [u8; 3] = [20, 180, 240];
+
+    fn background() -> BackgroundColor {
+        BackgroundColor::Color { r: BG[0], g: BG[1], b: BG[2] }
+    }
+
+    /// Une ligne de pixels identiques, un par valeur de masque de `ALPHAS`.
+    fn row<T: Channel>(px: [T; 4]) -> (ImageBuffer<Rgba<T>, Vec<T>>, GrayImage)
+    where
+        Rgba<T>: image::Pixel<Subpixel = T>,
+    {
+        let src = ImageBuffer::from_fn(ALPHAS.len() as u32, 1, |_, _| Rgba(px));
+        let mask =
+            GrayImage::from_fn(ALPHAS.len() as u32, 1, |x, _| image::Luma([ALPHAS[x as usize]]));
+        (src, mask)
+    }
+
+    /// Valeur attendue (échelle 0..1 sRGB) d'une composante `fg` (sRGB 0..1)
+    /// mélangée au fond sRGB 8 bits `bg` avec l'alpha `m / 255`.
+    fn expected_srgb(fg: f64, bg: u8, m: u8) -> f64 {
+        let a = m as f64 / 255.0;
+        let bg = reference_to_linear(bg as f64 / 255.0);
+        reference_to_srgb(reference_to_linear(fg) * a + bg * (1.0 - a))
+    }
+
+    #[test]
+    fn linear_composite_8bit() {
+        let fg = [200u8, 100, 50];
+        let (src, mask) = row([fg[0], fg[1], fg[2], 255]);
+        let out = composite_linear(&src, &mask, &background());
+        for (x, &m) in ALPHAS.iter().enumerate() {
+            let px = out.get_pixel(x as u32, 0);
+            for c in 0..3 {
+                let want = expected_srgb(fg[c] as f64 / 255.0, BG[c], m) * 255.0;
+                let got = px[c] as f64;
+                assert!((got - want).abs() <= 1.0, "m={m} c={c} : {got} au lieu de {want:.2}");
+            }
+            assert_eq!(px[3], 255);
+        }
+
+        // Sans fond : couleur intacte, alpha = masque
+        let out = composite_linear(&src, &mask, &BackgroundColor::Transparent);
+        for (x, &m) in ALPHAS.iter().enumerate() {
+            let px = out.get_pixel(x as u32, 0);
+            assert_eq!(&px.0[..3], &fg);
+            assert_eq!(px[3], m);
+        }
+    }
+
+    #[test]
+    fn linear_composite_16bit() {
+        let fg = [51_400u16, 25_700, 12_850];
+        let (src, mask) = row([fg[0], fg[1], fg[2], u16::MAX]);
+        let out = composite_linear(&src, &mask, &background());
+        for (x, &m) in ALPHAS.iter().enumerate() {
+            let px = out.get_pixel(x as u32, 0);
+            for c in 0..3 {
+                let want = expected_srgb(fg[c] as f64 / 65_535.0, BG[c], m) * 65_535.0;
+                let got = px[c] as f64;
+                assert!((got - want).abs() <= 1.0, "m={m} c={c} : {got} au lieu de {want:.2}");
+            }
+            assert_eq!(px[3], u16::MAX);
+        }
+
+        let out = composite_linear(&src, &mask, &BackgroundColor::Transparent);
+        for (x, &m) in ALPHAS.iter().enumerate() {
+            let want = (m as f64 / 255.0 * 65_535.0).round() as u16;
+            assert!(out.get_pixel(x as u32, 0)[3].abs_diff(want) <= 1);
+        }
+    }
+
+    #[test]
+    fn linear_composite_f32() {
+        // Les images flottantes sont déjà linéaires : seul le fond est converti
+        let fg = [0.8f32, 0.3, 2.5];
+        let (src, mask) = row([fg[0], fg[1], fg[2], 1.0]);
+        let out = composite_linear(&src, &mask, &background());
+        for (x, &m) in ALPHAS.iter().enumerate() {
+            let px = out.get_pixel(x as u32, 0);
+            let a = m as f64 / 255.0;
+            for c in 0..3 {
+                let bg = reference_to_linear(BG[c] as f64 / 255.0);
+                let want = fg[c] as f64 * a + bg * (1.0 - a);
+                let got = px[c] as f64;
+                assert!((got - want).abs() <= 1e-5, "m={m} c={c} : {got} au lieu de {want}");
+            }
+            assert_eq!(px[3], 1.0);
+        }
+    }
+
+    // ─── Détection du format ────────────────────────────────────────────────
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn gzip_is_svg_only_with_svg_inside() {
+        let svg = b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+        assert_eq!(sniff_format(&gzip(svg)), SniffedFormat::Svg);
+        assert_eq!(sniff_format(&gzip(b"journal.log")), SniffedFormat::Other("GZIP"));
+
+        // En-tête tronqué comme celui lu sur disque
+        let mut long = b"<svg>".to_vec();
+        long.extend((0..20_000u32).flat_map(|i| i.to_le_bytes()));
+        let header = &gzip(&long)[..64];
+        assert_eq!(sniff_format(header), SniffedFormat::Svg);
+    }
 }
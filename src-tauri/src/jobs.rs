@@ -0,0 +1,679 @@
+//! jobs.rs — File d'attente des traitements (image seule, presse-papier, lot,
+//! animation, pages, vidéo, dossiers surveillés). Un seul thread de travail :
+//! le modèle n'a qu'une session, deux lots ne s'entrelacent plus.
+//!
+//! - Priorités : les traitements interactifs (image seule, presse-papier)
+//!   passent devant les lots ; à priorité égale, ordre d'arrivée.
+//! - Un traitement long appelle `JobContext::checkpoint` entre deux unités
+//!   (fichier, image, page) : c'est là qu'il est annulé, mis en pause, ou
+//!   qu'il laisse passer un traitement plus prioritaire arrivé entre-temps.
+//!   Il rend alors son tour : un autre thread de travail exécute la suite de
+//!   la file pendant que le sien attend, sans rien calculer, de le reprendre.
+//! - Chaque étape du cycle de vie est émise (`JobEvent`) vers l'écouteur
+//!   installé au démarrage (événement `job-event`), ainsi que le début et la
+//!   durée de chaque étape de calcul (`Stage`) et, à chaque unité terminée,
+//!   le débit glissant et le temps restant estimé.
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::error::{AppError, AppResult};
+use crate::tr;
+
+/// Identifiant d'un traitement, unique pendant la session.
+pub type JobId = u64;
+
+/// Unités récentes prises en compte pour le débit et le temps restant.
+const THROUGHPUT_WINDOW: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Single,
+    Clipboard,
+    Batch,
+    Animation,
+    Pages,
+    Video,
+    /// Fichier d'un dossier surveillé (voir watch.rs).
+    Watch,
+}
+
+/// Ordre croissant : `Interactive` passe devant `Background`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Background,
+    Interactive,
+}
+
+impl JobKind {
+    pub fn priority(self) -> JobPriority {
+        match self {
+            JobKind::Single | JobKind::Clipboard => JobPriority::Interactive,
+            _ => JobPriority::Background,
+        }
+    }
+}
+
+/// Étape du cycle de vie, émise dans `JobEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobEventKind {
+    Queued,
+    Started,
+    Progress,
+    Paused,
+    Resumed,
+    StageStarted,
+    StageFinished,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Étape de calcul d'une unité, dans l'ordre du pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Decode,
+    Preprocess,
+    Inference,
+    Refine,
+    Composite,
+    Encode,
+    Save,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct StageTiming {
+    pub stage: Stage,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub name: String,
+    pub event: JobEventKind,
+    /// Unités terminées (fichiers, images, pages).
+    pub done: usize,
+    /// Inconnu pour une vidéo lue en flux.
+    pub total: Option<usize>,
+    /// Avec `StageStarted` / `StageFinished`.
+    pub stage: Option<Stage>,
+    /// Durée de l'étape, avec `StageFinished`.
+    pub duration_ms: Option<f64>,
+    /// Unités par seconde sur les dernières unités, avec `Progress`.
+    pub throughput: Option<f64>,
+    /// Temps restant estimé, avec `Progress` quand `total` est connu.
+    pub eta_ms: Option<u64>,
+    /// Avec `Failed`.
+    pub error: Option<AppError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Paused,
+}
+
+/// État d'un traitement en attente ou en cours (`list_jobs`).
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub name: String,
+    pub priority: JobPriority,
+    pub state: JobState,
+    pub done: usize,
+    pub total: Option<usize>,
+}
+
+// ─── Écouteur ────────────────────────────────────────────────────────────────
+
+type Listener = Box<dyn Fn(&JobEvent) + Send + Sync>;
+
+static LISTENER: OnceCell<Listener> = OnceCell::new();
+
+/// Installe la destination des événements (une fois, au démarrage).
+pub fn set_listener(listener: impl Fn(&JobEvent) + Send + Sync + 'static) {
+    let _ = LISTENER.set(Box::new(listener));
+}
+
+fn emit(job: &Job, event: JobEventKind, error: Option<AppError>) {
+    emit_event(JobEvent { error, ..job.event(event) });
+}
+
+fn emit_event(event: JobEvent) {
+    if let Some(listener) = LISTENER.get() {
+        listener(&event);
+    }
+}
+
+// ─── File d'attente ──────────────────────────────────────────────────────────
+
+/// Travail à exécuter, jusqu'à l'envoi du résultat à la commande.
+type Work = Box<dyn FnOnce(&JobContext) + Send>;
+
+struct Job {
+    id: JobId,
+    kind: JobKind,
+    name: String,
+    done: usize,
+    total: Option<usize>,
+    running: bool,
+    paused: bool,
+    cancelled: bool,
+    /// Démarré, en attente de son tour dans `checkpoint`.
+    parked: bool,
+    /// Durées des dernières unités, pauses exclues.
+    recent: VecDeque<Duration>,
+    /// Pris par le thread de travail au démarrage.
+    work: Option<Work>,
+}
+
+impl Job {
+    fn priority(&self) -> JobPriority {
+        self.kind.priority()
+    }
+
+    /// Rang dans la file : le plus prioritaire, puis le plus ancien.
+    fn rank(&self) -> (std::cmp::Reverse<JobPriority>, JobId) {
+        (std::cmp::Reverse(self.priority()), self.id)
+    }
+
+    fn event(&self, event: JobEventKind) -> JobEvent {
+        JobEvent {
+            id: self.id,
+            kind: self.kind,
+            name: self.name.clone(),
+            event,
+            done: self.done,
+            total: self.total,
+            stage: None,
+            duration_ms: None,
+            throughput: None,
+            eta_ms: None,
+            error: None,
+        }
+    }
+
+    /// Débit (unités/s) et temps restant sur la fenêtre récente.
+    fn estimate(&self) -> (Option<f64>, Option<u64>) {
+        let elapsed: Duration = self.recent.iter().sum();
+        if self.recent.is_empty() || elapsed.is_zero() {
+            return (None, None);
+        }
+        let per_unit = elapsed / self.recent.len() as u32;
+        let remaining = self.total.map(|total| total.saturating_sub(self.done) as u32);
+        let eta = remaining.map(|n| (per_unit * n).as_millis() as u64);
+        (Some(self.recent.len() as f64 / elapsed.as_secs_f64()), eta)
+    }
+
+    fn info(&self) -> JobInfo {
+        let state = match (self.paused, self.running) {
+            (true, _) => JobState::Paused,
+            (false, true) => JobState::Running,
+            (false, false) => JobState::Queued,
+        };
+        JobInfo {
+            id: self.id,
+            kind: self.kind,
+            name: self.name.clone(),
+            priority: self.priority(),
+            state,
+            done: self.done,
+            total: self.total,
+        }
+    }
+
+    /// Copie sans le travail, pour émettre hors du verrou.
+    fn snapshot(&self) -> Job {
+        Job {
+            id: self.id,
+            kind: self.kind,
+            name: self.name.clone(),
+            done: self.done,
+            total: self.total,
+            running: self.running,
+            paused: self.paused,
+            cancelled: self.cancelled,
+            parked: self.parked,
+            recent: self.recent.clone(),
+            work: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    next_id: JobId,
+    /// Dans l'ordre d'arrivée.
+    jobs: Vec<Job>,
+    /// Seul traitement qui calcule ; les autres démarrés attendent leur tour.
+    turn: Option<JobId>,
+    /// Threads de travail libres, en attente d'un traitement à démarrer.
+    idle: usize,
+}
+
+impl Queue {
+    fn get_mut(&mut self, id: JobId) -> AppResult<&mut Job> {
+        self.jobs.iter_mut().find(|j| j.id == id).ok_or(AppError::JobNotFound { id })
+    }
+
+    /// Prochain traitement à qui revient le tour : non démarré, ou démarré
+    /// et en attente dans `checkpoint`, hors pause.
+    fn next_turn(&self) -> Option<&Job> {
+        self.jobs
+            .iter()
+            .filter(|j| !j.paused && !j.cancelled && (j.work.is_some() || j.parked))
+            .min_by_key(|j| j.rank())
+    }
+
+    /// Démarre le prochain traitement si le tour est libre et lui revient.
+    fn start_next(&mut self) -> Option<(Job, Work)> {
+        if self.turn.is_some() {
+            return None;
+        }
+        let id = self.next_turn().filter(|j| !j.running)?.id;
+        let job = self.get_mut(id).ok()?;
+        job.running = true;
+        let work = job.work.take()?;
+        let snapshot = job.snapshot();
+        self.turn = Some(id);
+        Some((snapshot, work))
+    }
+
+    /// Le tour de `current` revient-il à un autre : plus prioritaire, ou démarré
+    /// avant lui et repris depuis ?
+    fn should_yield(&self, current: &Job) -> bool {
+        self.next_turn().is_some_and(|next| {
+            next.priority() > current.priority() || next.parked && next.rank() < current.rank()
+        })
+    }
+}
+
+static QUEUE: Lazy<(Mutex<Queue>, Condvar)> =
+    Lazy::new(|| (Mutex::new(Queue::default()), Condvar::new()));
+
+static WORKER: OnceCell<()> = OnceCell::new();
+
+fn queue() -> MutexGuard<'static, Queue> {
+    QUEUE.0.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn wait(guard: MutexGuard<'static, Queue>) -> MutexGuard<'static, Queue> {
+    QUEUE.1.wait(guard).unwrap_or_else(|e| e.into_inner())
+}
+
+fn notify() {
+    QUEUE.1.notify_all();
+}
+
+/// Résultat d'un traitement soumis, à attendre depuis une commande.
+pub struct JobHandle<T> {
+    pub id: JobId,
+    receiver: oneshot::Receiver<AppResult<T>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Attend la fin ; `Cancelled` si le traitement a été annulé avant de démarrer.
+    pub async fn wait(self) -> AppResult<T> {
+        self.receiver.await.unwrap_or(Err(AppError::Cancelled))
+    }
+}
+
+/// Met `work` en file. `total` : nombre d'unités, s'il est connu.
+pub fn submit<T, F>(kind: JobKind, name: String, total: Option<usize>, work: F) -> JobHandle<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&JobContext) -> AppResult<T> + Send + 'static,
+{
+    WORKER.get_or_init(|| spawn_worker(&mut queue()));
+
+    let (sender, receiver) = oneshot::channel();
+    let work: Work = Box::new(move |ctx| {
+        // Une panique ne doit pas emporter le thread de travail
+        let result = catch_unwind(AssertUnwindSafe(|| work(ctx))).unwrap_or_else(|_| {
+            Err(AppError::Internal(tr!("error.task_aborted", error = tr!("jobs.panic"))))
+        });
+        // Retiré de la file avant de rendre la main à la commande
+        finish(ctx.id, result.as_ref().err().cloned());
+        let _ = sender.send(result);
+    });
+
+    let id = {
+        let mut queue = queue();
+        queue.next_id += 1;
+        queue.next_id
+    };
+    let job = Job {
+        id,
+        kind,
+        name,
+        done: 0,
+        total,
+        running: false,
+        paused: false,
+        cancelled: false,
+        parked: false,
+        recent: VecDeque::new(),
+        work: Some(work),
+    };
+    // Émis avant l'ajout : `Queued` précède toujours `Started`
+    emit(&job, JobEventKind::Queued, None);
+    queue().jobs.push(job);
+    notify();
+    JobHandle { id, receiver }
+}
+
+/// Ajoute un thread de travail, compté libre dès maintenant.
+fn spawn_worker(queue: &mut Queue) {
+    queue.idle += 1;
+    std::thread::Builder::new().name("jobs".into()).spawn(worker).expect("thread de traitement");
+}
+
+fn worker() {
+    loop {
+        let next = {
+            let mut queue = queue();
+            loop {
+                if let Some(next) = queue.start_next() {
+                    queue.idle -= 1;
+                    break next;
+                }
+                queue = wait(queue);
+            }
+        };
+        run(next);
+        // Un autre thread a pris le relais pendant une pause : un seul reste libre
+        let mut queue = queue();
+        if queue.idle > 0 {
+            return;
+        }
+        queue.idle += 1;
+    }
+}
+
+/// Exécute un traitement sur le thread courant, du démarrage à l'événement final.
+fn run((job, work): (Job, Work)) {
+    emit(&job, JobEventKind::Started, None);
+    work(&JobContext::new(job.id));
+}
+
+/// Retire le traitement terminé et émet l'événement final.
+fn finish(id: JobId, error: Option<AppError>) {
+    let removed = {
+        let mut queue = queue();
+        if queue.turn == Some(id) {
+            queue.turn = None;
+        }
+        let index = queue.jobs.iter().position(|j| j.id == id);
+        index.map(|i| queue.jobs.remove(i))
+    };
+    notify();
+    let Some(mut job) = removed else { return };
+    // Une vidéo annulée se termine normalement (images déjà écrites) : annulée quand même
+    match error {
+        Some(AppError::Cancelled) => emit(&job, JobEventKind::Cancelled, None),
+        None if job.cancelled => emit(&job, JobEventKind::Cancelled, None),
+        None => {
+            job.done = job.total.unwrap_or(job.done);
+            emit(&job, JobEventKind::Completed, None)
+        }
+        Some(error) => emit(&job, JobEventKind::Failed, Some(error)),
+    }
+}
+
+// ─── Côté traitement ─────────────────────────────────────────────────────────
+
+/// Passé au travail soumis : progression, étapes et points d'arrêt.
+pub struct JobContext {
+    id: JobId,
+    /// Début de l'unité en cours.
+    unit_started: Cell<Instant>,
+    /// Temps passé dans `checkpoint` (pause, traitements prioritaires)
+    /// depuis le début de l'unité, exclu de sa durée.
+    unit_excluded: Cell<Duration>,
+    /// Étapes de l'unité en cours (voir `take_timings`).
+    timings: RefCell<Vec<StageTiming>>,
+}
+
+impl JobContext {
+    fn new(id: JobId) -> Self {
+        Self {
+            id,
+            unit_started: Cell::new(Instant::now()),
+            unit_excluded: Cell::new(Duration::ZERO),
+            timings: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Exécute `f` comme étape `stage` : émet son début, puis sa durée.
+    pub fn stage<T>(&self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        self.emit_stage(JobEventKind::StageStarted, stage, None);
+        let started = Instant::now();
+        let value = f();
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+        self.timings.borrow_mut().push(StageTiming { stage, duration_ms });
+        self.emit_stage(JobEventKind::StageFinished, stage, Some(duration_ms));
+        value
+    }
+
+    /// Étapes mesurées depuis le dernier appel.
+    pub fn take_timings(&self) -> Vec<StageTiming> {
+        self.timings.take()
+    }
+
+    fn emit_stage(&self, event: JobEventKind, stage: Stage, duration_ms: Option<f64>) {
+        let Some(event) = queue().get_mut(self.id).ok().map(|job| job.event(event)) else {
+            return;
+        };
+        emit_event(JobEvent { stage: Some(stage), duration_ms, ..event });
+    }
+
+    /// Entre deux unités : rend le tour aux traitements plus prioritaires en
+    /// attente (à tous, pendant une pause) et attend de le reprendre ; renvoie
+    /// `Cancelled` s'il a été annulé, même en attente.
+    pub fn checkpoint(&self) -> AppResult<()> {
+        let started = Instant::now();
+        let result = self.wait_turn();
+        self.unit_excluded.set(self.unit_excluded.get() + started.elapsed());
+        result
+    }
+
+    fn wait_turn(&self) -> AppResult<()> {
+        let mut guard = queue();
+        let job = guard.get_mut(self.id)?;
+        if job.cancelled {
+            return Err(AppError::Cancelled);
+        }
+        let current = job.snapshot();
+        if !current.paused && !guard.should_yield(&current) {
+            return Ok(());
+        }
+
+        // Ce thread reste bloqué ici : un autre démarre la suite de la file
+        guard.get_mut(self.id)?.parked = true;
+        guard.turn = None;
+        if guard.idle == 0 {
+            spawn_worker(&mut guard);
+        }
+        notify();
+        loop {
+            // Réveillé par resume / cancel / fin d'un traitement / soumission
+            guard = wait(guard);
+            let mine = guard.turn.is_none()
+                && guard.next_turn().is_some_and(|next| next.id == self.id);
+            let job = guard.get_mut(self.id)?;
+            if job.cancelled {
+                job.parked = false;
+                return Err(AppError::Cancelled);
+            }
+            if mine {
+                job.parked = false;
+                guard.turn = Some(self.id);
+                return Ok(());
+            }
+        }
+    }
+
+    /// `done` unités terminées ; `total` corrigé s'il est connu entre-temps.
+    /// La durée de l'unité (pauses exclues) alimente débit et temps restant.
+    pub fn progress(&self, done: usize, total: Option<usize>) {
+        let elapsed = self.unit_started.get().elapsed().saturating_sub(self.unit_excluded.get());
+        self.unit_started.set(Instant::now());
+        self.unit_excluded.set(Duration::ZERO);
+
+        let event = {
+            let mut queue = queue();
+            let Ok(job) = queue.get_mut(self.id) else { return };
+            let units = done.saturating_sub(job.done).max(1) as u32;
+            for _ in 0..units {
+                job.recent.push_back(elapsed / units);
+            }
+            while job.recent.len() > THROUGHPUT_WINDOW {
+                job.recent.pop_front();
+            }
+            job.done = done;
+            job.total = total.or(job.total);
+            let (throughput, eta_ms) = job.estimate();
+            JobEvent { throughput, eta_ms, ..job.event(JobEventKind::Progress) }
+        };
+        emit_event(event);
+    }
+}
+
+// ─── Contrôle ────────────────────────────────────────────────────────────────
+
+/// Annule : retiré de la file s'il n'a pas démarré, arrêté au prochain
+/// `checkpoint` sinon.
+pub fn cancel(id: JobId) -> AppResult<()> {
+    let removed = {
+        let mut queue = queue();
+        let job = queue.get_mut(id)?;
+        job.cancelled = true;
+        if job.running {
+            None
+        } else {
+            let index = queue.jobs.iter().position(|j| j.id == id);
+            index.map(|i| queue.jobs.remove(i))
+        }
+    };
+    notify();
+    // Le travail jeté ferme son canal : la commande qui attend reçoit `Cancelled`
+    if let Some(job) = removed {
+        emit(&job, JobEventKind::Cancelled, None);
+    }
+    Ok(())
+}
+
+/// Annule tous les traitements de ce type (ex. `cancel_video_processing`).
+pub fn cancel_kind(kind: JobKind) {
+    let ids: Vec<JobId> = queue().jobs.iter().filter(|j| j.kind == kind).map(|j| j.id).collect();
+    for id in ids {
+        let _ = cancel(id);
+    }
+}
+
+/// En attente : ne démarrera pas ; en cours : s'arrête au prochain `checkpoint`.
+pub fn pause(id: JobId) -> AppResult<()> {
+    set_paused(id, true)
+}
+
+pub fn resume(id: JobId) -> AppResult<()> {
+    set_paused(id, false)
+}
+
+fn set_paused(id: JobId, paused: bool) -> AppResult<()> {
+    let snapshot = {
+        let mut queue = queue();
+        let job = queue.get_mut(id)?;
+        if job.paused == paused {
+            return Ok(());
+        }
+        job.paused = paused;
+        job.snapshot()
+    };
+    notify();
+    let event = if paused { JobEventKind::Paused } else { JobEventKind::Resumed };
+    emit(&snapshot, event, None);
+    Ok(())
+}
+
+/// Traitements en attente ou en cours, dans l'ordre d'arrivée.
+pub fn list() -> Vec<JobInfo> {
+    queue().jobs.iter().map(Job::info).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_job_gives_the_worker_back() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let first = submit(JobKind::Batch, "a".into(), None, |ctx| {
+            pause(ctx.id())?;
+            ctx.checkpoint()
+        });
+        let second = submit(JobKind::Batch, "b".into(), None, move |_| {
+            sender.send(()).map_err(|e| AppError::Internal(e.to_string()))
+        });
+
+        // Même priorité : le second démarre pendant la pause du premier
+        receiver.recv_timeout(Duration::from_secs(10)).expect("second traitement bloqué");
+        resume(first.id).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            second.wait().await.unwrap();
+            first.wait().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn paused_job_cancelled_while_a_long_job_runs() {
+        let (started, on_started) = std::sync::mpsc::channel();
+        let (release, on_release) = std::sync::mpsc::channel::<()>();
+        let first = submit(JobKind::Batch, "a".into(), None, |ctx| {
+            pause(ctx.id())?;
+            ctx.checkpoint()
+        });
+        let long = submit(JobKind::Batch, "b".into(), None, move |ctx| {
+            let _ = started.send(());
+            while on_release.recv_timeout(Duration::from_millis(10)).is_err() {
+                ctx.checkpoint()?;
+            }
+            Ok(())
+        });
+        on_started.recv_timeout(Duration::from_secs(10)).expect("second traitement bloqué");
+
+        // L'annulation aboutit sans attendre la fin du traitement long
+        cancel(first.id).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            let _ = sender.send(runtime.block_on(first.wait()));
+        });
+        let result = receiver.recv_timeout(Duration::from_secs(10)).expect("annulation bloquée");
+        assert!(matches!(result, Err(AppError::Cancelled)));
+
+        release.send(()).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(long.wait()).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+//! layers.rs — Export en calques pour la retouche : OpenRaster (.ora) et TIFF
+//! multipage dont chaque page est un calque nommé (détourage, masque,
+//! original, fond), ou PSD (voir psd.rs), au lieu d'un PNG aplati.
+
+use anyhow::Result;
+use image::{DynamicImage, GrayImage, RgbImage};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::image_processor::{
+    apply_refined_mask, encode_png, write_tiff_page, BackgroundColor, CompositingSpace, ImageMetadata,
+};
+use crate::psd::encode_psd;
+use crate::tr;
+
+/// Conteneur de l'export en calques.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+pub enum LayerFormat {
+    /// OpenRaster : GIMP, Krita, MyPaint.
+    #[default]
+    Ora,
+    /// TIFF multipage, une page nommée par calque (« ouvrir en calques » dans GIMP).
+    Tiff,
+    /// Photoshop : l'original avec le masque en masque de fusion modifiable.
+    Psd,
+}
+
+impl LayerFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            LayerFormat::Ora => "ora",
+            LayerFormat::Tiff => "tiff",
+            LayerFormat::Psd => "psd",
+        }
+    }
+
+    /// Corrige l'extension de `dest` si elle ne correspond pas au format.
+    pub fn with_extension(self, dest: &Path) -> PathBuf {
+        let matches = dest
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| {
+                e.eq_ignore_ascii_case(self.extension())
+                    || (matches!(self, LayerFormat::Tiff) && e.eq_ignore_ascii_case("tif"))
+            });
+        if matches {
+            dest.to_path_buf()
+        } else {
+            dest.with_extension(self.extension())
+        }
+    }
+}
+
+/// Options de l'export en calques (toutes facultatives côté frontend).
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct LayerOptions {
+    pub format: LayerFormat,
+    /// Fond placé sous le détourage ; aucun calque de fond si transparent.
+    pub background: BackgroundColor,
+    pub compositing: CompositingSpace,
+}
+
+impl Default for LayerOptions {
+    fn default() -> Self {
+        Self {
+            format: LayerFormat::Ora,
+            background: BackgroundColor::Transparent,
+            compositing: CompositingSpace::default(),
+        }
+    }
+}
+
+/// Un calque, tous à l'origine (0, 0) et à la taille de l'image.
+pub struct Layer {
+    /// Nom dans la langue courante (catalogue i18n).
+    pub name: String,
+    pub image: DynamicImage,
+    pub visible: bool,
+}
+
+/// Pile de calques, du plus haut au plus bas, et l'image aplatie.
+pub struct LayerStack {
+    pub layers: Vec<Layer>,
+    pub merged: DynamicImage,
+}
+
+impl LayerStack {
+    /// Le masque et l'original sont masqués à l'ouverture : seul le détourage
+    /// sur son fond est visible, comme dans le PNG aplati. `mask` est déjà
+    /// affiné (celui du store) : il est appliqué tel quel.
+    pub fn new(original: &DynamicImage, mask: &GrayImage, options: &LayerOptions) -> Self {
+        let cutout = apply_refined_mask(original, mask, &BackgroundColor::Transparent, options.compositing);
+        let merged = apply_refined_mask(original, mask, &options.background, options.compositing);
+
+        let mut layers = vec![
+            Layer { name: tr!("layer.subject"), image: cutout, visible: true },
+            Layer {
+                name: tr!("layer.mask"),
+                image: DynamicImage::ImageLuma8(mask.clone()),
+                visible: false,
+            },
+            Layer { name: tr!("layer.original"), image: original.clone(), visible: false },
+        ];
+        if let Some(fill) = background_fill(&options.background, original.width(), original.height()) {
+            layers.push(Layer { name: tr!("layer.background"), image: fill, visible: true });
+        }
+        Self { layers, merged }
+    }
+}
+
+fn background_fill(background: &BackgroundColor, w: u32, h: u32) -> Option<DynamicImage> {
+    let rgb = match background {
+        BackgroundColor::Transparent => return None,
+        BackgroundColor::White => [255; 3],
+        BackgroundColor::Black => [0; 3],
+        BackgroundColor::Color { r, g, b } => [*r, *g, *b],
+    };
+    Some(DynamicImage::ImageRgb8(RgbImage::from_pixel(w, h, image::Rgb(rgb))))
+}
+
+// ─── OpenRaster ──────────────────────────────────────────────────────────────
+
+/// Archive ZIP : `mimetype` non compressé en tête, `stack.xml`, un PNG par
+/// calque, `mergedimage.png` et la miniature (256 px max) exigés par la norme.
+pub fn encode_ora(stack: &LayerStack) -> Result<Vec<u8>> {
+    let zip_err = |e: zip::result::ZipError| {
+        AppError::Encode(tr!("codec.encode", format = "OpenRaster", error = e))
+    };
+    let io_err =
+        |e: std::io::Error| AppError::Encode(tr!("codec.encode", format = "OpenRaster", error = e));
+
+    // Les PNG sont déjà compressés : tout est stocké tel quel
+    let stored = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored);
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+    zip.start_file("mimetype", stored).map_err(zip_err)?;
+    zip.write_all(b"image/openraster").map_err(io_err)?;
+
+    let (w, h) = (stack.merged.width(), stack.merged.height());
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <image version=\"0.0.5\" w=\"{w}\" h=\"{h}\">\n  <stack>\n"
+    );
+    for (i, layer) in stack.layers.iter().enumerate() {
+        let visibility = if layer.visible { "visible" } else { "hidden" };
+        xml.push_str(&format!(
+            "    <layer name=\"{}\" src=\"data/layer{i}.png\" x=\"0\" y=\"0\" \
+             opacity=\"1.0\" visibility=\"{visibility}\"/>\n",
+            xml_escape(&layer.name)
+        ));
+    }
+    xml.push_str("  </stack>\n</image>\n");
+    zip.start_file("stack.xml", stored).map_err(zip_err)?;
+    zip.write_all(xml.as_bytes()).map_err(io_err)?;
+
+    for (i, layer) in stack.layers.iter().enumerate() {
+        zip.start_file(format!("data/layer{i}.png"), stored).map_err(zip_err)?;
+        zip.write_all(&encode_png(&layer.image)?).map_err(io_err)?;
+    }
+
+    zip.start_file("mergedimage.png", stored).map_err(zip_err)?;
+    zip.write_all(&encode_png(&stack.merged)?).map_err(io_err)?;
+
+    let thumbnail = if w > 256 || h > 256 {
+        stack.merged.thumbnail(256, 256)
+    } else {
+        stack.merged.clone()
+    };
+    let thumbnail = DynamicImage::ImageRgba8(thumbnail.to_rgba8());
+    zip.start_file("Thumbnails/thumbnail.png", stored).map_err(zip_err)?;
+    zip.write_all(&encode_png(&thumbnail)?).map_err(io_err)?;
+
+    Ok(zip.finish().map_err(zip_err)?.into_inner())
+}
+
+/// Échappe une valeur d'attribut XML (les noms de calques sont traduits).
+fn xml_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// ─── TIFF en calques ─────────────────────────────────────────────────────────
+
+/// Une page par calque, dans l'ordre de la pile, nommée par le tag PageName.
+/// Le profil ICC et le XMP de la source accompagnent les calques en couleur.
+pub fn encode_layered_tiff(stack: &LayerStack, metadata: &ImageMetadata) -> Result<Vec<u8>> {
+    let tiff_err =
+        |e: tiff::TiffError| AppError::Encode(tr!("codec.encode", format = "TIFF", error = e));
+    let mut buf = Cursor::new(Vec::new());
+    let mut encoder = tiff::encoder::TiffEncoder::new(&mut buf).map_err(tiff_err)?;
+
+    let none = ImageMetadata::default();
+    for layer in &stack.layers {
+        let metadata = if layer.image.color().has_color() { metadata } else { &none };
+        write_tiff_page(&mut encoder, &layer.image, metadata, Some(&layer.name)).map_err(tiff_err)?;
+    }
+    Ok(buf.into_inner())
+}
+
+/// Écrit les calques de `original` et du masque affiné `mask` dans `dest`
+/// (extension corrigée) ; retourne le chemin écrit.
+pub fn save_layers(
+    original: &DynamicImage,
+    mask: &GrayImage,
+    options: &LayerOptions,
+    metadata: &ImageMetadata,
+    dest: &Path,
+) -> Result<PathBuf> {
+    let bytes = match options.format {
+        LayerFormat::Ora => encode_ora(&LayerStack::new(original, mask, options))?,
+        LayerFormat::Tiff => encode_layered_tiff(&LayerStack::new(original, mask, options), metadata)?,
+        LayerFormat::Psd => {
+            encode_psd(original, mask, &options.background, options.compositing, metadata)?
+        }
+    };
+    let dest = options.format.with_extension(dest);
+    std::fs::write(&dest, bytes)
+        .map_err(|e| AppError::Io(tr!("io.save", path = dest.display(), error = e)))?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_escape_attribute() {
+        assert_eq!(xml_escape("Fond & \"sujet\""), "Fond &amp; &quot;sujet&quot;");
+        assert_eq!(xml_escape("<l'original>"), "&lt;l&apos;original&gt;");
+        assert_eq!(xml_escape("Détourage"), "Détourage");
+    }
+}
@@ -1,16 +1,49 @@
+pub mod animation;
 pub mod commands;
+pub mod error;
+pub mod i18n;
 pub mod image_processor;
+pub mod jobs;
+pub mod layers;
 pub mod ml_engine;
+pub mod pages;
+pub mod protocol;
+pub mod psd;
+pub mod results;
+pub mod scan;
+pub mod vectorizer;
+pub mod video;
+pub mod watch;
 
 use commands::*;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        // Résultats servis en PNG aux <img> (voir protocol.rs), encodés hors du thread UI
+        .register_asynchronous_uri_scheme_protocol(protocol::SCHEME, |_ctx, request, responder| {
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(protocol::handle(&request));
+            });
+        })
         .setup(|app| {
+            // Polices livrées dans resources/ (texte des SVG), en plus du système
+            if let Ok(dir) = app.path().resource_dir() {
+                image_processor::set_bundled_fonts_dir(dir);
+            }
+            // Cycle de vie des traitements en file (voir jobs.rs)
+            let handle = app.handle().clone();
+            jobs::set_listener(move |event| {
+                let _ = handle.emit("job-event", event);
+            });
+            // Dossiers surveillés : fichiers pris, traités, erreurs (voir watch.rs)
+            let handle = app.handle().clone();
+            watch::set_listener(move |event| {
+                let _ = handle.emit("watch-event", event);
+            });
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.set_icon(tauri::include_image!("icons/icon.ico"));
             }
@@ -18,14 +51,40 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             process_single_image,
+            scan_folder,
             process_batch_images,
+            process_animated_image,
+            process_video_frames,
+            process_multipage_image,
+            cancel_video_processing,
+            cancel_job,
+            pause_job,
+            resume_job,
+            list_jobs,
             process_clipboard_image,
             reprocess_clipboard_image,
             copy_result_to_clipboard,
             save_result_to_file,
             save_batch_to_folder,
+            release_results,
+            clear_results,
+            export_mask,
+            import_mask,
+            export_mask_svg,
             check_model,
+            get_locale,
+            set_locale,
+            start_watch,
+            stop_watch,
+            list_watches,
+            read_watch_log,
         ])
-        .run(tauri::generate_context!())
-        .expect("Erreur critique au lancement de Tauri");
+        .build(tauri::generate_context!())
+        .expect("Erreur critique au lancement de Tauri")
+        .run(|_app, event| {
+            // Résultats déchargés sur disque : dossier temporaire supprimé
+            if let tauri::RunEvent::Exit = event {
+                results::clear();
+            }
+        });
 }
@@ -2,12 +2,15 @@
 /// Input  : [1, 3, 1024, 1024] float32 normalisé (pixel/255 - 0.5)
 /// Output : [1, 1, 1024, 1024] float32 sigmoid (0..1 = masque alpha)
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use image::{imageops::FilterType, DynamicImage, GrayImage};
 use once_cell::sync::OnceCell;
 use ort::{inputs, session::Session, value::Tensor as OrtTensor};
 use std::{path::Path, sync::Mutex};
 
+use crate::error::AppError;
+use crate::tr;
+
 const INPUT_SIZE: usize = 1024;
 
 static SESSION: OnceCell<Mutex<Session>> = OnceCell::new();
@@ -19,35 +22,37 @@ pub fn init_model(model_path: &Path) -> Result<()> {
     }
 
     if !model_path.exists() {
-        return Err(anyhow!(
-            "model.onnx introuvable à : {}. Téléchargez RMBG-1.4 depuis HuggingFace.",
-            model_path.display()
-        ));
+        return Err(AppError::ModelNotFound { path: model_path.display().to_string() }.into());
     }
 
-    let session = Session::builder()?.commit_from_file(model_path)?;
+    let session = Session::builder()
+        .and_then(|b| b.commit_from_file(model_path))
+        .map_err(|e| AppError::Inference(tr!("model.load", error = e)))?;
 
     SESSION
         .set(Mutex::new(session))
-        .map_err(|_| anyhow!("Modèle déjà initialisé (race condition)"))?;
+        .map_err(|_| AppError::Internal(tr!("model.already_loaded")))?;
 
     Ok(())
 }
 
+/// Entrée du modèle : tenseur normalisé et taille de l'image d'origine.
+pub struct ModelInput {
+    data: Vec<f32>,
+    width: u32,
+    height: u32,
+}
+
 /// Lance l'inférence et retourne le masque alpha (GrayImage taille originale).
 pub fn run_inference(img: &DynamicImage) -> Result<GrayImage> {
-    let session_mutex = SESSION
-        .get()
-        .ok_or_else(|| anyhow!("Modèle non initialisé — appelez init_model() d'abord"))?;
-
-    // unwrap_or_else(|e| e.into_inner()) : récupère le lock même si un thread a paniqué
-    let mut session = session_mutex
-        .lock()
-        .unwrap_or_else(|e| e.into_inner());
+    run_prepared(prepare_input(img)?)
+}
 
+/// Prétraitement seul (redimensionnement, normalisation), hors du verrou de session.
+pub fn prepare_input(img: &DynamicImage) -> Result<ModelInput> {
     let (orig_w, orig_h) = (img.width(), img.height());
     if orig_w == 0 || orig_h == 0 {
-        return Err(anyhow!("Image invalide : dimensions 0×0"));
+        return Err(AppError::InvalidInput(tr!("image.empty")).into());
     }
 
     // ── Prétraitement ─────────────────────────────────────────────────────────
@@ -65,20 +70,38 @@ pub fn run_inference(img: &DynamicImage) -> Result<GrayImage> {
         data[2 * plane + idx] = pixel[2] as f32 / 255.0 - 0.5; // B
     }
 
+    Ok(ModelInput { data, width: orig_w, height: orig_h })
+}
+
+/// Inférence sur une entrée préparée ; masque ramené à la taille d'origine.
+pub fn run_prepared(input: ModelInput) -> Result<GrayImage> {
+    let session_mutex = SESSION
+        .get()
+        .ok_or(AppError::ModelNotLoaded)?;
+
+    // unwrap_or_else(|e| e.into_inner()) : récupère le lock même si un thread a paniqué
+    let mut session = session_mutex
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+
+    let ModelInput { data, width: orig_w, height: orig_h } = input;
+
     // ── Création du tenseur ort ────────────────────────────────────────────────
     // ort rc.11 : Tensor::from_array((shape, slice))
     let shape = [1usize, 3, INPUT_SIZE, INPUT_SIZE];
     let tensor = OrtTensor::from_array((shape, data))
-        .map_err(|e| anyhow!("Création tenseur : {e}"))?;
+        .map_err(|e| AppError::Inference(tr!("model.input_tensor", error = e)))?;
 
     // ── Inférence ─────────────────────────────────────────────────────────────
-    let outputs = session.run(inputs!["input" => tensor])?;
+    let outputs = session
+        .run(inputs!["input" => tensor])
+        .map_err(|e| AppError::Inference(e.to_string()))?;
 
     // ── Post-traitement ───────────────────────────────────────────────────────
     // try_extract_tensor() retourne (Shape, &[T]) dans ort rc.11
     let (_, mask_data) = outputs[0]
         .try_extract_tensor::<f32>()
-        .map_err(|e| anyhow!("Extraction tenseur de sortie : {e}"))?;
+        .map_err(|e| AppError::Inference(tr!("model.output_tensor", error = e)))?;
 
     // Convertit le masque [1,1,H,W] float → GrayImage 1024×1024
     let raw_mask: Vec<u8> = mask_data
@@ -87,7 +110,7 @@ pub fn run_inference(img: &DynamicImage) -> Result<GrayImage> {
         .collect();
 
     let mask_1024 = GrayImage::from_raw(INPUT_SIZE as u32, INPUT_SIZE as u32, raw_mask)
-        .ok_or_else(|| anyhow!("Impossible de créer GrayImage depuis le masque"))?;
+        .ok_or_else(|| AppError::Inference(tr!("model.output_mask")))?;
 
     // Redimensionne le masque à la résolution originale
     let mask_orig = image::imageops::resize(&mask_1024, orig_w, orig_h, FilterType::Lanczos3);
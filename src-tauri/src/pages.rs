@@ -0,0 +1,325 @@
+//! pages.rs — Fichiers à plusieurs images : pages d'un TIFF multipage et
+//! tailles d'une icône ICO, chargées une à une puis réécrites en un seul
+//! fichier multipage ou en fichiers numérotés.
+
+use anyhow::Result;
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageBuffer};
+use std::io::{BufReader, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::image_processor::{
+    check_megapixels, encode_png, load_image_from_bytes_with_metadata, sniff_format,
+    write_tiff_page, ImageMetadata, OutputFormat, SvgRenderOptions,
+};
+use crate::tr;
+
+/// Conteneur multipage reconnu à sa signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageContainer {
+    Tiff,
+    Ico,
+}
+
+impl PageContainer {
+    pub fn sniff(path: &Path) -> Result<Self> {
+        let mut header = [0u8; 4];
+        std::fs::File::open(path)
+            .and_then(|mut f| f.read_exact(&mut header))
+            .map_err(|e| AppError::io(path, &e, tr!("io.read", path = path.display(), error = e)))?;
+
+        match header {
+            [b'I', b'I', 42 | 43, 0] | [b'M', b'M', 0, 42 | 43] => Ok(PageContainer::Tiff),
+            [0, 0, 1, 0] => Ok(PageContainer::Ico),
+            // Autre type : rapporté par son nom (TIFF ou ICO attendu)
+            _ => Err(AppError::UnsupportedFormat { detected: sniff_format(&header).name() }.into()),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PageContainer::Tiff => "tiff",
+            PageContainer::Ico => "ico",
+        }
+    }
+}
+
+/// Écriture des pages traitées.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+pub enum PageOutput {
+    /// Un fichier par page : `{nom}_p1.{ext}`, `{nom}_p2.{ext}`…
+    #[default]
+    Separate,
+    /// Un seul fichier dans le conteneur source (TIFF multipage ou ICO).
+    MultiPage,
+}
+
+/// Options des fichiers multipages (toutes facultatives côté frontend).
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct PageOptions {
+    pub output: PageOutput,
+    /// Format des fichiers numérotés (`Separate`).
+    pub format: OutputFormat,
+}
+
+// ─── Chargement ──────────────────────────────────────────────────────────────
+
+/// Pages décodées au plus, quelle que soit leur taille.
+pub const MAX_PAGES: usize = 1000;
+
+/// Pixels décodés au plus pour l'ensemble des pages, en plus de la limite
+/// par page (même ordre de grandeur que les animations).
+pub const MAX_DOCUMENT_MEGAPIXELS: f32 = 400.0;
+
+/// Pages et pixels déjà acceptés ; vérifié avant de décoder chaque page.
+#[derive(Default)]
+struct PageBudget {
+    pages: usize,
+    megapixels: f64,
+}
+
+impl PageBudget {
+    fn admit(&mut self, width: u32, height: u32, max_megapixels: f32) -> Result<()> {
+        check_megapixels(width, height, max_megapixels)?;
+        if self.pages == MAX_PAGES {
+            return Err(AppError::InvalidInput(tr!("pages.too_many", max = MAX_PAGES)).into());
+        }
+        let megapixels = self.megapixels + width as f64 * height as f64 / 1_000_000.0;
+        if megapixels > MAX_DOCUMENT_MEGAPIXELS as f64 {
+            return Err(AppError::ImageTooLarge {
+                width,
+                height,
+                megapixels,
+                max_megapixels: MAX_DOCUMENT_MEGAPIXELS,
+            }
+            .into());
+        }
+        self.pages += 1;
+        self.megapixels = megapixels;
+        Ok(())
+    }
+}
+
+/// Charge toutes les pages (TIFF) ou toutes les tailles (ICO), chacune
+/// vérifiée contre `max_megapixels`, dans la limite de `MAX_PAGES` pages et
+/// `MAX_DOCUMENT_MEGAPIXELS` au total.
+pub fn load_pages(
+    path: &Path,
+    max_megapixels: f32,
+) -> Result<(PageContainer, Vec<(DynamicImage, ImageMetadata)>)> {
+    let container = PageContainer::sniff(path)?;
+    let pages = match container {
+        PageContainer::Tiff => load_tiff_pages(path, max_megapixels)?,
+        PageContainer::Ico => load_ico_images(path, max_megapixels)?,
+    };
+    if pages.is_empty() {
+        return Err(AppError::Decode(tr!("pages.empty", path = path.display())).into());
+    }
+    Ok((container, pages))
+}
+
+fn load_tiff_pages(path: &Path, max_megapixels: f32) -> Result<Vec<(DynamicImage, ImageMetadata)>> {
+    use tiff::tags::Tag;
+
+    let file = std::fs::File::open(path)
+        .map_err(|e| AppError::io(path, &e, tr!("io.open", path = path.display(), error = e)))?;
+    let mut decoder = tiff::decoder::Decoder::new(BufReader::new(file))
+        .map_err(|e| AppError::Decode(tr!("codec.decode", format = "TIFF", error = e)))?
+        .with_limits(tiff::decoder::Limits::unlimited());
+
+    let mut pages = Vec::new();
+    let mut budget = PageBudget::default();
+    loop {
+        let page = pages.len() + 1;
+        let tiff_err =
+            |e: tiff::TiffError| AppError::Decode(tr!("pages.tiff_decode", page = page, error = e));
+
+        let (w, h) = decoder.dimensions().map_err(tiff_err)?;
+        budget.admit(w, h, max_megapixels)?;
+
+        let metadata = ImageMetadata {
+            icc_profile: decoder.get_tag_u8_vec(Tag::IccProfile).ok(),
+            exif: None,
+            xmp: decoder.get_tag_u8_vec(Tag::Unknown(700)).ok(),
+        };
+        let orientation = decoder
+            .get_tag_u32(Tag::Orientation)
+            .ok()
+            .and_then(|v| Orientation::from_exif(v as u8))
+            .unwrap_or(Orientation::NoTransforms);
+
+        let colortype = decoder.colortype().map_err(tiff_err)?;
+        let data = decoder.read_image().map_err(tiff_err)?;
+        let mut img = tiff_page_to_image(w, h, colortype, data)
+            .ok_or_else(|| {
+                let color = format!("{colortype:?}");
+                AppError::Decode(tr!("pages.tiff_color", page = page, color = color))
+            })?;
+        img.apply_orientation(orientation);
+        pages.push((img, metadata));
+
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().map_err(tiff_err)?;
+    }
+    Ok(pages)
+}
+
+/// Pixels décodés par le crate `tiff` → DynamicImage (8/16 bits, flottant,
+/// CMJN 8 bits converti en RGB).
+fn tiff_page_to_image(
+    w: u32,
+    h: u32,
+    colortype: tiff::ColorType,
+    data: tiff::decoder::DecodingResult,
+) -> Option<DynamicImage> {
+    use tiff::decoder::DecodingResult as D;
+    use tiff::ColorType as C;
+
+    Some(match (colortype, data) {
+        (C::Gray(8), D::U8(v)) => DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, v)?),
+        (C::GrayA(8), D::U8(v)) => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(w, h, v)?),
+        (C::RGB(8), D::U8(v)) => DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, v)?),
+        (C::RGBA(8), D::U8(v)) => DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, v)?),
+        (C::Gray(16), D::U16(v)) => DynamicImage::ImageLuma16(ImageBuffer::from_raw(w, h, v)?),
+        (C::GrayA(16), D::U16(v)) => DynamicImage::ImageLumaA16(ImageBuffer::from_raw(w, h, v)?),
+        (C::RGB(16), D::U16(v)) => DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, v)?),
+        (C::RGBA(16), D::U16(v)) => DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, v)?),
+        (C::RGB(32), D::F32(v)) => DynamicImage::ImageRgb32F(ImageBuffer::from_raw(w, h, v)?),
+        (C::RGBA(32), D::F32(v)) => DynamicImage::ImageRgba32F(ImageBuffer::from_raw(w, h, v)?),
+        (C::CMYK(8), D::U8(v)) => {
+            let rgb = v
+                .chunks_exact(4)
+                .flat_map(|p| {
+                    let k = 255 - p[3] as u16;
+                    [0, 1, 2].map(|i| ((255 - p[i] as u16) * k / 255) as u8)
+                })
+                .collect();
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, rgb)?)
+        }
+        _ => return None,
+    })
+}
+
+/// Chaque entrée du répertoire ICO est extraite dans une icône à une seule
+/// image, décodée ensuite par `image` (PNG ou BMP embarqué).
+fn load_ico_images(path: &Path, max_megapixels: f32) -> Result<Vec<(DynamicImage, ImageMetadata)>> {
+    let data = std::fs::read(path)
+        .map_err(|e| AppError::io(path, &e, tr!("io.read", path = path.display(), error = e)))?;
+    let invalid = || AppError::Decode(tr!("pages.ico_truncated"));
+
+    let count = data
+        .get(4..6)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]) as usize)
+        .ok_or_else(invalid)?;
+    let mut images = Vec::with_capacity(count.min(MAX_PAGES));
+    let mut budget = PageBudget::default();
+    for i in 0..count {
+        let entry = data.get(6 + i * 16..6 + (i + 1) * 16).ok_or_else(invalid)?;
+        // Taille annoncée par le répertoire (0 = 256 px), vérifiée au décodage
+        let side = |b: u8| if b == 0 { 256 } else { b as u32 };
+        budget.admit(side(entry[0]), side(entry[1]), max_megapixels)?;
+        let size = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]) as usize;
+        let offset = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]) as usize;
+        let payload = data.get(offset..offset + size).ok_or_else(invalid)?;
+
+        let mut single = vec![0, 0, 1, 0, 1, 0];
+        single.extend_from_slice(&entry[..12]);
+        single.extend_from_slice(&22u32.to_le_bytes());
+        single.extend_from_slice(payload);
+
+        // Une entrée ICO est un PNG ou un BMP, jamais un SVG
+        let svg = SvgRenderOptions::default();
+        let image = load_image_from_bytes_with_metadata(&single, max_megapixels, &svg)
+            .map_err(|e| {
+                // Limite de taille : le code `IMAGE_TOO_LARGE` prime sur le contexte
+                if e.is::<AppError>() {
+                    e
+                } else {
+                    AppError::Decode(tr!("pages.ico_entry", index = i + 1, error = e)).into()
+                }
+            })?;
+        images.push(image);
+    }
+    Ok(images)
+}
+
+// ─── Écriture ────────────────────────────────────────────────────────────────
+
+/// `{dossier}/{nom}_p{n}.{ext}` pour la page `index` (à partir de 0).
+pub fn page_path(dest: &Path, index: usize, format: &OutputFormat) -> PathBuf {
+    let stem = dest.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    dest.with_file_name(format!("{stem}_p{}.{}", index + 1, format.extension()))
+}
+
+/// Réécrit toutes les pages dans le conteneur source : TIFF multipage (profondeur
+/// et métadonnées de chaque page conservées) ou ICO (PNG embarqués, 256 px max).
+pub fn encode_multipage(
+    container: PageContainer,
+    pages: &[(DynamicImage, ImageMetadata)],
+) -> Result<Vec<u8>> {
+    let mut buf = Cursor::new(Vec::new());
+    match container {
+        PageContainer::Tiff => {
+            let tiff_err = |e: tiff::TiffError| {
+                AppError::Encode(tr!("codec.encode", format = "TIFF", error = e))
+            };
+            let mut encoder = tiff::encoder::TiffEncoder::new(&mut buf).map_err(tiff_err)?;
+            for (img, metadata) in pages {
+                write_tiff_page(&mut encoder, img, metadata, None).map_err(tiff_err)?;
+            }
+        }
+        PageContainer::Ico => {
+            let pngs = pages
+                .iter()
+                .map(|(img, _)| {
+                    if img.width() > 256 || img.height() > 256 {
+                        let (width, height) = (img.width(), img.height());
+                        let message = tr!("pages.ico_too_large", width = width, height = height);
+                        return Err(AppError::Encode(message).into());
+                    }
+                    Ok((img.width(), img.height(), encode_png(&DynamicImage::ImageRgba8(img.to_rgba8()))?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let frames = pngs
+                .iter()
+                .map(|(w, h, png)| IcoFrame::with_encoded(png, *w, *h, image::ExtendedColorType::Rgba8))
+                .collect::<image::ImageResult<Vec<_>>>()
+                .map_err(|e| AppError::Encode(tr!("codec.encode", format = "ICO", error = e)))?;
+            IcoEncoder::new(&mut buf)
+                .encode_images(&frames)
+                .map_err(|e| AppError::Encode(tr!("codec.encode", format = "ICO", error = e)))?;
+        }
+    }
+    Ok(buf.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_limits_total_pixels() {
+        // 100 pages de 4 MP : la 101e dépasse les 400 MP cumulés
+        let mut budget = PageBudget::default();
+        for _ in 0..100 {
+            budget.admit(2000, 2000, 50.0).unwrap();
+        }
+        let err = AppError::from(budget.admit(2000, 2000, 50.0).unwrap_err());
+        assert_eq!(err.code(), "IMAGE_TOO_LARGE");
+    }
+
+    #[test]
+    fn budget_limits_page_count() {
+        let mut budget = PageBudget::default();
+        for _ in 0..MAX_PAGES {
+            budget.admit(1, 1, 50.0).unwrap();
+        }
+        let err = AppError::from(budget.admit(1, 1, 50.0).unwrap_err());
+        assert_eq!(err.code(), "INVALID_INPUT");
+    }
+}
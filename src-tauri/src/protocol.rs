@@ -0,0 +1,341 @@
+//! protocol.rs — Protocole `pureremove://` : sert les résultats du store
+//! (voir results.rs) en PNG, à pleine taille ou réduits, pour que le
+//! frontend les affiche avec de simples `<img src>` au lieu de data URL
+//! transportées par l'IPC.
+//!
+//! - `pureremove://localhost/{session}/result/{id}?size={px}` : détourage
+//! - `pureremove://localhost/{session}/mask/{id}?size={px}`   : masque affiné
+//!
+//! `size` borne le plus grand côté (jamais d'agrandissement). Les identifiants
+//! repartent de 1 à chaque lancement : `session`, tiré au démarrage, distingue
+//! les URL (et les ETag) d'un lancement à l'autre. Dans un même lancement, un
+//! identifiant ne change jamais de contenu : réponses cachables indéfiniment,
+//! avec ETag et requêtes `Range` (un seul intervalle).
+
+use image::{imageops::FilterType, DynamicImage};
+use once_cell::sync::Lazy;
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use tauri::http::{header, Method, Request, Response, StatusCode};
+
+use crate::error::AppError;
+use crate::image_processor::{encode_output, ImageMetadata, OutputFormat, PngCompression};
+use crate::results::{self, ResultId, PREVIEW_MAX_SIZE};
+use crate::tr;
+
+pub const SCHEME: &str = "pureremove";
+
+/// Encodages gardés pour les requêtes répétées (ETag expiré, `Range`).
+const ENCODED_CACHE_ENTRIES: usize = 16;
+
+/// Jeton du lancement, placé dans toutes les URL : le cache du webview ne
+/// confond pas le résultat 1 d'hier avec celui d'aujourd'hui.
+static SESSION: Lazy<String> = Lazy::new(|| {
+    // `RandomState` est initialisé aléatoirement par processus
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    format!("{:016x}", hasher.finish())
+});
+
+/// Ce que sert une URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Result,
+    Mask,
+}
+
+impl Resource {
+    fn path(self) -> &'static str {
+        match self {
+            Resource::Result => "result",
+            Resource::Mask => "mask",
+        }
+    }
+}
+
+/// URL de `resource` pour le webview : `pureremove://localhost/…`, ou
+/// `http://pureremove.localhost/…` là où WebView2 / Android l'imposent.
+pub fn url(resource: Resource, id: ResultId, size: Option<u32>) -> String {
+    let base = if cfg!(any(windows, target_os = "android")) {
+        format!("http://{SCHEME}.localhost")
+    } else {
+        format!("{SCHEME}://localhost")
+    };
+    let mut url = format!("{base}/{}/{}/{id}", *SESSION, resource.path());
+    if let Some(size) = size {
+        url.push_str(&format!("?size={size}"));
+    }
+    url
+}
+
+// ─── Requêtes ────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Target {
+    resource: Resource,
+    id: ResultId,
+    size: Option<u32>,
+    /// URL d'un lancement précédent : l'identifiant désigne un autre résultat.
+    stale: bool,
+}
+
+impl Target {
+    fn parse(request: &Request<Vec<u8>>) -> Option<Self> {
+        let mut segments = request.uri().path().trim_matches('/').split('/');
+        let stale = segments.next()? != SESSION.as_str();
+        let resource = match segments.next()? {
+            "result" => Resource::Result,
+            "mask" => Resource::Mask,
+            _ => return None,
+        };
+        let id = segments.next()?.parse().ok()?;
+        if segments.next().is_some() {
+            return None;
+        }
+
+        let mut size = None;
+        let query = request.uri().query().unwrap_or("");
+        for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+            if key == "size" {
+                size = Some(value.parse::<u32>().ok().filter(|&s| s > 0)?);
+            }
+        }
+        Some(Self { resource, id, size, stale })
+    }
+
+    fn etag(&self) -> String {
+        let size = self.size.map_or_else(|| "full".to_string(), |s| s.to_string());
+        format!("\"{}-{}-{}-{size}\"", *SESSION, self.resource.path(), self.id)
+    }
+}
+
+/// Répond à une requête du webview (GET ou HEAD).
+pub fn handle(request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::ALLOW, "GET, HEAD")
+            .body(Vec::new())
+            .unwrap_or_default();
+    }
+    let Some(target) = Target::parse(request) else {
+        let error = AppError::InvalidInput(tr!("protocol.bad_url", url = request.uri()));
+        return error_response(StatusCode::BAD_REQUEST, &error);
+    };
+    if target.stale {
+        let error = AppError::ResultNotFound { id: target.id };
+        return error_response(StatusCode::NOT_FOUND, &error);
+    }
+
+    let etag = target.etag();
+    let cached = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+    if cached {
+        return base_response(&etag)
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Vec::new())
+            .unwrap_or_default();
+    }
+
+    let body = match encoded(target) {
+        Ok(body) => body,
+        Err(error @ AppError::ResultNotFound { .. }) => {
+            return error_response(StatusCode::NOT_FOUND, &error)
+        }
+        Err(error) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &error),
+    };
+
+    let len = body.len();
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| parse_range(v, len));
+    let head = request.method() == Method::HEAD;
+
+    let response = base_response(&etag).header(header::CONTENT_TYPE, "image/png");
+    let response = match range {
+        // Plusieurs intervalles ou syntaxe inconnue : l'en-tête est ignoré
+        None | Some(RangeRequest::Ignored) => response
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, len)
+            .body(if head { Vec::new() } else { body.to_vec() }),
+        Some(RangeRequest::Unsatisfiable) => response
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+            .body(Vec::new()),
+        Some(RangeRequest::Bytes(start, end)) => response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"))
+            .header(header::CONTENT_LENGTH, end - start + 1)
+            .body(if head { Vec::new() } else { body[start..=end].to_vec() }),
+    };
+    response.unwrap_or_default()
+}
+
+fn base_response(etag: &str) -> tauri::http::response::Builder {
+    Response::builder()
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, "private, max-age=31536000, immutable")
+        .header(header::ACCEPT_RANGES, "bytes")
+        // Origine de la page ≠ origine du protocole : lisible par canvas / fetch
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+}
+
+/// Erreur au même format `{ code, message, details }` que les commandes.
+fn error_response(status: StatusCode, error: &AppError) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(serde_json::to_vec(error).unwrap_or_default())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RangeRequest {
+    /// Intervalle inclusif, borné à la taille du corps.
+    Bytes(usize, usize),
+    Unsatisfiable,
+    Ignored,
+}
+
+/// `bytes=a-b`, `bytes=a-` ou `bytes=-n` (les n derniers octets).
+fn parse_range(value: &str, len: usize) -> RangeRequest {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Ignored;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Ignored;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Ignored;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    let (start, end) = match (start.parse::<usize>(), end.parse::<usize>()) {
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        (Ok(start), Err(_)) if end.is_empty() => (start, len.saturating_sub(1)),
+        (Err(_), Ok(suffix)) if start.is_empty() && suffix > 0 => {
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        _ => return RangeRequest::Ignored,
+    };
+    if len == 0 || start >= len {
+        return RangeRequest::Unsatisfiable;
+    }
+    RangeRequest::Bytes(start, end)
+}
+
+// ─── Encodage ────────────────────────────────────────────────────────────────
+
+/// Du plus ancien au plus récent.
+type EncodedCache = VecDeque<(Target, Arc<Vec<u8>>)>;
+
+static ENCODED: Lazy<Mutex<EncodedCache>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// PNG 8 bits de la cible, depuis le cache ou encodé à la volée.
+fn encoded(target: Target) -> Result<Arc<Vec<u8>>, AppError> {
+    {
+        let cache = ENCODED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, bytes)) = cache.iter().find(|(t, _)| *t == target) {
+            return Ok(bytes.clone());
+        }
+    }
+
+    // Vignette : l'aperçu d'un résultat déchargé suffit, ses pixels restent sur disque
+    let entry = match (target.resource, target.size) {
+        (Resource::Result, Some(size)) if size <= PREVIEW_MAX_SIZE => {
+            results::get_preview(target.id)?
+        }
+        _ => results::get(target.id)?,
+    };
+    // Affichage seulement : 8 bits (le webview ignore le reste), compression rapide
+    let (img, metadata) = match target.resource {
+        Resource::Result => {
+            let img = DynamicImage::ImageRgba8(fit(&entry.image, target.size).to_rgba8());
+            let icc = entry.metadata.icc_profile.clone();
+            (img, ImageMetadata { icc_profile: icc, ..Default::default() })
+        }
+        Resource::Mask => {
+            let mask = DynamicImage::ImageLuma8(entry.mask.clone());
+            let img = DynamicImage::ImageLuma8(fit(&mask, target.size).to_luma8());
+            (img, ImageMetadata::default())
+        }
+    };
+    let format = OutputFormat::Png { compression: PngCompression::Fast };
+    let bytes = encode_output(&img, &format, &metadata)
+        .map_err(|e| AppError::Encode(e.to_string()))?;
+    let bytes = Arc::new(bytes);
+
+    let mut cache = ENCODED.lock().unwrap_or_else(|e| e.into_inner());
+    cache.push_back((target, bytes.clone()));
+    while cache.len() > ENCODED_CACHE_ENTRIES {
+        cache.pop_front();
+    }
+    Ok(bytes)
+}
+
+/// Oublie les encodages des résultats libérés (voir `results::release`).
+pub fn forget(ids: &[ResultId]) {
+    let mut cache = ENCODED.lock().unwrap_or_else(|e| e.into_inner());
+    cache.retain(|(target, _)| !ids.contains(&target.id));
+}
+
+/// Vide le cache d'encodages (voir `results::clear`).
+pub fn forget_all() {
+    ENCODED.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Réduit pour que le plus grand côté tienne dans `size` (jamais d'agrandissement).
+fn fit(img: &DynamicImage, size: Option<u32>) -> Cow<'_, DynamicImage> {
+    match size {
+        Some(size) if img.width().max(img.height()) > size => {
+            Cow::Owned(img.resize(size, size, FilterType::Triangle))
+        }
+        _ => Cow::Borrowed(img),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(url: &str) -> Option<Target> {
+        Target::parse(&Request::builder().uri(url).body(Vec::new()).unwrap())
+    }
+
+    #[test]
+    fn urls_carry_the_session() {
+        let url = url(Resource::Mask, 7, Some(256));
+        let parsed = target(&url).unwrap();
+        assert_eq!((parsed.resource, parsed.id, parsed.size), (Resource::Mask, 7, Some(256)));
+        assert!(!parsed.stale);
+        assert!(parsed.etag().contains(SESSION.as_str()));
+
+        // Même identifiant, lancement précédent
+        let old = target("pureremove://localhost/0123456789abcdef/mask/7?size=256").unwrap();
+        assert!(old.stale);
+        assert!(target("pureremove://localhost/result/7").is_none());
+    }
+
+    #[test]
+    fn single_ranges() {
+        assert_eq!(parse_range("bytes=0-9", 100), RangeRequest::Bytes(0, 9));
+        assert_eq!(parse_range("bytes=90-", 100), RangeRequest::Bytes(90, 99));
+        assert_eq!(parse_range("bytes=-10", 100), RangeRequest::Bytes(90, 99));
+        assert_eq!(parse_range("bytes=50-500", 100), RangeRequest::Bytes(50, 99));
+        assert_eq!(parse_range("bytes=100-", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), RangeRequest::Ignored);
+        assert_eq!(parse_range("items=0-1", 100), RangeRequest::Ignored);
+    }
+}
@@ -0,0 +1,451 @@
+//! psd.rs — Export Photoshop (.psd, RGB 8 bits) : l'original en calque avec
+//! le masque prédit comme masque de fusion modifiable (non destructif), et un
+//! calque de remplissage optionnel pour le fond.
+
+use anyhow::Result;
+use image::{DynamicImage, GrayImage, RgbaImage};
+
+use crate::error::AppError;
+use crate::image_processor::{apply_refined_mask, BackgroundColor, CompositingSpace, ImageMetadata};
+use crate::tr;
+
+/// Identifiants de couche d'un calque PSD.
+const CHANNEL_ALPHA: i16 = -1;
+const CHANNEL_USER_MASK: i16 = -2;
+
+/// Dimension maximale d'un PSD (au-delà, il faut du PSB).
+const PSD_MAX_DIMENSION: u32 = 30_000;
+
+struct PsdLayer {
+    name: String,
+    /// Couches (identifiant, plan de w×h octets), dans l'ordre d'écriture.
+    channels: Vec<(i16, Vec<u8>)>,
+}
+
+/// Encode le document : calque de fond uni (sauf fond transparent) sous le
+/// calque du sujet, dont le masque de fusion est `mask` (déjà affiné). L'image composite
+/// (lue par les visionneuses) est le détourage aplati sur le fond.
+pub fn encode_psd(
+    original: &DynamicImage,
+    mask: &GrayImage,
+    background: &BackgroundColor,
+    compositing: CompositingSpace,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>> {
+    let (w, h) = (original.width(), original.height());
+    if w > PSD_MAX_DIMENSION || h > PSD_MAX_DIMENSION {
+        let message = tr!("psd.too_large", width = w, height = h, max = PSD_MAX_DIMENSION);
+        return Err(AppError::Encode(message).into());
+    }
+    if mask.dimensions() != (w, h) {
+        return Err(AppError::Encode(tr!("psd.mask_size")).into());
+    }
+
+    let rgba = original.to_rgba8();
+    let fill = background_rgb(background);
+
+    // Ordre PSD : du calque le plus bas au plus haut
+    let mut layers = Vec::new();
+    if let Some(rgb) = fill {
+        let plane = |v: u8| vec![v; (w * h) as usize];
+        layers.push(PsdLayer {
+            name: tr!("layer.background"),
+            channels: vec![
+                (CHANNEL_ALPHA, plane(255)),
+                (0, plane(rgb[0])),
+                (1, plane(rgb[1])),
+                (2, plane(rgb[2])),
+            ],
+        });
+    }
+    layers.push(PsdLayer {
+        name: tr!("layer.subject"),
+        channels: vec![
+            (CHANNEL_ALPHA, channel_plane(&rgba, 3)),
+            (0, channel_plane(&rgba, 0)),
+            (1, channel_plane(&rgba, 1)),
+            (2, channel_plane(&rgba, 2)),
+            (CHANNEL_USER_MASK, mask.as_raw().clone()),
+        ],
+    });
+
+    let merged = apply_refined_mask(original, mask, background, compositing).to_rgba8();
+    let merged_channels = if fill.is_some() { 3 } else { 4 };
+
+    let mut out = Vec::new();
+
+    // En-tête
+    out.extend_from_slice(b"8BPS");
+    put_u16(&mut out, 1);
+    out.extend_from_slice(&[0; 6]);
+    put_u16(&mut out, merged_channels as u16);
+    put_u32(&mut out, h);
+    put_u32(&mut out, w);
+    put_u16(&mut out, 8); // bits par couche
+    put_u16(&mut out, 3); // mode RGB
+
+    // Données de mode colorimétrique : aucune en RGB
+    put_u32(&mut out, 0);
+
+    // Ressources d'image : profil ICC (1039)
+    let mut resources = Vec::new();
+    if let Some(icc) = &metadata.icc_profile {
+        resources.extend_from_slice(b"8BIM");
+        put_u16(&mut resources, 1039);
+        resources.extend_from_slice(&[0, 0]); // nom Pascal vide, aligné sur 2
+        put_u32(&mut resources, icc.len() as u32);
+        resources.extend_from_slice(icc);
+        if icc.len() % 2 == 1 {
+            resources.push(0);
+        }
+    }
+    put_u32(&mut out, resources.len() as u32);
+    out.extend_from_slice(&resources);
+
+    // Calques et masques
+    let layer_info = layer_info(&layers, w, h, fill.is_none());
+    put_u32(&mut out, layer_info.len() as u32 + 4 + 4);
+    put_u32(&mut out, layer_info.len() as u32);
+    out.extend_from_slice(&layer_info);
+    put_u32(&mut out, 0); // pas de masque global
+
+    // Image composite : RLE, tous les compteurs de lignes puis toutes les lignes
+    let planes: Vec<Vec<u8>> = (0..merged_channels).map(|c| channel_plane(&merged, c)).collect();
+    put_u16(&mut out, 1);
+    let rows: Vec<Vec<u8>> = planes
+        .iter()
+        .flat_map(|plane| plane.chunks(w as usize).map(packbits).collect::<Vec<_>>())
+        .collect();
+    for row in &rows {
+        put_u16(&mut out, row.len() as u16);
+    }
+    for row in &rows {
+        out.extend_from_slice(row);
+    }
+
+    Ok(out)
+}
+
+/// Section « Layer info » : enregistrements des calques puis leurs couches,
+/// complétée à une longueur paire. Un nombre de calques négatif indique que
+/// la couche alpha de l'image composite est sa transparence.
+fn layer_info(layers: &[PsdLayer], w: u32, h: u32, merged_alpha: bool) -> Vec<u8> {
+    let compressed: Vec<Vec<Vec<u8>>> = layers
+        .iter()
+        .map(|layer| layer.channels.iter().map(|(_, plane)| rle_channel(plane, w)).collect())
+        .collect();
+
+    let mut info = Vec::new();
+    let count = layers.len() as i16;
+    put_u16(&mut info, if merged_alpha { -count } else { count } as u16);
+
+    for (layer, channels) in layers.iter().zip(&compressed) {
+        put_rect(&mut info, w, h);
+        put_u16(&mut info, layer.channels.len() as u16);
+        for ((id, _), data) in layer.channels.iter().zip(channels) {
+            put_u16(&mut info, *id as u16);
+            put_u32(&mut info, data.len() as u32);
+        }
+        info.extend_from_slice(b"8BIMnorm");
+        info.extend_from_slice(&[255, 0, 0, 0]); // opacité, détourage, drapeaux (visible), remplissage
+
+        let mut extra = Vec::new();
+        if layer.channels.iter().any(|(id, _)| *id == CHANNEL_USER_MASK) {
+            put_u32(&mut extra, 20);
+            put_rect(&mut extra, w, h);
+            extra.extend_from_slice(&[0, 0, 0, 0]); // couleur par défaut noire, drapeaux, remplissage
+        } else {
+            put_u32(&mut extra, 0);
+        }
+        put_u32(&mut extra, 0); // plages de fusion
+        put_pascal_name(&mut extra, &layer.name);
+        put_unicode_name(&mut extra, &layer.name);
+        put_u32(&mut info, extra.len() as u32);
+        info.extend_from_slice(&extra);
+    }
+
+    for channels in &compressed {
+        for data in channels {
+            info.extend_from_slice(data);
+        }
+    }
+
+    if info.len() % 2 == 1 {
+        info.push(0);
+    }
+    info
+}
+
+/// Couche d'un calque compressée en RLE : compression, compteurs, lignes.
+fn rle_channel(plane: &[u8], w: u32) -> Vec<u8> {
+    let rows: Vec<Vec<u8>> = plane.chunks(w as usize).map(packbits).collect();
+    let mut data = Vec::with_capacity(2 + rows.len() * 2 + rows.iter().map(Vec::len).sum::<usize>());
+    put_u16(&mut data, 1);
+    for row in &rows {
+        put_u16(&mut data, row.len() as u16);
+    }
+    for row in &rows {
+        data.extend_from_slice(row);
+    }
+    data
+}
+
+/// Compression PackBits d'une ligne (paquets de 128 octets au plus).
+fn packbits(row: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(row.len() / 2 + 2);
+    let mut i = 0;
+    while i < row.len() {
+        let run = row[i..].iter().take(128).take_while(|&&b| b == row[i]).count();
+        if run >= 2 {
+            out.push((1i16 - run as i16) as u8);
+            out.push(row[i]);
+            i += run;
+            continue;
+        }
+
+        // Littéraux jusqu'à la prochaine répétition d'au moins 3 octets
+        let start = i;
+        while i < row.len() && i - start < 128 {
+            if i + 2 < row.len() && row[i] == row[i + 1] && row[i] == row[i + 2] {
+                break;
+            }
+            i += 1;
+        }
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&row[start..i]);
+    }
+    out
+}
+
+fn channel_plane(img: &RgbaImage, channel: usize) -> Vec<u8> {
+    img.pixels().map(|p| p[channel]).collect()
+}
+
+fn background_rgb(background: &BackgroundColor) -> Option<[u8; 3]> {
+    match background {
+        BackgroundColor::Transparent => None,
+        BackgroundColor::White => Some([255; 3]),
+        BackgroundColor::Black => Some([0; 3]),
+        BackgroundColor::Color { r, g, b } => Some([*r, *g, *b]),
+    }
+}
+
+fn put_u16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+/// Rectangle haut, gauche, bas, droite couvrant toute l'image.
+fn put_rect(out: &mut Vec<u8>, w: u32, h: u32) {
+    for v in [0, 0, h, w] {
+        put_u32(out, v);
+    }
+}
+
+/// Nom Pascal (MacRoman : les caractères non ASCII deviennent `_`) complété
+/// à un multiple de 4 octets, longueur comprise.
+fn put_pascal_name(out: &mut Vec<u8>, name: &str) {
+    let bytes: Vec<u8> = name
+        .chars()
+        .take(255)
+        .map(|c| if c.is_ascii() { c as u8 } else { b'_' })
+        .collect();
+    out.push(bytes.len() as u8);
+    out.extend_from_slice(&bytes);
+    let len = 1 + bytes.len();
+    out.extend(std::iter::repeat_n(0, (4 - len % 4) % 4));
+}
+
+/// Bloc d'information additionnelle `luni` : le nom complet en UTF-16, lu de
+/// préférence au nom Pascal par Photoshop (accents, noms traduits).
+fn put_unicode_name(out: &mut Vec<u8>, name: &str) {
+    let units: Vec<u16> = name.encode_utf16().collect();
+    out.extend_from_slice(b"8BIMluni");
+    put_u32(out, 4 + 2 * units.len() as u32);
+    put_u32(out, units.len() as u32);
+    for unit in units {
+        put_u16(out, unit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lecteur big-endian minimal sur le fichier produit.
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        fn bytes(&mut self, n: usize) -> &'a [u8] {
+            let slice = &self.data[self.pos..self.pos + n];
+            self.pos += n;
+            slice
+        }
+
+        fn u16(&mut self) -> u16 {
+            u16::from_be_bytes(self.bytes(2).try_into().unwrap())
+        }
+
+        fn u32(&mut self) -> u32 {
+            u32::from_be_bytes(self.bytes(4).try_into().unwrap())
+        }
+    }
+
+    struct ReadLayer {
+        rect: [u32; 4],
+        channels: Vec<(i16, u32)>,
+        pascal_name: String,
+        unicode_name: Option<String>,
+    }
+
+    fn unpackbits(mut data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        while let Some((&header, rest)) = data.split_first() {
+            let n = header as i8;
+            if n >= 0 {
+                out.extend_from_slice(&rest[..n as usize + 1]);
+                data = &rest[n as usize + 1..];
+            } else {
+                out.extend(std::iter::repeat_n(rest[0], (1 - n as i16) as usize));
+                data = &rest[1..];
+            }
+        }
+        out
+    }
+
+    fn read_layer(r: &mut Reader) -> ReadLayer {
+        let rect = [r.u32(), r.u32(), r.u32(), r.u32()];
+        let count = r.u16();
+        let channels = (0..count).map(|_| (r.u16() as i16, r.u32())).collect();
+        assert_eq!(r.bytes(8), b"8BIMnorm");
+        r.bytes(4);
+
+        let extra_len = r.u32() as usize;
+        let end = r.pos + extra_len;
+        let mask_len = r.u32() as usize;
+        r.bytes(mask_len);
+        let ranges_len = r.u32() as usize;
+        r.bytes(ranges_len);
+        let name_len = r.bytes(1)[0] as usize;
+        let pascal_name = String::from_utf8(r.bytes(name_len).to_vec()).unwrap();
+        r.bytes((4 - (1 + name_len) % 4) % 4);
+
+        let mut unicode_name = None;
+        while r.pos < end {
+            assert_eq!(r.bytes(4), b"8BIM");
+            let key = r.bytes(4);
+            let len = r.u32() as usize;
+            let mut block = Reader { data: r.bytes(len), pos: 0 };
+            if key == b"luni" {
+                let units: Vec<u16> = (0..block.u32()).map(|_| block.u16()).collect();
+                unicode_name = Some(String::from_utf16(&units).unwrap());
+            }
+        }
+        assert_eq!(r.pos, end);
+        ReadLayer { rect, channels, pascal_name, unicode_name }
+    }
+
+    /// Couche RLE d'un calque : compression 1, compteurs de lignes, lignes.
+    fn read_channel(data: &[u8], w: u32, h: u32) -> Vec<u8> {
+        let mut r = Reader { data, pos: 0 };
+        assert_eq!(r.u16(), 1);
+        let counts: Vec<usize> = (0..h).map(|_| r.u16() as usize).collect();
+        let plane: Vec<u8> = counts.iter().flat_map(|&n| unpackbits(r.bytes(n))).collect();
+        assert_eq!(plane.len(), (w * h) as usize);
+        assert_eq!(r.pos, data.len());
+        plane
+    }
+
+    #[test]
+    fn two_layers_round_trip() {
+        let (w, h) = (7, 5);
+        let original = DynamicImage::ImageRgb8(image::RgbImage::from_fn(w, h, |x, y| {
+            image::Rgb([(x * 30) as u8, (y * 50) as u8, 90])
+        }));
+        let mask = GrayImage::from_fn(w, h, |x, _| image::Luma([(x * 40) as u8]));
+        let background = BackgroundColor::Color { r: 10, g: 20, b: 30 };
+        let psd = encode_psd(
+            &original,
+            &mask,
+            &background,
+            CompositingSpace::default(),
+            &ImageMetadata::default(),
+        )
+        .unwrap();
+
+        let mut r = Reader { data: &psd, pos: 0 };
+        assert_eq!(r.bytes(4), b"8BPS");
+        assert_eq!(r.u16(), 1);
+        r.bytes(6);
+        assert_eq!(r.u16(), 3); // fond uni : composite sans alpha
+        assert_eq!((r.u32(), r.u32()), (h, w));
+        assert_eq!((r.u16(), r.u16()), (8, 3));
+        let color_mode = r.u32() as usize;
+        r.bytes(color_mode);
+        let resources = r.u32() as usize;
+        r.bytes(resources);
+
+        let section_end = r.u32() as usize + r.pos;
+        let info_len = r.u32() as usize;
+        let info_start = r.pos;
+        assert_eq!(r.u16() as i16, 2);
+        let layers: Vec<ReadLayer> = (0..2).map(|_| read_layer(&mut r)).collect();
+
+        let names = [tr!("layer.background"), tr!("layer.subject")];
+        for (layer, name) in layers.iter().zip(&names) {
+            assert_eq!(layer.rect, [0, 0, h, w]);
+            assert_eq!(&layer.pascal_name, name);
+            assert_eq!(layer.unicode_name.as_ref(), Some(name));
+        }
+
+        // Couches lues dans l'ordre des enregistrements
+        let mut planes = Vec::new();
+        for layer in &layers {
+            let mut channels = Vec::new();
+            for &(id, len) in &layer.channels {
+                channels.push((id, read_channel(r.bytes(len as usize), w, h)));
+            }
+            planes.push(channels);
+        }
+        assert!(r.pos <= info_start + info_len);
+        r.pos = info_start + info_len;
+        assert_eq!(r.u32(), 0); // pas de masque global
+        assert_eq!(r.pos, section_end);
+
+        let ids = |i: usize| planes[i].iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(ids(0), [CHANNEL_ALPHA, 0, 1, 2]);
+        assert_eq!(ids(1), [CHANNEL_ALPHA, 0, 1, 2, CHANNEL_USER_MASK]);
+        for (channel, value) in planes[0].iter().zip([255, 10, 20, 30]) {
+            assert!(channel.1.iter().all(|&v| v == value));
+        }
+
+        let rgba = original.to_rgba8();
+        for c in 0..3 {
+            assert_eq!(planes[1][c + 1].1, channel_plane(&rgba, c));
+        }
+        assert_eq!(planes[1][0].1, vec![255; (w * h) as usize]);
+        assert_eq!(&planes[1][4].1, mask.as_raw());
+    }
+
+    #[test]
+    fn non_ascii_names() {
+        let mut out = Vec::new();
+        put_pascal_name(&mut out, "Détourage");
+        assert_eq!(&out[..10], b"\x09D_tourage");
+        assert_eq!(out.len() % 4, 0);
+
+        let mut out = Vec::new();
+        put_unicode_name(&mut out, "Détourage");
+        let mut r = Reader { data: &out, pos: 0 };
+        assert_eq!(r.bytes(8), b"8BIMluni");
+        assert_eq!(r.u32(), 4 + 2 * 9);
+        let units: Vec<u16> = (0..r.u32()).map(|_| r.u16()).collect();
+        assert_eq!(String::from_utf16(&units).unwrap(), "Détourage");
+    }
+}
@@ -0,0 +1,437 @@
+//! results.rs — Résultats détourés gardés côté Rust, en pleine qualité
+//! (16 bits, flottant, métadonnées), sous un identifiant. Le frontend ne reçoit
+//! que l'identifiant et des URL `pureremove://` ; sauvegarde, copie et export en
+//! calques repartent de l'image stockée, sans aller-retour base64 ni PNG.
+
+use image::imageops::FilterType;
+use image::{ColorType, DynamicImage, GenericImageView, GrayImage, ImageBuffer};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{AppError, AppResult};
+use crate::image_processor::{ImageMetadata, SvgRenderOptions};
+use crate::protocol::{self, Resource};
+use crate::tr;
+
+/// Identifiant d'un résultat, unique pendant la session.
+pub type ResultId = u64;
+
+/// Plus grand côté des aperçus (`preview_url`).
+pub const PREVIEW_MAX_SIZE: u32 = 1024;
+
+/// Mémoire occupée par les pixels stockés (2 Gio) au-delà de laquelle les
+/// résultats les plus anciennement consultés sont déchargés sur disque.
+const STORE_BUDGET_BYTES: usize = 2 << 30;
+
+/// D'où vient l'original (pour l'export en calques).
+#[derive(Debug, Clone)]
+pub enum ResultSource {
+    File(PathBuf),
+    /// Image du presse-papier, encodée en PNG.
+    Bytes(Arc<Vec<u8>>),
+}
+
+/// Résultat complet : image composée, masque affiné et métadonnées de sortie.
+pub struct StoredResult {
+    pub name: String,
+    pub image: DynamicImage,
+    pub mask: GrayImage,
+    pub metadata: ImageMetadata,
+    pub source: ResultSource,
+    /// Limite et rasterisation SVG du traitement, reprises pour relire l'original.
+    pub max_megapixels: f32,
+    pub svg: SvgRenderOptions,
+    /// Sous-dossier de sortie relatif (lot issu d'un dossier scanné), vide sinon.
+    pub subfolder: PathBuf,
+}
+
+impl StoredResult {
+    fn size_bytes(&self) -> usize {
+        self.image.as_bytes().len() + self.mask.as_raw().len()
+    }
+
+    /// Copie sans les pixels, remplacés par `image` et `mask`.
+    fn with_pixels(&self, image: DynamicImage, mask: GrayImage) -> Self {
+        StoredResult {
+            name: self.name.clone(),
+            image,
+            mask,
+            metadata: self.metadata.clone(),
+            source: self.source.clone(),
+            max_megapixels: self.max_megapixels,
+            svg: self.svg.clone(),
+            subfolder: self.subfolder.clone(),
+        }
+    }
+}
+
+/// Ce que le frontend reçoit d'un traitement.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessedResult {
+    pub id: ResultId,
+    pub name: String,
+    /// Taille réelle du résultat (l'aperçu est plus petit).
+    pub width: u32,
+    pub height: u32,
+    /// Image à taille réelle (PNG 8 bits, voir protocol.rs).
+    pub url: String,
+    /// Aperçu de `PREVIEW_MAX_SIZE` px au plus, pour les listes.
+    pub preview_url: String,
+    /// Masque affiné, si `include_mask`.
+    pub mask_url: Option<String>,
+}
+
+// ─── Stockage ────────────────────────────────────────────────────────────────
+
+/// Pixels bruts d'une image et d'un masque, dans cet ordre (ordre natif).
+struct RawFile {
+    path: PathBuf,
+    color: ColorType,
+    image_size: (u32, u32),
+    mask_size: (u32, u32),
+}
+
+impl RawFile {
+    fn write(path: PathBuf, image: &DynamicImage, mask: &GrayImage) -> std::io::Result<Self> {
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(image.as_bytes())?;
+        file.write_all(mask.as_raw())?;
+        file.flush()?;
+        Ok(Self {
+            path,
+            color: image.color(),
+            image_size: image.dimensions(),
+            mask_size: mask.dimensions(),
+        })
+    }
+
+    fn read(&self) -> AppResult<(DynamicImage, GrayImage)> {
+        let io_error = |e: std::io::Error| {
+            AppError::Io(tr!("io.reread", path = self.path.display(), error = e))
+        };
+        let invalid = || io_error(std::io::ErrorKind::InvalidData.into());
+        let data = std::fs::read(&self.path).map_err(io_error)?;
+        let (w, h) = self.image_size;
+        let (mw, mh) = self.mask_size;
+        let split = data.len().checked_sub(mw as usize * mh as usize).ok_or_else(invalid)?;
+        let image = image_from_bytes(self.color, w, h, &data[..split]).ok_or_else(invalid)?;
+        let mask = GrayImage::from_raw(mw, mh, data[split..].to_vec()).ok_or_else(invalid)?;
+        Ok((image, mask))
+    }
+}
+
+/// Résultat déchargé : pixels et aperçu sur disque ; le reste (nom,
+/// métadonnées, source) demeure dans `shell`, dont l'image et le masque sont vides.
+struct Spilled {
+    pixels: RawFile,
+    /// Image réduite à `PREVIEW_MAX_SIZE`, en 8 bits, sans masque : les
+    /// vignettes d'un long lot ne rechargent pas les pixels.
+    preview: RawFile,
+    shell: StoredResult,
+}
+
+impl Spilled {
+    fn write(id: ResultId, result: &StoredResult) -> std::io::Result<Self> {
+        let dir = spill_dir();
+        std::fs::create_dir_all(&dir)?;
+        let pixels = RawFile::write(dir.join(format!("{id}.raw")), &result.image, &result.mask)?;
+
+        let image = &result.image;
+        let preview = if image.width().max(image.height()) > PREVIEW_MAX_SIZE {
+            image.resize(PREVIEW_MAX_SIZE, PREVIEW_MAX_SIZE, FilterType::Triangle).to_rgba8()
+        } else {
+            image.to_rgba8()
+        };
+        let preview_path = dir.join(format!("{id}.preview.raw"));
+        let preview = RawFile::write(preview_path, &preview.into(), &GrayImage::new(0, 0))
+            .inspect_err(|_| {
+                let _ = std::fs::remove_file(&pixels.path);
+            })?;
+
+        Ok(Self {
+            pixels,
+            preview,
+            shell: result.with_pixels(
+                DynamicImage::new(0, 0, result.image.color()),
+                GrayImage::new(0, 0),
+            ),
+        })
+    }
+
+    fn read(&self) -> AppResult<StoredResult> {
+        let (image, mask) = self.pixels.read()?;
+        Ok(self.shell.with_pixels(image, mask))
+    }
+
+    fn read_preview(&self) -> AppResult<StoredResult> {
+        let (image, mask) = self.preview.read()?;
+        Ok(self.shell.with_pixels(image, mask))
+    }
+
+    fn remove(&self) {
+        let _ = std::fs::remove_file(&self.pixels.path);
+        let _ = std::fs::remove_file(&self.preview.path);
+    }
+}
+
+/// Reconstruit une image depuis ses octets bruts (`as_bytes`, ordre natif).
+fn image_from_bytes(color: ColorType, w: u32, h: u32, bytes: &[u8]) -> Option<DynamicImage> {
+    fn u16s(bytes: &[u8]) -> Vec<u16> {
+        bytes.chunks_exact(2).map(|c| u16::from_ne_bytes([c[0], c[1]])).collect()
+    }
+    fn f32s(bytes: &[u8]) -> Vec<f32> {
+        bytes.chunks_exact(4).map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]])).collect()
+    }
+
+    let u8s = || bytes.to_vec();
+    Some(match color {
+        ColorType::L8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, u8s())?),
+        ColorType::La8 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(w, h, u8s())?),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, u8s())?),
+        ColorType::Rgba8 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, u8s())?),
+        ColorType::L16 => DynamicImage::ImageLuma16(ImageBuffer::from_raw(w, h, u16s(bytes))?),
+        ColorType::La16 => DynamicImage::ImageLumaA16(ImageBuffer::from_raw(w, h, u16s(bytes))?),
+        ColorType::Rgb16 => DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, u16s(bytes))?),
+        ColorType::Rgba16 => DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, u16s(bytes))?),
+        ColorType::Rgb32F => DynamicImage::ImageRgb32F(ImageBuffer::from_raw(w, h, f32s(bytes))?),
+        ColorType::Rgba32F => {
+            DynamicImage::ImageRgba32F(ImageBuffer::from_raw(w, h, f32s(bytes))?)
+        }
+        _ => return None,
+    })
+}
+
+/// Dossier temporaire des résultats déchargés, propre au processus.
+fn spill_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("pureremove-results-{}", std::process::id()))
+}
+
+enum Slot {
+    Loaded(Arc<StoredResult>),
+    Spilled(Arc<Spilled>),
+}
+
+#[derive(Default)]
+struct Store {
+    next_id: ResultId,
+    entries: HashMap<ResultId, Slot>,
+    /// Du moins récemment consulté au plus récent.
+    order: VecDeque<ResultId>,
+    /// Pixels gardés en mémoire (résultats non déchargés).
+    bytes: usize,
+}
+
+impl Store {
+    fn touch(&mut self, id: ResultId) {
+        self.order.retain(|&other| other != id);
+        self.order.push_back(id);
+    }
+
+    fn remove(&mut self, id: ResultId) {
+        match self.entries.remove(&id) {
+            Some(Slot::Loaded(entry)) => self.bytes -= entry.size_bytes(),
+            Some(Slot::Spilled(spilled)) => spilled.remove(),
+            None => return,
+        }
+        self.order.retain(|&other| other != id);
+    }
+
+    /// Plus ancien résultat en mémoire à décharger si le budget est dépassé ;
+    /// le dernier consulté reste en mémoire.
+    fn spill_candidate(&self) -> Option<(ResultId, Arc<StoredResult>)> {
+        if self.bytes <= STORE_BUDGET_BYTES {
+            return None;
+        }
+        let keep_last = self.order.len().saturating_sub(1);
+        self.order.iter().take(keep_last).find_map(|&id| match self.entries.get(&id) {
+            Some(Slot::Loaded(entry)) => Some((id, entry.clone())),
+            _ => None,
+        })
+    }
+}
+
+static STORE: Lazy<Mutex<Store>> = Lazy::new(|| Mutex::new(Store::default()));
+
+/// Un seul passage de déchargement à la fois, hors du verrou du store.
+static SPILLING: Mutex<()> = Mutex::new(());
+
+fn store() -> std::sync::MutexGuard<'static, Store> {
+    STORE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Décharge les plus anciens sur disque jusqu'à repasser sous le budget.
+/// Les écritures se font verrou relâché : les autres appels au store
+/// n'attendent pas le disque. Aucun résultat n'est perdu : si une écriture
+/// échoue (disque plein), les pixels restent en mémoire.
+fn spill() {
+    let _pass = SPILLING.lock().unwrap_or_else(|e| e.into_inner());
+    while let Some((id, entry)) = store().spill_candidate() {
+        let Ok(spilled) = Spilled::write(id, &entry) else {
+            return;
+        };
+        let mut store = store();
+        match store.entries.get(&id) {
+            Some(Slot::Loaded(current)) if Arc::ptr_eq(current, &entry) => {
+                store.bytes -= entry.size_bytes();
+                store.entries.insert(id, Slot::Spilled(Arc::new(spilled)));
+            }
+            // Libéré pendant l'écriture
+            _ => spilled.remove(),
+        }
+    }
+}
+
+/// Range un résultat ; retourne son identifiant.
+pub fn insert(result: StoredResult) -> ResultId {
+    let id = {
+        let mut store = store();
+        store.next_id += 1;
+        let id = store.next_id;
+        store.bytes += result.size_bytes();
+        store.entries.insert(id, Slot::Loaded(Arc::new(result)));
+        store.order.push_back(id);
+        id
+    };
+    spill();
+    id
+}
+
+/// Résultat `id`, ou `ResultNotFound` s'il a été libéré. Un résultat déchargé
+/// est relu depuis le disque, verrou relâché, sans revenir en mémoire : le
+/// lire ne fait décharger aucun autre résultat.
+pub fn get(id: ResultId) -> AppResult<Arc<StoredResult>> {
+    fetch(id, Spilled::read)
+}
+
+/// Comme `get`, mais d'un résultat déchargé ne relit que l'aperçu (image de
+/// `PREVIEW_MAX_SIZE` px au plus, 8 bits, masque vide).
+pub fn get_preview(id: ResultId) -> AppResult<Arc<StoredResult>> {
+    fetch(id, Spilled::read_preview)
+}
+
+fn fetch(
+    id: ResultId,
+    read: impl FnOnce(&Spilled) -> AppResult<StoredResult>,
+) -> AppResult<Arc<StoredResult>> {
+    let spilled = {
+        let mut store = store();
+        match store.entries.get(&id).ok_or(AppError::ResultNotFound { id })? {
+            Slot::Loaded(entry) => {
+                let entry = entry.clone();
+                store.touch(id);
+                return Ok(entry);
+            }
+            Slot::Spilled(spilled) => spilled.clone(),
+        }
+    };
+    Ok(Arc::new(read(&spilled)?))
+}
+
+/// `ResultNotFound` pour le premier identifiant libéré ou inconnu, sans
+/// toucher à l'ordre d'usage (avant une écriture qui doit être complète).
+pub fn check(ids: &[ResultId]) -> AppResult<()> {
+    let store = store();
+    match ids.iter().find(|id| !store.entries.contains_key(id)) {
+        Some(&id) => Err(AppError::ResultNotFound { id }),
+        None => Ok(()),
+    }
+}
+
+pub fn release(ids: &[ResultId]) {
+    let mut store = store();
+    for &id in ids {
+        store.remove(id);
+    }
+    protocol::forget(ids);
+}
+
+/// Libère tout ; les identifiants ne sont jamais réutilisés.
+pub fn clear() {
+    let mut store = store();
+    store.entries.clear();
+    store.order.clear();
+    store.bytes = 0;
+    let _ = std::fs::remove_dir_all(spill_dir());
+    protocol::forget_all();
+}
+
+// ─── Vers le frontend ────────────────────────────────────────────────────────
+
+/// Range le résultat ; les images seront servies par le protocole `pureremove://`.
+pub fn store_result(result: StoredResult, include_mask: bool) -> ProcessedResult {
+    let (name, width, height) = (result.name.clone(), result.image.width(), result.image.height());
+    let id = insert(result);
+    ProcessedResult {
+        id,
+        name,
+        width,
+        height,
+        url: protocol::url(Resource::Result, id, None),
+        preview_url: protocol::url(Resource::Result, id, Some(PREVIEW_MAX_SIZE)),
+        mask_url: include_mask.then(|| protocol::url(Resource::Mask, id, None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(image: DynamicImage) -> StoredResult {
+        let (w, h) = image.dimensions();
+        StoredResult {
+            name: "photo.png".into(),
+            image,
+            mask: GrayImage::from_fn(w, h, |x, y| image::Luma([(x * 7 + y) as u8])),
+            metadata: ImageMetadata::default(),
+            source: ResultSource::File("photo.png".into()),
+            max_megapixels: 100.0,
+            svg: SvgRenderOptions::default(),
+            subfolder: PathBuf::from("sous"),
+        }
+    }
+
+    #[test]
+    fn spilled_pixels_read_back_identical() {
+        let images = [
+            DynamicImage::ImageRgba8(ImageBuffer::from_fn(5, 3, |x, y| {
+                image::Rgba([x as u8, y as u8, 200, 128])
+            })),
+            DynamicImage::ImageRgb16(ImageBuffer::from_fn(5, 3, |x, y| {
+                image::Rgb([x as u16 * 1000, y as u16 * 20_000, 65_535])
+            })),
+            DynamicImage::ImageRgba32F(ImageBuffer::from_fn(5, 3, |x, y| {
+                image::Rgba([x as f32 / 4.0, y as f32 / 2.0, 0.25, 1.0])
+            })),
+        ];
+        for (i, image) in images.into_iter().enumerate() {
+            let original = result(image);
+            // Hors de portée des identifiants du store
+            let spilled = Spilled::write(ResultId::MAX - i as u64, &original).unwrap();
+            assert_eq!(spilled.shell.image.as_bytes().len(), 0);
+
+            let read = spilled.read().unwrap();
+            let preview = spilled.read_preview().unwrap();
+            spilled.remove();
+            assert_eq!(read.image, original.image);
+            assert_eq!(read.mask, original.mask);
+            assert_eq!(read.subfolder, original.subfolder);
+            assert_eq!(preview.image.to_rgba8(), original.image.to_rgba8());
+        }
+    }
+
+    #[test]
+    fn spilled_preview_is_downscaled() {
+        let image = DynamicImage::ImageRgb16(ImageBuffer::new(PREVIEW_MAX_SIZE * 2, 10));
+        let spilled = Spilled::write(ResultId::MAX - 10, &result(image)).unwrap();
+        let preview = spilled.read_preview().unwrap();
+        spilled.remove();
+        assert_eq!(preview.image.dimensions(), (PREVIEW_MAX_SIZE, 5));
+        assert_eq!(preview.image.color(), ColorType::Rgba8);
+        assert_eq!(preview.mask.dimensions(), (0, 0));
+    }
+}
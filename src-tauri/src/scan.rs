@@ -0,0 +1,296 @@
+//! scan.rs — Sélection des images d'un dossier pour un traitement en lot :
+//! parcours (récursif ou non), formats reconnus (`is_supported`), motifs glob
+//! include / exclude, filtres de poids et de dimensions, et sorties déjà
+//! produites écartées. Les chemins relatifs retournés servent à recréer les
+//! sous-dossiers dans le dossier de sortie.
+
+use anyhow::Result;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::image_processor::{is_supported, read_dimensions};
+use crate::tr;
+
+/// Suffixes des fichiers écrits par `save_batch_to_folder` (`{stem}_nobg.png`…).
+pub const OUTPUT_SUFFIXES: &[&str] = &["_nobg", "_mask", "_layers"];
+
+/// Sorties qui valent traitement fait (un masque seul ne suffit pas).
+const RESULT_SUFFIXES: &[&str] = &["_nobg", "_layers"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// Descend dans les sous-dossiers (liens symboliques de dossiers exclus).
+    pub recursive: bool,
+    /// Motifs glob sur le chemin relatif, sans casse (`*.jpg`, `clients/**`) ;
+    /// vide = tout.
+    pub include: Vec<String>,
+    /// Motifs glob écartant des fichiers, ou des dossiers entiers (`raw`, `**/old`).
+    pub exclude: Vec<String>,
+    /// Poids du fichier, en octets.
+    pub min_file_size: Option<u64>,
+    pub max_file_size: Option<u64>,
+    /// Dimensions lues dans l'en-tête ; un format qui ne les expose pas (SVG…)
+    /// passe le filtre.
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Écarte les sorties de l'application (`OUTPUT_SUFFIXES`) posées à côté de
+    /// leur source et, avec `output_folder`, les images dont le détourage y
+    /// existe déjà.
+    pub skip_processed: bool,
+    /// Dossier de sortie prévu : jamais parcouru, même placé dans le dossier scanné.
+    pub output_folder: Option<String>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            min_file_size: None,
+            max_file_size: None,
+            min_width: None,
+            min_height: None,
+            max_width: None,
+            max_height: None,
+            skip_processed: true,
+            output_folder: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannedFile {
+    pub path: String,
+    /// Relatif au dossier scanné, séparateurs `/`.
+    pub relative: String,
+}
+
+/// Fichiers écartés, par raison.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanSkipped {
+    pub excluded: usize,
+    pub processed: usize,
+    pub size: usize,
+    pub unsupported: usize,
+    pub dimensions: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanReport {
+    pub root: String,
+    /// Dans l'ordre du parcours (noms triés, dossier par dossier).
+    pub files: Vec<ScannedFile>,
+    pub skipped: ScanSkipped,
+}
+
+fn glob_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| {
+                AppError::InvalidInput(tr!("scan.bad_pattern", pattern = pattern, error = e))
+            })?;
+        builder.add(glob);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Chemin relatif avec des `/`, quel que soit l'OS (motifs et affichage).
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.'))
+}
+
+/// Nom de la source, sans casse, si `path` porte l'un des `suffixes`
+/// (`photo_nobg.png` → `photo`).
+fn output_source_stem(path: &Path, suffixes: &[&str]) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    suffixes
+        .iter()
+        .find_map(|suffix| stem.strip_suffix(suffix))
+        .map(str::to_lowercase)
+}
+
+/// Parcourt `root` et retourne les images à traiter (fichiers cachés ignorés).
+pub fn scan_folder(root: &Path, options: &ScanOptions) -> Result<ScanReport> {
+    if !root.is_dir() {
+        return Err(AppError::FileNotFound { path: root.display().to_string() }.into());
+    }
+    let include = glob_set(&options.include)?;
+    let exclude = glob_set(&options.exclude)?;
+    let output_folder = options.output_folder.as_deref().map(PathBuf::from);
+    let output_canonical = output_folder.as_deref().and_then(|p| p.canonicalize().ok());
+    // Sorties déjà présentes, par sous-dossier de sortie
+    let mut existing_outputs: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+
+    let mut report = ScanReport {
+        root: root.display().to_string(),
+        files: Vec::new(),
+        skipped: ScanSkipped::default(),
+    };
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries: Vec<_> = std::fs::read_dir(&dir)
+            .map_err(|e| {
+                AppError::io(&dir, &e, tr!("io.read_dir", path = dir.display(), error = e))
+            })?
+            .filter_map(|e| e.ok())
+            .collect();
+        entries.sort_by_key(|e| e.file_name());
+        // Noms du dossier, sans casse : une sortie n'est écartée qu'à côté de sa source
+        let stems: HashSet<String> = entries
+            .iter()
+            .filter_map(|e| Some(e.path().file_stem()?.to_str()?.to_lowercase()))
+            .collect();
+
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let path = entry.path();
+            if is_hidden(&path) {
+                continue;
+            }
+            let relative = relative_path(root, &path);
+            let Ok(file_type) = entry.file_type() else { continue };
+
+            if file_type.is_dir() {
+                let is_output = output_canonical.is_some()
+                    && path.canonicalize().ok() == output_canonical;
+                let is_excluded = exclude.as_ref().is_some_and(|set| set.is_match(&relative));
+                if options.recursive && !is_output && !is_excluded {
+                    subdirs.push(path);
+                }
+                continue;
+            }
+            // Lien symbolique : suivi pour un fichier, jamais pour un dossier
+            let Ok(metadata) = std::fs::metadata(&path) else { continue };
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let included = include.as_ref().is_none_or(|set| set.is_match(&relative));
+            if !included || exclude.as_ref().is_some_and(|set| set.is_match(&relative)) {
+                report.skipped.excluded += 1;
+                continue;
+            }
+
+            if options.skip_processed {
+                let already_done = output_source_stem(&path, OUTPUT_SUFFIXES)
+                    .is_some_and(|source| stems.contains(&source))
+                    || output_folder.as_deref().is_some_and(|out| {
+                        has_output(&mut existing_outputs, out, root, &path)
+                    });
+                if already_done {
+                    report.skipped.processed += 1;
+                    continue;
+                }
+            }
+
+            let size = metadata.len();
+            if options.min_file_size.is_some_and(|min| size < min)
+                || options.max_file_size.is_some_and(|max| size > max)
+            {
+                report.skipped.size += 1;
+                continue;
+            }
+
+            if !is_supported(&path) {
+                report.skipped.unsupported += 1;
+                continue;
+            }
+
+            if !dimensions_match(&path, options) {
+                report.skipped.dimensions += 1;
+                continue;
+            }
+
+            report.files.push(ScannedFile { path: path.display().to_string(), relative });
+        }
+
+        // Pile : dernier empilé, premier parcouru → ordre alphabétique
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok(report)
+}
+
+fn dimensions_match(path: &Path, options: &ScanOptions) -> bool {
+    let any_filter = options.min_width.is_some()
+        || options.min_height.is_some()
+        || options.max_width.is_some()
+        || options.max_height.is_some();
+    if !any_filter {
+        return true;
+    }
+    let Some((width, height)) = read_dimensions(path) else {
+        return true;
+    };
+    options.min_width.is_none_or(|min| width >= min)
+        && options.min_height.is_none_or(|min| height >= min)
+        && options.max_width.is_none_or(|max| width <= max)
+        && options.max_height.is_none_or(|max| height <= max)
+}
+
+/// Le détourage de `path` existe-t-il déjà dans le sous-dossier correspondant
+/// de `output` ? Chaque dossier de sortie n'est listé qu'une fois.
+fn has_output(
+    cache: &mut HashMap<PathBuf, HashSet<String>>,
+    output: &Path,
+    root: &Path,
+    path: &Path,
+) -> bool {
+    let subfolder = path.parent().and_then(|p| p.strip_prefix(root).ok());
+    let dir = output.join(subfolder.unwrap_or(Path::new("")));
+    let stems = cache.entry(dir.clone()).or_insert_with(|| {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return HashSet::new();
+        };
+        entries.filter_map(|e| output_source_stem(&e.ok()?.path(), RESULT_SUFFIXES)).collect()
+    });
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|stem| stems.contains(&stem.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_skipped_only_next_to_their_source() {
+        let dir = std::env::temp_dir().join(format!("pureremove-scan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let names = ["photo.jpg", "photo_nobg.png", "logo_mask.png", "Plan.PNG", "plan_layers.png"];
+        for name in names {
+            let png = image::RgbaImage::new(1, 1);
+            png.save_with_format(dir.join(name), image::ImageFormat::Png).unwrap();
+        }
+
+        let options = ScanOptions { skip_processed: true, ..Default::default() };
+        let report = scan_folder(&dir, &options).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = report.files.iter().map(|f| f.relative.as_str()).collect();
+        assert_eq!(names, ["Plan.PNG", "logo_mask.png", "photo.jpg"]);
+        assert_eq!(report.skipped.processed, 2);
+    }
+}
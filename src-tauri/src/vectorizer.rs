@@ -0,0 +1,465 @@
+//! vectorizer.rs — Vectorisation du masque alpha en tracé SVG.
+//! Marching squares → simplification Ramer–Douglas–Peucker → lissage Bézier,
+//! puis écriture d'un SVG contenant le clipPath.
+
+use anyhow::Result;
+use image::{DynamicImage, GrayImage};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::image_processor::{encode_base64_png, ImageMetadata};
+use crate::tr;
+
+const CLIP_ID: &str = "pureremove-clip";
+
+/// Options d'export SVG (toutes facultatives côté frontend).
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct SvgExportOptions {
+    /// Seuil 0..255 : au-dessus = intérieur du tracé.
+    pub threshold: u8,
+    /// Écart max (px) toléré par la simplification du contour.
+    pub tolerance: f32,
+    /// 0 = polygone brut, 1 = courbes Bézier pleinement lissées.
+    pub smoothing: f32,
+    /// Intègre l'image originale, découpée par le clipPath.
+    pub embed_image: bool,
+}
+
+impl Default for SvgExportOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 128,
+            tolerance: 1.0,
+            smoothing: 1.0,
+            embed_image: false,
+        }
+    }
+}
+
+type Point = (f32, f32);
+
+// ─── Marching squares ────────────────────────────────────────────────────────
+
+/// Extrait les contours fermés du masque seuillé.
+/// Les sommets sont interpolés sur les valeurs de gris pour un tracé sub-pixel ;
+/// l'extérieur de l'image est considéré comme vide, donc tout contour est fermé.
+fn trace_contours(mask: &GrayImage, threshold: u8) -> Vec<Vec<Point>> {
+    let (w, h) = (mask.width() as i32, mask.height() as i32);
+    let t = threshold as f32;
+
+    // Valeur au centre du pixel (x, y), 0 hors image
+    let value = |x: i32, y: i32| -> f32 {
+        if x < 0 || y < 0 || x >= w || y >= h {
+            0.0
+        } else {
+            mask.get_pixel(x as u32, y as u32)[0] as f32
+        }
+    };
+    let inside = |x: i32, y: i32| value(x, y) >= t;
+
+    // Sommet sur l'arête entre deux centres de pixels voisins.
+    // Clé en coordonnées doublées : unique par arête, partagée par les 2 cellules.
+    let edge_point = |(x0, y0): (i32, i32), (x1, y1): (i32, i32)| -> ((i32, i32), Point) {
+        let (a, b) = (value(x0, y0), value(x1, y1));
+        let f = if (b - a).abs() > f32::EPSILON {
+            ((t - a) / (b - a)).clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+        let px = x0 as f32 + (x1 - x0) as f32 * f + 0.5;
+        let py = y0 as f32 + (y1 - y0) as f32 * f + 0.5;
+        ((x0 + x1, y0 + y1), (px, py))
+    };
+
+    let mut points: HashMap<(i32, i32), Point> = HashMap::new();
+    let mut links: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+
+    for y in -1..h {
+        for x in -1..w {
+            // Coins de la cellule : haut-gauche, haut-droit, bas-droit, bas-gauche
+            let tl = (x, y);
+            let tr = (x + 1, y);
+            let br = (x + 1, y + 1);
+            let bl = (x, y + 1);
+
+            let case = (inside(tl.0, tl.1) as u8) << 3
+                | (inside(tr.0, tr.1) as u8) << 2
+                | (inside(br.0, br.1) as u8) << 1
+                | inside(bl.0, bl.1) as u8;
+
+            let top = || edge_point(tl, tr);
+            let right = || edge_point(tr, br);
+            let bottom = || edge_point(bl, br);
+            let left = || edge_point(tl, bl);
+
+            let segments = match case {
+                0 | 15 => vec![],
+                1 | 14 => vec![(left(), bottom())],
+                2 | 13 => vec![(bottom(), right())],
+                3 | 12 => vec![(left(), right())],
+                4 | 11 => vec![(top(), right())],
+                6 | 9 => vec![(top(), bottom())],
+                7 | 8 => vec![(left(), top())],
+                // Cas ambigus : on tranche par la valeur moyenne de la cellule
+                5 | 10 => {
+                    let center = (value(tl.0, tl.1)
+                        + value(tr.0, tr.1)
+                        + value(br.0, br.1)
+                        + value(bl.0, bl.1))
+                        / 4.0;
+                    let joined = (center >= t) == (case == 5);
+                    if joined {
+                        vec![(left(), top()), (bottom(), right())]
+                    } else {
+                        vec![(left(), bottom()), (top(), right())]
+                    }
+                }
+                _ => unreachable!(),
+            };
+
+            for ((ka, pa), (kb, pb)) in segments {
+                points.insert(ka, pa);
+                points.insert(kb, pb);
+                links.entry(ka).or_default().push(kb);
+                links.entry(kb).or_default().push(ka);
+            }
+        }
+    }
+
+    // Chaque sommet a exactement 2 voisins → le graphe est une union de cycles
+    let mut contours = Vec::new();
+    let mut keys: Vec<(i32, i32)> = links.keys().copied().collect();
+    keys.sort_unstable();
+
+    for start in keys {
+        if !links.contains_key(&start) {
+            continue;
+        }
+        let mut contour = vec![points[&start]];
+        let mut prev = start;
+        let mut current = links[&start][0];
+
+        while current != start {
+            contour.push(points[&current]);
+            let next = match links.get(&current) {
+                Some(n) if n[0] != prev => n[0],
+                Some(n) if n.len() > 1 => n[1],
+                _ => break,
+            };
+            links.remove(&current);
+            prev = current;
+            current = next;
+        }
+        links.remove(&start);
+
+        if contour.len() >= 3 {
+            contours.push(contour);
+        }
+    }
+
+    contours
+}
+
+// ─── Simplification ──────────────────────────────────────────────────────────
+
+fn distance_to_segment(p: Point, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    if len2 <= f32::EPSILON {
+        return ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt();
+    }
+    let t = (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0);
+    ((p.0 - (a.0 + t * dx)).powi(2) + (p.1 - (a.1 + t * dy)).powi(2)).sqrt()
+}
+
+/// Ramer–Douglas–Peucker sur une polyligne ouverte (extrémités conservées).
+fn simplify_open(points: &[Point], tolerance: f32, out: &mut Vec<Point>) {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let (mut index, mut max_dist) = (0, 0.0f32);
+
+    for (i, &p) in points.iter().enumerate().take(points.len() - 1).skip(1) {
+        let d = distance_to_segment(p, first, last);
+        if d > max_dist {
+            index = i;
+            max_dist = d;
+        }
+    }
+
+    if max_dist > tolerance {
+        simplify_open(&points[..=index], tolerance, out);
+        out.pop(); // le point de jonction est rajouté par la 2e moitié
+        simplify_open(&points[index..], tolerance, out);
+    } else {
+        out.push(first);
+        out.push(last);
+    }
+}
+
+/// RDP sur un contour fermé : on coupe au point le plus éloigné du premier.
+fn simplify_closed(contour: &[Point], tolerance: f32) -> Vec<Point> {
+    if tolerance <= 0.0 || contour.len() < 4 {
+        return contour.to_vec();
+    }
+
+    let start = contour[0];
+    let far = contour
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| {
+            let da = (a.0 - start.0).powi(2) + (a.1 - start.1).powi(2);
+            let db = (b.0 - start.0).powi(2) + (b.1 - start.1).powi(2);
+            da.total_cmp(&db)
+        })
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+
+    let mut closed = contour.to_vec();
+    closed.push(start);
+
+    let mut out = Vec::new();
+    simplify_open(&closed[..=far], tolerance, &mut out);
+    out.pop();
+    simplify_open(&closed[far..], tolerance, &mut out);
+    out.pop(); // dernier point = premier
+    out
+}
+
+// ─── Tracé SVG ───────────────────────────────────────────────────────────────
+
+/// Au-delà de cet angle de virage (≈ 70°), le sommet est gardé anguleux.
+const CORNER_COS: f32 = 0.35;
+
+/// Tangente unitaire au sommet `i`, ou `None` si c'est un angle vif.
+fn vertex_tangent(points: &[Point], i: usize) -> Option<Point> {
+    let n = points.len();
+    let (p0, p1, p2) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+
+    let (ax, ay) = (p1.0 - p0.0, p1.1 - p0.1);
+    let (bx, by) = (p2.0 - p1.0, p2.1 - p1.1);
+    let (la, lb) = ((ax * ax + ay * ay).sqrt(), (bx * bx + by * by).sqrt());
+    if la <= f32::EPSILON || lb <= f32::EPSILON || (ax * bx + ay * by) / (la * lb) < CORNER_COS {
+        return None;
+    }
+
+    let (tx, ty) = (ax / la + bx / lb, ay / la + by / lb);
+    let lt = (tx * tx + ty * ty).sqrt();
+    (lt > f32::EPSILON).then(|| (tx / lt, ty / lt))
+}
+
+/// Convertit un contour fermé en commandes de chemin SVG.
+/// `smoothing` > 0 : Bézier cubiques dont les poignées suivent la tangente
+/// au sommet, proportionnelles à la longueur du segment (pas de dépassement).
+fn contour_to_path(points: &[Point], smoothing: f32, d: &mut String) {
+    let n = points.len();
+    let k = smoothing.clamp(0.0, 1.0) / 3.0;
+    let tangents: Vec<Option<Point>> = (0..n).map(|i| vertex_tangent(points, i)).collect();
+
+    let _ = write!(d, "M{:.2} {:.2}", points[0].0, points[0].1);
+    for i in 0..n {
+        let j = (i + 1) % n;
+        let (p1, p2) = (points[i], points[j]);
+
+        if k > 0.0 {
+            let len = ((p2.0 - p1.0).powi(2) + (p2.1 - p1.1).powi(2)).sqrt() * k;
+            let c1 = tangents[i].map_or(p1, |t| (p1.0 + t.0 * len, p1.1 + t.1 * len));
+            let c2 = tangents[j].map_or(p2, |t| (p2.0 - t.0 * len, p2.1 - t.1 * len));
+            let _ = write!(
+                d,
+                "C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}",
+                c1.0, c1.1, c2.0, c2.1, p2.0, p2.1
+            );
+        } else {
+            let _ = write!(d, "L{:.2} {:.2}", p2.0, p2.1);
+        }
+    }
+    d.push('Z');
+}
+
+/// Vectorise le masque et retourne le document SVG.
+/// `raster` : image originale à intégrer, découpée par le tracé, avec ses
+/// métadonnées (profil ICC conservé dans le PNG intégré).
+pub fn mask_to_svg(
+    mask: &GrayImage,
+    raster: Option<(&DynamicImage, &ImageMetadata)>,
+    options: &SvgExportOptions,
+) -> Result<String> {
+    let (w, h) = mask.dimensions();
+    if w == 0 || h == 0 {
+        return Err(AppError::InvalidInput(tr!("mask.empty")).into());
+    }
+
+    let mut d = String::new();
+    for contour in trace_contours(mask, options.threshold) {
+        let simplified = simplify_closed(&contour, options.tolerance);
+        if simplified.len() >= 3 {
+            contour_to_path(&simplified, options.smoothing, &mut d);
+        }
+    }
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink""#,
+            r#" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        ),
+        w = w,
+        h = h,
+    );
+    let _ = writeln!(svg, "  <defs>");
+    let _ = writeln!(svg, r#"    <clipPath id="{CLIP_ID}" clipPathUnits="userSpaceOnUse">"#);
+    let _ = writeln!(svg, r#"      <path clip-rule="evenodd" d="{d}"/>"#);
+    let _ = writeln!(svg, "    </clipPath>");
+    let _ = writeln!(svg, "  </defs>");
+
+    match raster {
+        Some((img, metadata)) => {
+            // `xlink:href` pour les lecteurs SVG 1.1, `href` pour SVG 2.
+            let href = encode_base64_png(img, metadata)?;
+            let _ = writeln!(
+                svg,
+                concat!(
+                    r#"  <image width="{w}" height="{h}" preserveAspectRatio="none""#,
+                    r#" clip-path="url(#{clip})" xlink:href="{href}" href="{href}"/>"#,
+                ),
+                w = w,
+                h = h,
+                clip = CLIP_ID,
+                href = href,
+            );
+        }
+        None => {
+            let _ = writeln!(svg, r##"  <path fill="#000000" fill-rule="evenodd" d="{d}"/>"##);
+        }
+    }
+    svg.push_str("</svg>\n");
+
+    Ok(svg)
+}
+
+/// Vectorise le masque et écrit le SVG sur le disque.
+pub fn save_mask_svg(
+    mask: &GrayImage,
+    raster: Option<(&DynamicImage, &ImageMetadata)>,
+    options: &SvgExportOptions,
+    dest: &Path,
+) -> Result<()> {
+    let svg = mask_to_svg(mask, raster, options)?;
+    std::fs::write(dest, svg)
+        .map_err(|e| AppError::Io(tr!("io.save", path = dest.display(), error = e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: u32 = 96;
+
+    fn mask_from(inside: impl Fn(f32, f32) -> bool) -> GrayImage {
+        GrayImage::from_fn(SIZE, SIZE, |x, y| {
+            image::Luma([if inside(x as f32 + 0.5, y as f32 + 0.5) { 255 } else { 0 }])
+        })
+    }
+
+    /// Rastérise le SVG exporté (tracé noir) et retourne sa couverture alpha.
+    fn rasterize(svg: &str) -> GrayImage {
+        let tree = resvg::usvg::Tree::from_str(svg, &resvg::usvg::Options::default()).unwrap();
+        let mut pixmap = resvg::tiny_skia::Pixmap::new(SIZE, SIZE).unwrap();
+        resvg::render(&tree, resvg::tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        GrayImage::from_fn(SIZE, SIZE, |x, y| {
+            image::Luma([pixmap.pixel(x, y).unwrap().alpha()])
+        })
+    }
+
+    /// Intersection sur union des deux masques seuillés à mi-hauteur.
+    fn iou(a: &GrayImage, b: &GrayImage) -> f32 {
+        let (mut inter, mut union) = (0u32, 0u32);
+        for (pa, pb) in a.pixels().zip(b.pixels()) {
+            let (ia, ib) = (pa[0] >= 128, pb[0] >= 128);
+            inter += (ia && ib) as u32;
+            union += (ia || ib) as u32;
+        }
+        inter as f32 / union.max(1) as f32
+    }
+
+    fn round_trip(mask: &GrayImage) -> GrayImage {
+        rasterize(&mask_to_svg(mask, None, &SvgExportOptions::default()).unwrap())
+    }
+
+    #[test]
+    fn disc_round_trip() {
+        let mask = mask_from(|x, y| (x - 48.0).hypot(y - 48.0) <= 30.0);
+        let back = round_trip(&mask);
+        assert!(iou(&mask, &back) > 0.97, "IoU {}", iou(&mask, &back));
+    }
+
+    #[test]
+    fn ring_keeps_its_hole() {
+        let mask = mask_from(|x, y| {
+            let r = (x - 48.0).hypot(y - 48.0);
+            (14.0..=36.0).contains(&r)
+        });
+        let back = round_trip(&mask);
+        assert!(iou(&mask, &back) > 0.95, "IoU {}", iou(&mask, &back));
+        assert_eq!(back.get_pixel(48, 48)[0], 0, "le trou doit rester transparent");
+    }
+
+    #[test]
+    fn shapes_touching_the_edges() {
+        // Bande pleine hauteur collée à gauche, coin bas-droit, bord haut
+        let mask = mask_from(|x, y| {
+            x < 30.0 || (x > 70.0 && y > 70.0) || (y < 10.0 && x > 40.0 && x < 60.0)
+        });
+        let back = round_trip(&mask);
+        assert!(iou(&mask, &back) > 0.97, "IoU {}", iou(&mask, &back));
+
+        // Sans simplification, le tracé suit le bord de l'image (la tolérance
+        // par défaut autorise un écart d'un demi-pixel)
+        let exact = SvgExportOptions { tolerance: 0.0, ..Default::default() };
+        let back = rasterize(&mask_to_svg(&mask, None, &exact).unwrap());
+        for (x, y) in [(0, 48), (SIZE - 1, 85), (50, 0), (85, SIZE - 1)] {
+            assert!(back.get_pixel(x, y)[0] >= 250, "bord ({x}, {y}) découvert");
+        }
+    }
+
+    #[test]
+    fn full_and_empty_masks() {
+        let full = mask_from(|_, _| true);
+        assert!(iou(&full, &round_trip(&full)) > 0.99);
+        let empty = mask_from(|_, _| false);
+        assert!(round_trip(&empty).pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn embedded_image_keeps_xlink_and_icc() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use image::ImageDecoder;
+
+        let mask = mask_from(|x, y| (x - 48.0).hypot(y - 48.0) <= 30.0);
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            SIZE,
+            SIZE,
+            image::Rgb([200, 40, 40]),
+        ));
+        let icc = b"icc-profile-test".to_vec();
+        let metadata = ImageMetadata { icc_profile: Some(icc.clone()), ..Default::default() };
+        let svg =
+            mask_to_svg(&mask, Some((&img, &metadata)), &SvgExportOptions::default()).unwrap();
+
+        assert!(svg.contains(r#"xmlns:xlink="http://www.w3.org/1999/xlink""#));
+        let data = svg.split(r#"xlink:href="data:image/png;base64,"#).nth(1).unwrap();
+        let data = &data[..data.find('"').unwrap()];
+        assert!(svg.contains(&format!(r#" href="data:image/png;base64,{data}""#)));
+
+        let png = STANDARD.decode(data).unwrap();
+        let mut decoder = image::codecs::png::PngDecoder::new(std::io::Cursor::new(png)).unwrap();
+        assert_eq!(decoder.icc_profile().unwrap(), Some(icc));
+
+        // L'image découpée reste rendue à l'intérieur du tracé seulement
+        let back = rasterize(&svg);
+        assert!(iou(&mask, &back) > 0.97, "IoU {}", iou(&mask, &back));
+    }
+}
@@ -0,0 +1,690 @@
+//! video.rs — Détourage de courtes vidéos sous forme de séquences d'images :
+//! dossier d'images numérotées ou flux Y4M en entrée, séquence PNG avec alpha
+//! en sortie, plus un Y4M et/ou un PNG animé (APNG) en option.
+
+use anyhow::Result;
+use image::{DynamicImage, GrayImage, Rgba, RgbaImage};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::animation::smooth_mask;
+use crate::error::AppError;
+use crate::image_processor::{
+    check_megapixels, is_supported, load_image, save_output, ImageMetadata, OutputFormat,
+};
+use crate::scan::OUTPUT_SUFFIXES;
+use crate::tr;
+
+/// Options du traitement vidéo (toutes facultatives côté frontend).
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct VideoOptions {
+    /// Lissage temporel des masques, 0 (aucun) … 1 ([1 2 1] / 4 sur 3 images).
+    pub temporal_smoothing: f32,
+    /// Cadence d'un dossier d'images ; un flux Y4M porte la sienne.
+    pub fps: f32,
+    /// Écrit aussi `{nom}_nobg.y4m` (YUV 4:4:4, avec alpha si le fond est transparent).
+    pub y4m: bool,
+    /// Écrit aussi `{nom}_nobg.png` animé (APNG).
+    pub apng: bool,
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self {
+            temporal_smoothing: 0.5,
+            fps: 25.0,
+            y4m: false,
+            apng: false,
+        }
+    }
+}
+
+/// Bilan retourné au frontend.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VideoSummary {
+    pub frames: usize,
+    pub output_dir: String,
+    pub y4m_path: Option<String>,
+    pub apng_path: Option<String>,
+    pub cancelled: bool,
+}
+
+/// Cadence décimale → fraction `(num, den)` images par seconde.
+pub fn fps_ratio(fps: f32) -> (u32, u32) {
+    let fps = if fps.is_finite() && fps > 0.0 { fps } else { 25.0 };
+    reduce(((fps * 1000.0).round() as u32).max(1), 1000)
+}
+
+fn reduce(num: u32, den: u32) -> (u32, u32) {
+    fn gcd(a: u32, b: u32) -> u32 {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+    let g = gcd(num, den).max(1);
+    (num / g, den / g)
+}
+
+// ─── Sources ─────────────────────────────────────────────────────────────────
+
+/// Entrée d'une séquence : dossier d'images numérotées ou fichier Y4M.
+pub enum FrameSource {
+    Directory {
+        frames: Vec<PathBuf>,
+        next: usize,
+        max_megapixels: f32,
+    },
+    Y4m(Y4mReader<BufReader<File>>),
+}
+
+/// Numéro final du nom de fichier (`frame_0042.png` → 42).
+fn frame_number(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits: String = stem
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    digits.parse().ok()
+}
+
+/// Sortie d'un passage précédent (`clip_nobg_00012.png`, APNG `clip_nobg.png`) :
+/// jamais reprise comme image source.
+fn is_output_frame(path: &Path) -> bool {
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return false;
+    };
+    let base = stem.trim_end_matches(|c: char| c.is_ascii_digit()).trim_end_matches('_');
+    OUTPUT_SUFFIXES.iter().any(|suffix| base.ends_with(suffix))
+}
+
+/// Refuse d'écrire la séquence dans le dossier d'images lu : un nouveau
+/// passage y trouverait les sorties du précédent.
+pub fn check_output_dir(input: &Path, output_dir: &Path) -> Result<()> {
+    let same = input.is_dir()
+        && input.canonicalize().ok().is_some_and(|i| output_dir.canonicalize().ok() == Some(i));
+    if same {
+        let message = tr!("video.same_folder", path = output_dir.display());
+        return Err(AppError::InvalidInput(message).into());
+    }
+    Ok(())
+}
+
+impl FrameSource {
+    pub fn open(input: &Path, max_megapixels: f32) -> Result<Self> {
+        if !input.is_dir() {
+            return Ok(FrameSource::Y4m(Y4mReader::open(input, max_megapixels)?));
+        }
+
+        let mut frames: Vec<PathBuf> = std::fs::read_dir(input)
+            .map_err(|e| {
+                AppError::io(input, &e, tr!("io.read_dir", path = input.display(), error = e))
+            })?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && !is_output_frame(p) && is_supported(p))
+            .collect();
+        if frames.is_empty() {
+            let message = tr!("video.no_images", path = input.display());
+            return Err(AppError::InvalidInput(message).into());
+        }
+        // Ordre numérique (frame_2 avant frame_10), puis alphabétique
+        frames.sort_by_key(|p| (frame_number(p), p.clone()));
+
+        Ok(FrameSource::Directory {
+            frames,
+            next: 0,
+            max_megapixels,
+        })
+    }
+
+    /// Nombre d'images attendu (estimé pour un flux Y4M).
+    pub fn len_hint(&self) -> Option<usize> {
+        match self {
+            FrameSource::Directory { frames, .. } => Some(frames.len()),
+            FrameSource::Y4m(reader) => reader.frames_hint,
+        }
+    }
+
+    /// Cadence déclarée par la source, si elle en a une.
+    pub fn fps(&self) -> Option<(u32, u32)> {
+        match self {
+            FrameSource::Directory { .. } => None,
+            FrameSource::Y4m(reader) => Some(reader.fps),
+        }
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<DynamicImage>> {
+        match self {
+            FrameSource::Directory {
+                frames,
+                next,
+                max_megapixels,
+            } => {
+                let Some(path) = frames.get(*next) else {
+                    return Ok(None);
+                };
+                *next += 1;
+                load_image(path, *max_megapixels).map(Some)
+            }
+            FrameSource::Y4m(reader) => Ok(reader.next_frame()?.map(DynamicImage::ImageRgba8)),
+        }
+    }
+}
+
+// ─── Y4M ─────────────────────────────────────────────────────────────────────
+
+/// Sous-échantillonnage de la chrominance (8 bits uniquement).
+#[derive(Debug, Clone, Copy)]
+enum Chroma {
+    C420,
+    C422,
+    C444,
+    C444Alpha,
+    Mono,
+}
+
+impl Chroma {
+    fn parse(tag: &str) -> Result<Self> {
+        match tag {
+            "420" | "420jpeg" | "420paldv" | "420mpeg2" => Ok(Chroma::C420),
+            "422" => Ok(Chroma::C422),
+            "444" => Ok(Chroma::C444),
+            "444alpha" => Ok(Chroma::C444Alpha),
+            "mono" => Ok(Chroma::Mono),
+            other => Err(AppError::Decode(tr!("y4m.colorspace", colorspace = other)).into()),
+        }
+    }
+
+    /// Dimensions d'un plan de chrominance.
+    fn plane_size(self, w: usize, h: usize) -> (usize, usize) {
+        match self {
+            Chroma::C420 => (w.div_ceil(2), h.div_ceil(2)),
+            Chroma::C422 => (w.div_ceil(2), h),
+            Chroma::C444 | Chroma::C444Alpha => (w, h),
+            Chroma::Mono => (0, 0),
+        }
+    }
+}
+
+/// YCbCr BT.601 → RGB, en plage limitée (16–235) sauf `XCOLORRANGE=FULL`.
+fn yuv_to_rgb(y: u8, u: u8, v: u8, full_range: bool) -> [u8; 3] {
+    let (d, e) = (u as f32 - 128.0, v as f32 - 128.0);
+    let (r, g, b) = if full_range {
+        let y = y as f32;
+        (y + 1.402 * e, y - 0.344_136 * d - 0.714_136 * e, y + 1.772 * d)
+    } else {
+        let c = 1.164_383 * (y as f32 - 16.0);
+        (c + 1.596_027 * e, c - 0.391_762 * d - 0.812_968 * e, c + 2.017_232 * d)
+    };
+    [r, g, b].map(|v| v.round().clamp(0.0, 255.0) as u8)
+}
+
+/// RGB → YCbCr BT.601 plage limitée.
+fn rgb_to_yuv(p: &Rgba<u8>) -> [u8; 3] {
+    let (r, g, b) = (p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0);
+    let y = 16.0 + 65.481 * r + 128.553 * g + 24.966 * b;
+    let u = 128.0 - 37.797 * r - 74.203 * g + 112.0 * b;
+    let v = 128.0 + 112.0 * r - 93.786 * g - 18.214 * b;
+    [y, u, v].map(|c| c.round().clamp(0.0, 255.0) as u8)
+}
+
+/// Lecteur de flux YUV4MPEG2 (en-tête texte puis `FRAME\n` + plans bruts).
+pub struct Y4mReader<R> {
+    reader: R,
+    width: u32,
+    height: u32,
+    fps: (u32, u32),
+    chroma: Chroma,
+    full_range: bool,
+    frames_hint: Option<usize>,
+}
+
+impl Y4mReader<BufReader<File>> {
+    pub fn open(path: &Path, max_megapixels: f32) -> Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| AppError::io(path, &e, tr!("io.open", path = path.display(), error = e)))?;
+        let file_len = file.metadata().map(|m| m.len() as usize).ok();
+        let mut reader = Self::new(BufReader::new(file))?;
+        check_megapixels(reader.width, reader.height, max_megapixels)?;
+
+        // "FRAME\n" (6 octets) devant chaque image dans le cas courant
+        reader.frames_hint = file_len.map(|len| len / (reader.frame_len() + 6));
+        Ok(reader)
+    }
+}
+
+impl<R: BufRead> Y4mReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = Vec::new();
+        (&mut reader)
+            .take(4096)
+            .read_until(b'\n', &mut header)
+            .map_err(|e| AppError::Decode(tr!("y4m.read_header", error = e)))?;
+        let header = String::from_utf8_lossy(&header);
+        let mut params = header.trim_end().split(' ');
+        if params.next() != Some("YUV4MPEG2") {
+            return Err(AppError::InvalidInput(tr!("video.unsupported_input")).into());
+        }
+
+        let (mut width, mut height, mut fps) = (0u32, 0u32, (25, 1));
+        let (mut chroma, mut full_range) = (Chroma::C420, false);
+        for param in params.filter(|p| !p.is_empty()) {
+            let (tag, value) = param.split_at(1);
+            let invalid = || AppError::Decode(tr!("y4m.invalid_param", param = param));
+            match tag {
+                "W" => width = value.parse().map_err(|_| invalid())?,
+                "H" => height = value.parse().map_err(|_| invalid())?,
+                "F" => {
+                    let (num, den) = value.split_once(':').ok_or_else(invalid)?;
+                    let num: u32 = num.parse().map_err(|_| invalid())?;
+                    let den: u32 = den.parse().map_err(|_| invalid())?;
+                    if num > 0 && den > 0 {
+                        fps = reduce(num, den);
+                    }
+                }
+                "C" => chroma = Chroma::parse(value)?,
+                "X" => full_range |= value.eq_ignore_ascii_case("COLORRANGE=FULL"),
+                _ => {}
+            }
+        }
+        if width == 0 || height == 0 {
+            return Err(AppError::Decode(tr!("y4m.missing_size")).into());
+        }
+
+        Ok(Self {
+            reader,
+            width,
+            height,
+            fps,
+            chroma,
+            full_range,
+            frames_hint: None,
+        })
+    }
+
+    fn frame_len(&self) -> usize {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let (cw, ch) = self.chroma.plane_size(w, h);
+        let alpha = matches!(self.chroma, Chroma::C444Alpha) as usize;
+        w * h * (1 + alpha) + 2 * cw * ch
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<RgbaImage>> {
+        let mut line = Vec::new();
+        let read = (&mut self.reader)
+            .take(4096)
+            .read_until(b'\n', &mut line)
+            .map_err(|e| AppError::Decode(tr!("y4m.read", error = e)))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if !line.starts_with(b"FRAME") {
+            return Err(AppError::Decode(tr!("y4m.invalid_frame")).into());
+        }
+
+        let mut data = vec![0u8; self.frame_len()];
+        self.reader
+            .read_exact(&mut data)
+            .map_err(|e| AppError::Decode(tr!("y4m.truncated", error = e)))?;
+        Ok(Some(self.to_rgba(&data)))
+    }
+
+    fn to_rgba(&self, data: &[u8]) -> RgbaImage {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let (cw, ch) = self.chroma.plane_size(w, h);
+        let (y_plane, rest) = data.split_at(w * h);
+        let (u_plane, rest) = rest.split_at(cw * ch);
+        let (v_plane, a_plane) = rest.split_at(cw * ch);
+
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let (x, y) = (x as usize, y as usize);
+            let (u, v) = if cw == 0 {
+                (128, 128)
+            } else {
+                let i = (y * ch / h) * cw + x * cw / w;
+                (u_plane[i], v_plane[i])
+            };
+            let [r, g, b] = yuv_to_rgb(y_plane[y * w + x], u, v, self.full_range);
+            let a = a_plane.get(y * w + x).copied().unwrap_or(255);
+            Rgba([r, g, b, a])
+        })
+    }
+}
+
+/// Écrit un flux YUV4MPEG2 4:4:4 (`C444alpha` si `alpha`).
+pub struct Y4mWriter<W: Write> {
+    out: W,
+    size: (u32, u32),
+    alpha: bool,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(mut out: W, size: (u32, u32), fps: (u32, u32), alpha: bool) -> Result<Self> {
+        let colorspace = if alpha { "C444alpha" } else { "C444" };
+        writeln!(out, "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 {colorspace}", size.0, size.1, fps.0, fps.1)
+            .map_err(|e| AppError::Io(tr!("y4m.write", error = e)))?;
+        Ok(Self { out, size, alpha })
+    }
+
+    pub fn write_frame(&mut self, rgba: &RgbaImage) -> Result<()> {
+        if rgba.dimensions() != self.size {
+            return Err(AppError::Encode(tr!("video.size_mismatch")).into());
+        }
+
+        let n = rgba.len() / 4;
+        let mut planes = vec![0u8; n * if self.alpha { 4 } else { 3 }];
+        for (i, p) in rgba.pixels().enumerate() {
+            let [y, u, v] = rgb_to_yuv(p);
+            planes[i] = y;
+            planes[n + i] = u;
+            planes[2 * n + i] = v;
+            if self.alpha {
+                planes[3 * n + i] = p[3];
+            }
+        }
+
+        self.out
+            .write_all(b"FRAME\n")
+            .and_then(|_| self.out.write_all(&planes))
+            .map_err(|e| AppError::Io(tr!("y4m.write", error = e)).into())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.out.flush().map_err(|e| AppError::Io(tr!("y4m.write", error = e)).into())
+    }
+}
+
+// ─── Lissage temporel ────────────────────────────────────────────────────────
+
+/// Lissage des masques en flux : chaque image ressort avec une image de
+/// retard, le temps de connaître le masque de sa voisine suivante.
+pub struct TemporalSmoother {
+    strength: f32,
+    prev: Option<GrayImage>,
+    pending: Option<(DynamicImage, GrayImage)>,
+}
+
+impl TemporalSmoother {
+    pub fn new(strength: f32) -> Self {
+        Self {
+            strength,
+            prev: None,
+            pending: None,
+        }
+    }
+
+    /// Ajoute une image et son masque brut ; retourne l'image précédente avec
+    /// son masque lissé.
+    pub fn push(&mut self, frame: DynamicImage, mask: GrayImage) -> Option<(DynamicImage, GrayImage)> {
+        let ready = self.pending.take().map(|(img, current)| {
+            let prev = self.prev.take().unwrap_or_else(|| current.clone());
+            let smoothed = smooth_mask(&prev, &current, &mask, self.strength);
+            self.prev = Some(current);
+            (img, smoothed)
+        });
+        self.pending = Some((frame, mask));
+        ready
+    }
+
+    /// Dernière image de la séquence (sans voisine suivante).
+    pub fn finish(&mut self) -> Option<(DynamicImage, GrayImage)> {
+        let (img, current) = self.pending.take()?;
+        let prev = self.prev.take().unwrap_or_else(|| current.clone());
+        let smoothed = smooth_mask(&prev, &current, &current, self.strength);
+        Some((img, smoothed))
+    }
+}
+
+// ─── Sorties ─────────────────────────────────────────────────────────────────
+
+/// Écrit la séquence `{nom}_nobg_00000.png`… et, en option, le Y4M au fil de
+/// l'eau puis l'APNG à la fin (assemblé depuis les PNG écrits).
+/// Le suffixe `_nobg` évite d'écraser la source (`{nom}.y4m`, ou les images
+/// `{nom}_00000.png` d'un dossier) quand la sortie est à côté d'elle.
+pub struct SequenceWriter {
+    output_dir: PathBuf,
+    stem: String,
+    fps: (u32, u32),
+    frames: Vec<PathBuf>,
+    alpha: bool,
+    want_y4m: bool,
+    want_apng: bool,
+    y4m: Option<(PathBuf, Y4mWriter<BufWriter<File>>)>,
+}
+
+impl SequenceWriter {
+    pub fn new(
+        output_dir: &Path,
+        stem: &str,
+        fps: (u32, u32),
+        options: &VideoOptions,
+        alpha: bool,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(output_dir)
+            .map_err(|e| AppError::Io(tr!("io.create", path = output_dir.display(), error = e)))?;
+        Ok(Self {
+            output_dir: output_dir.to_path_buf(),
+            stem: format!("{stem}_nobg"),
+            fps,
+            frames: Vec::new(),
+            alpha,
+            want_y4m: options.y4m,
+            want_apng: options.apng,
+            y4m: None,
+        })
+    }
+
+    pub fn frames_written(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn write(&mut self, frame: &DynamicImage) -> Result<()> {
+        let dest = self
+            .output_dir
+            .join(format!("{}_{:05}.png", self.stem, self.frames.len()));
+        let dest = save_output(frame, &dest, &OutputFormat::default(), &ImageMetadata::default())?;
+        self.frames.push(dest);
+
+        if self.want_y4m {
+            if self.y4m.is_none() {
+                let path = self.output_dir.join(format!("{}.y4m", self.stem));
+                let file = File::create(&path)
+                    .map_err(|e| AppError::Io(tr!("io.create", path = path.display(), error = e)))?;
+                let size = (frame.width(), frame.height());
+                let writer = Y4mWriter::new(BufWriter::new(file), size, self.fps, self.alpha)?;
+                self.y4m = Some((path, writer));
+            }
+            if let Some((_, writer)) = &mut self.y4m {
+                writer.write_frame(&frame.to_rgba8())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Termine les sorties ; l'APNG n'est pas assemblé si le traitement a été
+    /// annulé (le Y4M partiel reste lisible).
+    pub fn finish(self, cancelled: bool) -> Result<VideoSummary> {
+        let y4m_path = match self.y4m {
+            Some((path, writer)) => {
+                writer.finish()?;
+                Some(path.to_string_lossy().to_string())
+            }
+            None => None,
+        };
+
+        let apng_path = if self.want_apng && !cancelled && !self.frames.is_empty() {
+            let path = self.output_dir.join(format!("{}.png", self.stem));
+            write_apng(&self.frames, &path, self.fps)?;
+            Some(path.to_string_lossy().to_string())
+        } else {
+            None
+        };
+
+        Ok(VideoSummary {
+            frames: self.frames.len(),
+            output_dir: self.output_dir.to_string_lossy().to_string(),
+            y4m_path,
+            apng_path,
+            cancelled,
+        })
+    }
+}
+
+/// Assemble un PNG animé RGBA 8 bits à cadence fixe.
+fn write_apng(frames: &[PathBuf], dest: &Path, fps: (u32, u32)) -> Result<()> {
+    let apng_err =
+        |e: png::EncodingError| AppError::Encode(tr!("codec.encode", format = "APNG", error = e));
+
+    let first = image::open(&frames[0])
+        .map_err(|e| AppError::Decode(tr!("video.reread_frame", error = e)))?;
+    let (w, h) = (first.width(), first.height());
+
+    let file = File::create(dest)
+        .map_err(|e| AppError::Io(tr!("io.create", path = dest.display(), error = e)))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), w, h);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0).map_err(apng_err)?;
+
+    // Délai d'une image = den / num secondes, chaque terme sur 16 bits
+    let (mut num, mut den) = (fps.1, fps.0);
+    while num > u16::MAX as u32 || den > u16::MAX as u32 {
+        num = (num / 2).max(1);
+        den = (den / 2).max(1);
+    }
+    encoder.set_frame_delay(num as u16, den as u16).map_err(apng_err)?;
+
+    let mut writer = encoder.write_header().map_err(apng_err)?;
+    for path in frames {
+        let frame = image::open(path)
+            .map_err(|e| AppError::Decode(tr!("io.reread", path = path.display(), error = e)))?
+            .to_rgba8();
+        if frame.dimensions() != (w, h) {
+            return Err(AppError::Encode(tr!("video.size_mismatch")).into());
+        }
+        writer.write_image_data(&frame).map_err(apng_err)?;
+    }
+    Ok(writer.finish().map_err(apng_err)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::AnimationDecoder;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pureremove-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Écart d'un canal après l'aller-retour YCbCr 8 bits.
+    fn assert_close(a: &RgbaImage, b: &RgbaImage) {
+        assert_eq!(a.dimensions(), b.dimensions());
+        for (p, q) in a.pixels().zip(b.pixels()) {
+            let diff = p.0.iter().zip(q.0).map(|(&x, y)| x.abs_diff(y)).max().unwrap();
+            assert!(diff <= 2, "{p:?} ≠ {q:?}");
+        }
+    }
+
+    fn frame(seed: u8) -> RgbaImage {
+        RgbaImage::from_fn(6, 4, |x, y| {
+            Rgba([seed.wrapping_add(x as u8 * 40), y as u8 * 60, 200 - seed, (x * 50) as u8])
+        })
+    }
+
+    #[test]
+    fn y4m_444_alpha_round_trip() {
+        let frames = [frame(10), frame(90)];
+        let mut out = Vec::new();
+        let mut writer = Y4mWriter::new(&mut out, (6, 4), (30000, 1001), true).unwrap();
+        for f in &frames {
+            writer.write_frame(f).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut reader = Y4mReader::new(&out[..]).unwrap();
+        assert_eq!(reader.fps, (30000, 1001));
+        let mut read = Vec::new();
+        while let Some(f) = reader.next_frame().unwrap() {
+            read.push(f);
+        }
+        assert_eq!(read.len(), frames.len());
+        for (a, b) in read.iter().zip(&frames) {
+            assert_close(a, b);
+            assert!(a.pixels().zip(b.pixels()).all(|(p, q)| p[3] == q[3]));
+        }
+    }
+
+    #[test]
+    fn y4m_420_reads_subsampled_chroma() {
+        // Blocs 2×2 de même couleur : le sous-échantillonnage ne perd rien
+        let colors = [[250, 20, 20], [20, 250, 20], [20, 20, 250], [128, 128, 128]];
+        let expected = RgbaImage::from_fn(4, 4, |x, y| {
+            let [r, g, b] = colors[(y / 2 * 2 + x / 2) as usize];
+            Rgba([r, g, b, 255])
+        });
+        let mut stream = b"YUV4MPEG2 W4 H4 F25:1 Ip C420jpeg\n".to_vec();
+        for _ in 0..3 {
+            let yuv: Vec<[u8; 3]> = expected.pixels().map(rgb_to_yuv).collect();
+            stream.extend_from_slice(b"FRAME\n");
+            stream.extend(yuv.iter().map(|p| p[0]));
+            for plane in [1, 2] {
+                stream.extend((0..4).map(|i| yuv[(i / 2 * 8 + i % 2 * 2) as usize][plane]));
+            }
+        }
+
+        let mut reader = Y4mReader::new(&stream[..]).unwrap();
+        let mut count = 0;
+        while let Some(f) = reader.next_frame().unwrap() {
+            assert_close(&f, &expected);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn apng_round_trip() {
+        let dir = temp_dir("apng");
+        let frames = [frame(0), frame(70), frame(140)];
+        let paths: Vec<PathBuf> = frames
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let path = dir.join(format!("clip_nobg_{i:05}.png"));
+                f.save(&path).unwrap();
+                path
+            })
+            .collect();
+        let dest = dir.join("clip_nobg.png");
+        write_apng(&paths, &dest, (25, 1)).unwrap();
+
+        let file = BufReader::new(File::open(&dest).unwrap());
+        let decoder = image::codecs::png::PngDecoder::new(file).unwrap();
+        let read = decoder.apng().unwrap().into_frames().collect_frames().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(read.len(), frames.len());
+        for (a, b) in read.iter().zip(&frames) {
+            assert_eq!(a.buffer(), b);
+            assert_eq!(a.delay().numer_denom_ms(), (40, 1));
+        }
+    }
+
+    #[test]
+    fn previous_outputs_are_not_frames() {
+        let dir = temp_dir("frames");
+        for name in ["clip_00000.png", "clip_00001.png", "clip_nobg_00000.png", "clip_nobg.png"] {
+            frame(0).save(dir.join(name)).unwrap();
+        }
+        let source = FrameSource::open(&dir, 100.0);
+        let rejected = check_output_dir(&dir, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(source.unwrap().len_hint(), Some(2));
+        assert!(rejected.is_err());
+    }
+}
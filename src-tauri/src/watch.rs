@@ -0,0 +1,646 @@
+//! watch.rs — Dossiers surveillés (« hot folders ») : chaque dossier d'entrée
+//! est relevé à intervalle régulier (fiable sur un partage réseau, où les
+//! notifications du système manquent), et toute image nouvelle y est traitée
+//! avec les réglages du fichier JSON choisi pour ce dossier.
+//!
+//! - Un fichier n'est pris qu'une fois complètement écrit : taille et date de
+//!   modification inchangées pendant `settle_ms`, et ouvrable en lecture.
+//! - Le traitement passe par la file (`JobKind::Watch`, priorité des lots) ;
+//!   la sortie est écrite dans `output`, puis l'original est déplacé dans le
+//!   sous-dossier `done` ou `failed`.
+//! - Chaque fichier traité ajoute une ligne JSON au journal `LOG_FILE` du
+//!   dossier d'entrée, conservé d'une session à l'autre.
+
+use anyhow::Result;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::commands::{process_to_folder, ProcessOptions};
+use crate::error::{AppError, AppResult};
+use crate::image_processor::OutputFormat;
+use crate::jobs::{self, JobId, JobKind};
+use crate::scan::{self, ScanOptions};
+use crate::tr;
+
+/// Journal des fichiers traités, une entrée JSON par ligne (dans `input`).
+pub const LOG_FILE: &str = "pureremove-watch.jsonl";
+
+// ─── Configuration ───────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchConfig {
+    /// Dossier surveillé (sans ses sous-dossiers).
+    pub input: String,
+    /// Destination des détourages (`{stem}_nobg.{ext}`).
+    pub output: String,
+    /// Réglages de traitement (`WatchProfile`), relus quand le fichier change.
+    pub options_file: String,
+    /// Originaux traités ; `input/done` par défaut.
+    #[serde(default)]
+    pub done_folder: Option<String>,
+    /// Originaux en échec ; `input/failed` par défaut.
+    #[serde(default)]
+    pub failed_folder: Option<String>,
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Délai sans changement avant de considérer un fichier comme écrit.
+    #[serde(default = "default_settle_ms")]
+    pub settle_ms: u64,
+    /// Filtres de sélection ; `recursive` et `output_folder` sont ignorés.
+    #[serde(default)]
+    pub scan: ScanOptions,
+}
+
+fn default_poll_interval_ms() -> u64 {
+    2000
+}
+
+fn default_settle_ms() -> u64 {
+    3000
+}
+
+/// Contenu du fichier `options_file` : un `ProcessOptions`, plus le format
+/// de sortie (PNG par défaut).
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchProfile {
+    #[serde(flatten)]
+    pub options: ProcessOptions,
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+fn load_profile(path: &Path) -> Result<WatchProfile> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| AppError::io(path, &e, tr!("io.read", path = path.display(), error = e)))?;
+    serde_json::from_str(&text).map_err(|e| {
+        AppError::InvalidInput(tr!("watch.bad_options", path = path.display(), error = e)).into()
+    })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// ─── Événements et journal ───────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchStatus {
+    Done,
+    Failed,
+}
+
+/// Une ligne du journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchLogEntry {
+    /// Fin du traitement, en millisecondes depuis l'epoch Unix.
+    pub time_ms: u64,
+    /// Chemin d'origine.
+    pub file: String,
+    pub status: WatchStatus,
+    /// Détourage écrit.
+    pub output: Option<String>,
+    /// Nouvel emplacement de l'original (absent si le déplacement a échoué).
+    pub moved_to: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchEventKind {
+    Started,
+    Stopped,
+    /// Fichier écrit et stable, mis en file.
+    Queued,
+    Done,
+    Failed,
+    OptionsReloaded,
+    /// Dossier illisible, réglages invalides… ; la surveillance continue.
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchEvent {
+    /// `WatchConfig::input`, tel que donné à `start_watch`.
+    pub input: String,
+    pub event: WatchEventKind,
+    pub file: Option<String>,
+    /// Présente pour `done` et `failed`.
+    pub entry: Option<WatchLogEntry>,
+    pub error: Option<String>,
+}
+
+impl WatchEvent {
+    fn new(watch: &Watch, event: WatchEventKind) -> Self {
+        Self {
+            input: watch.config.input.clone(),
+            event,
+            file: None,
+            entry: None,
+            error: None,
+        }
+    }
+}
+
+type Listener = Box<dyn Fn(&WatchEvent) + Send + Sync>;
+
+static LISTENER: OnceCell<Listener> = OnceCell::new();
+
+/// Installe la destination des événements (une fois, au démarrage).
+pub fn set_listener(listener: impl Fn(&WatchEvent) + Send + Sync + 'static) {
+    let _ = LISTENER.set(Box::new(listener));
+}
+
+fn emit(event: WatchEvent) {
+    if let Some(listener) = LISTENER.get() {
+        listener(&event);
+    }
+}
+
+fn append_log(input: &Path, entry: &WatchLogEntry) -> Result<()> {
+    let path = input.join(LOG_FILE);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| AppError::Io(tr!("io.open", path = path.display(), error = e)))?;
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    // Une seule écriture par ligne : pas d'entrelacement en mode ajout
+    file.write_all(line.as_bytes())
+        .map_err(|e| AppError::Io(tr!("io.save", path = path.display(), error = e)))?;
+    Ok(())
+}
+
+/// Dernières entrées du journal de `input` (les plus récentes en dernier).
+/// Les lignes illisibles sont ignorées.
+pub fn read_log(input: &Path, limit: usize) -> Result<Vec<WatchLogEntry>> {
+    let path = input.join(LOG_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::Io(tr!("io.read", path = path.display(), error = e)).into()),
+    };
+    let entries: Vec<WatchLogEntry> =
+        text.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+/// Fichiers restés en place lors d'un lancement précédent (déplacement
+/// impossible, voir `finish`) : dernière entrée du journal sans `moved_to`,
+/// fichier inchangé depuis. Sans cela, ils seraient traités une seconde fois.
+fn stuck_from_log(input: &Path) -> HashSet<PathBuf> {
+    let Ok(entries) = read_log(input, usize::MAX) else {
+        return HashSet::new();
+    };
+    let mut last: HashMap<PathBuf, WatchLogEntry> = HashMap::new();
+    for entry in entries {
+        last.insert(PathBuf::from(&entry.file), entry);
+    }
+    last.into_iter()
+        .filter(|(path, entry)| {
+            let modified_ms = modified(path)
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64);
+            entry.moved_to.is_none() && modified_ms.is_some_and(|ms| ms <= entry.time_ms)
+        })
+        .map(|(path, _)| path)
+        .collect()
+}
+
+// ─── Surveillance ────────────────────────────────────────────────────────────
+
+/// Dossier surveillé, partagé entre le thread de relevé et les traitements.
+struct Watch {
+    config: WatchConfig,
+    input: PathBuf,
+    output: PathBuf,
+    done: PathBuf,
+    failed: PathBuf,
+    options_file: PathBuf,
+    scan: ScanOptions,
+    /// Fichiers en file ou en cours ; l'identifiant arrive après la soumission.
+    in_flight: Mutex<HashMap<PathBuf, Option<JobId>>>,
+    /// Traités mais restés en place (déplacement impossible) : pas repris,
+    /// même après un redémarrage (voir `stuck_from_log`).
+    stuck: Mutex<HashSet<PathBuf>>,
+}
+
+impl Watch {
+    fn in_flight(&self) -> MutexGuard<'_, HashMap<PathBuf, Option<JobId>>> {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn stuck(&self) -> MutexGuard<'_, HashSet<PathBuf>> {
+        self.stuck.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn info(&self) -> WatchInfo {
+        WatchInfo {
+            config: self.config.clone(),
+            in_flight: self.in_flight().len(),
+        }
+    }
+}
+
+/// Retire le fichier de `in_flight` à la fin du traitement, même annulé
+/// avant d'avoir démarré (le travail est alors jeté sans être exécuté).
+struct InFlightGuard {
+    watch: Arc<Watch>,
+    path: PathBuf,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.watch.in_flight().remove(&self.path);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchInfo {
+    pub config: WatchConfig,
+    /// Fichiers en file ou en cours de traitement.
+    pub in_flight: usize,
+}
+
+struct Running {
+    watch: Arc<Watch>,
+    /// Fermé à l'arrêt : réveille le thread de relevé, qui s'arrête.
+    _stop: Sender<()>,
+}
+
+static WATCHES: Lazy<Mutex<HashMap<PathBuf, Running>>> = Lazy::new(Default::default);
+
+fn watches() -> MutexGuard<'static, HashMap<PathBuf, Running>> {
+    WATCHES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn resolve(base: &Path, folder: Option<&str>, default: &str) -> Result<PathBuf> {
+    let path = folder.map(PathBuf::from).unwrap_or_else(|| base.join(default));
+    fs::create_dir_all(&path)
+        .map_err(|e| AppError::Io(tr!("io.create", path = path.display(), error = e)))?;
+    Ok(path.canonicalize()?)
+}
+
+/// Démarre (ou redémarre, avec la nouvelle configuration) la surveillance
+/// de `config.input`.
+pub fn start(config: WatchConfig) -> Result<WatchInfo> {
+    let input = Path::new(&config.input);
+    if !input.is_dir() {
+        return Err(AppError::FileNotFound { path: config.input.clone() }.into());
+    }
+    let input = input.canonicalize()?;
+    let output = resolve(&input, Some(config.output.as_str()), "")?;
+    let done = resolve(&input, config.done_folder.as_deref(), "done")?;
+    let failed = resolve(&input, config.failed_folder.as_deref(), "failed")?;
+    // Une sortie rangée dans le dossier surveillé serait reprise indéfiniment
+    for folder in [&output, &done, &failed] {
+        if *folder == input {
+            let folder = folder.display();
+            return Err(AppError::InvalidInput(tr!("watch.same_folder", path = folder)).into());
+        }
+    }
+
+    let options_file = PathBuf::from(&config.options_file);
+    let profile = load_profile(&options_file)?;
+    let scan = ScanOptions { recursive: false, output_folder: None, ..config.scan.clone() };
+
+    let watch = Arc::new(Watch {
+        config,
+        input: input.clone(),
+        output,
+        done,
+        failed,
+        options_file,
+        scan,
+        in_flight: Mutex::new(HashMap::new()),
+        stuck: Mutex::new(stuck_from_log(&input)),
+    });
+    let _ = stop(&input);
+
+    let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+    let thread_watch = watch.clone();
+    std::thread::Builder::new()
+        .name("watch".into())
+        .spawn(move || run(thread_watch, profile, stop_receiver))?;
+
+    let info = watch.info();
+    watches().insert(input, Running { watch, _stop: stop_sender });
+    Ok(info)
+}
+
+/// Arrête la surveillance de `input` et annule ses traitements en attente :
+/// les fichiers concernés restent dans le dossier d'entrée.
+pub fn stop(input: &Path) -> AppResult<()> {
+    let key = input.canonicalize().unwrap_or_else(|_| input.to_path_buf());
+    let running = watches().remove(&key).ok_or_else(|| {
+        AppError::InvalidInput(tr!("watch.not_found", path = input.display()))
+    })?;
+    let ids: Vec<JobId> = running.watch.in_flight().values().flatten().copied().collect();
+    drop(running);
+    for id in ids {
+        let _ = jobs::cancel(id);
+    }
+    Ok(())
+}
+
+pub fn list() -> Vec<WatchInfo> {
+    let mut infos: Vec<WatchInfo> = watches().values().map(|r| r.watch.info()).collect();
+    infos.sort_by(|a, b| a.config.input.cmp(&b.config.input));
+    infos
+}
+
+/// Taille et date de modification lors du dernier relevé.
+#[derive(PartialEq, Eq)]
+struct Signature(u64, Option<SystemTime>);
+
+struct Observation {
+    signature: Signature,
+    since: Instant,
+}
+
+/// Thread de relevé, jusqu'à la fermeture de `stop`.
+fn run(watch: Arc<Watch>, profile: WatchProfile, stop: mpsc::Receiver<()>) {
+    let interval = Duration::from_millis(watch.config.poll_interval_ms.max(100));
+    let mut profile = Arc::new(profile);
+    let mut options_modified = modified(&watch.options_file);
+    let mut observed: HashMap<PathBuf, Observation> = HashMap::new();
+    // Erreur signalée une seule fois tant qu'elle se répète
+    let mut last_error: Option<String> = None;
+
+    emit(WatchEvent::new(&watch, WatchEventKind::Started));
+    loop {
+        let current = modified(&watch.options_file);
+        if current != options_modified {
+            options_modified = current;
+            match load_profile(&watch.options_file) {
+                Ok(reloaded) => {
+                    profile = Arc::new(reloaded);
+                    emit(WatchEvent::new(&watch, WatchEventKind::OptionsReloaded));
+                }
+                // Les réglages précédents restent en vigueur
+                Err(e) => report_error(&watch, &mut last_error, e.to_string()),
+            }
+        }
+
+        match poll(&watch, &profile, &mut observed) {
+            Ok(()) => last_error = None,
+            Err(e) => report_error(&watch, &mut last_error, e.to_string()),
+        }
+
+        match stop.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => {}
+            _ => break,
+        }
+    }
+    emit(WatchEvent::new(&watch, WatchEventKind::Stopped));
+}
+
+fn report_error(watch: &Watch, last_error: &mut Option<String>, error: String) {
+    if last_error.as_ref() == Some(&error) {
+        return;
+    }
+    emit(WatchEvent {
+        error: Some(error.clone()),
+        ..WatchEvent::new(watch, WatchEventKind::Error)
+    });
+    *last_error = Some(error);
+}
+
+/// Relevé du dossier : met en file les fichiers stables depuis `settle_ms`.
+fn poll(
+    watch: &Arc<Watch>,
+    profile: &Arc<WatchProfile>,
+    observed: &mut HashMap<PathBuf, Observation>,
+) -> Result<()> {
+    let report = scan::scan_folder(&watch.input, &watch.scan)?;
+    let settle = Duration::from_millis(watch.config.settle_ms);
+    let now = Instant::now();
+    // Ordre du relevé (noms triés) : les fichiers partent en file dans cet ordre
+    let files: Vec<PathBuf> = report.files.into_iter().map(|f| PathBuf::from(f.path)).collect();
+    let present: HashSet<&PathBuf> = files.iter().collect();
+    observed.retain(|path, _| present.contains(path));
+    watch.stuck().retain(|path| present.contains(path));
+
+    for path in files {
+        if watch.in_flight().contains_key(&path) || watch.stuck().contains(&path) {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(&path) else { continue };
+        let signature = Signature(metadata.len(), metadata.modified().ok());
+        let stable = observed
+            .get(&path)
+            .is_some_and(|o| o.signature == signature && now.duration_since(o.since) >= settle);
+        // Fichier vide : copie tout juste créée, pas encore remplie
+        if stable && metadata.len() > 0 && fs::File::open(&path).is_ok() {
+            observed.remove(&path);
+            submit(watch, path, profile.clone());
+        } else if observed.get(&path).is_none_or(|o| o.signature != signature) {
+            observed.insert(path, Observation { signature, since: now });
+        }
+    }
+    Ok(())
+}
+
+fn submit(watch: &Arc<Watch>, path: PathBuf, profile: Arc<WatchProfile>) {
+    // Réservé avant la soumission : le relevé suivant ne le reprend pas
+    watch.in_flight().insert(path.clone(), None);
+    emit(WatchEvent {
+        file: Some(path.display().to_string()),
+        ..WatchEvent::new(watch, WatchEventKind::Queued)
+    });
+
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let guard = InFlightGuard { watch: watch.clone(), path: path.clone() };
+    let job = jobs::submit(JobKind::Watch, name, Some(1), move |ctx| {
+        let watch = &guard.watch;
+        let started = Instant::now();
+        let (options, format) = (&profile.options, &profile.format);
+        let outcome = process_to_folder(&guard.path, options, format, &watch.output, ctx);
+        let (output, error) = match outcome {
+            Ok(output) => (Some(output), None),
+            Err(e) => match AppError::from(e) {
+                // Arrêt de la surveillance : l'original reste à traiter
+                AppError::Cancelled => return Err(AppError::Cancelled),
+                e => (None, Some(e)),
+            },
+        };
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+        finish(watch, &guard.path, output, error.as_ref(), duration_ms);
+        ctx.progress(1, None);
+        error.map_or(Ok(()), Err)
+    });
+
+    // Déjà terminé et retiré si le traitement a été plus rapide que nous
+    if let Some(slot) = watch.in_flight().get_mut(&path) {
+        *slot = Some(job.id);
+    }
+}
+
+/// Range l'original, journalise et émet le résultat d'un fichier.
+fn finish(
+    watch: &Watch,
+    path: &Path,
+    output: Option<PathBuf>,
+    error: Option<&AppError>,
+    duration_ms: f64,
+) {
+    let status = if error.is_none() { WatchStatus::Done } else { WatchStatus::Failed };
+    let folder = match status {
+        WatchStatus::Done => &watch.done,
+        WatchStatus::Failed => &watch.failed,
+    };
+    let mut errors: Vec<String> = error.map(|e| e.to_string()).into_iter().collect();
+    let moved_to = match move_into(path, folder) {
+        Ok(dest) => Some(dest.display().to_string()),
+        Err(e) => {
+            watch.stuck().insert(path.to_path_buf());
+            errors.push(e.to_string());
+            None
+        }
+    };
+
+    let entry = WatchLogEntry {
+        time_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        file: path.display().to_string(),
+        status,
+        output: output.map(|p| p.display().to_string()),
+        moved_to,
+        error: (!errors.is_empty()).then(|| errors.join(" ; ")),
+        duration_ms,
+    };
+    let log_error = append_log(&watch.input, &entry).err().map(|e| e.to_string());
+
+    let event = match status {
+        WatchStatus::Done => WatchEventKind::Done,
+        WatchStatus::Failed => WatchEventKind::Failed,
+    };
+    emit(WatchEvent {
+        file: Some(entry.file.clone()),
+        error: log_error,
+        entry: Some(entry),
+        ..WatchEvent::new(watch, event)
+    });
+}
+
+/// Déplace `path` dans `folder` sans écraser : `photo (2).jpg` si besoin.
+/// Copie puis suppression si le renommage est impossible (autre volume).
+fn move_into(path: &Path, folder: &Path) -> Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| AppError::InvalidInput(path.display().to_string()))?;
+    let mut dest = folder.join(name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy()));
+    let mut n = 2;
+    while dest.exists() {
+        dest = folder.join(format!("{stem} ({n}){}", extension.as_deref().unwrap_or("")));
+        n += 1;
+    }
+
+    if fs::rename(path, &dest).is_err() {
+        fs::copy(path, &dest)
+            .map_err(|e| AppError::Io(tr!("io.save", path = dest.display(), error = e)))?;
+        if let Err(e) = fs::remove_file(path) {
+            // Pas de doublon : l'original reste seul en place
+            let _ = fs::remove_file(&dest);
+            return Err(AppError::Io(tr!("watch.move", path = path.display(), error = e)).into());
+        }
+    }
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pureremove-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entry(file: &Path, time_ms: u64, moved_to: Option<&str>) -> WatchLogEntry {
+        WatchLogEntry {
+            time_ms,
+            file: file.display().to_string(),
+            status: WatchStatus::Done,
+            output: Some("photo_nobg.png".into()),
+            moved_to: moved_to.map(str::to_string),
+            error: None,
+            duration_ms: 12.5,
+        }
+    }
+
+    #[test]
+    fn move_into_never_overwrites() {
+        let dir = temp_dir("watch-move");
+        let done = dir.join("done");
+        fs::create_dir_all(&done).unwrap();
+        fs::write(done.join("photo.jpg"), "1").unwrap();
+        fs::write(done.join("photo (2).jpg"), "2").unwrap();
+        let source = dir.join("photo.jpg");
+        fs::write(&source, "3").unwrap();
+
+        let moved = move_into(&source, &done);
+        let contents = fs::read_to_string(done.join("photo (3).jpg"));
+        let source_left = source.exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(moved.unwrap(), done.join("photo (3).jpg"));
+        assert_eq!(contents.unwrap(), "3");
+        assert!(!source_left);
+    }
+
+    #[test]
+    fn log_lines_read_back() {
+        let dir = temp_dir("watch-log");
+        assert!(read_log(&dir, 10).unwrap().is_empty());
+        for time_ms in [1, 2, 3] {
+            append_log(&dir, &entry(&dir.join("photo.jpg"), time_ms, Some("done"))).unwrap();
+        }
+        // Ligne tronquée (arrêt pendant l'écriture) : ignorée
+        let mut file = OpenOptions::new().append(true).open(dir.join(LOG_FILE)).unwrap();
+        file.write_all(b"{\"time_ms\": 4, \"fi\n").unwrap();
+
+        let all = read_log(&dir, 10).unwrap();
+        let last = read_log(&dir, 2).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(all.iter().map(|e| e.time_ms).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(last.iter().map(|e| e.time_ms).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(last[1].status, WatchStatus::Done);
+        assert_eq!(last[1].output.as_deref(), Some("photo_nobg.png"));
+    }
+
+    #[test]
+    fn unmoved_files_stay_stuck_across_restarts() {
+        let dir = temp_dir("watch-stuck");
+        let (stuck, retried, moved) =
+            (dir.join("stuck.jpg"), dir.join("retried.jpg"), dir.join("moved.jpg"));
+        for path in [&stuck, &retried, &moved] {
+            fs::write(path, "x").unwrap();
+        }
+        let later = u64::MAX / 2;
+        append_log(&dir, &entry(&stuck, later, None)).unwrap();
+        // Modifié depuis son entrée : à traiter de nouveau
+        append_log(&dir, &entry(&retried, 0, None)).unwrap();
+        append_log(&dir, &entry(&moved, later, None)).unwrap();
+        append_log(&dir, &entry(&moved, later, Some("done/moved.jpg"))).unwrap();
+
+        let found = stuck_from_log(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, HashSet::from([stuck]));
+    }
+}
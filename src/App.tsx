@@ -6,22 +6,39 @@ import { DropZone } from "@/components/DropZone";
 import { SplitPreview } from "@/components/SplitPreview";
 import { BatchList } from "@/components/BatchList";
 import { OutputOptions } from "@/components/OutputOptions";
+import { WatchPanel } from "@/components/WatchPanel";
+import { LanguageSelect, applyStoredLocale } from "@/components/LanguageSelect";
 import { generateId } from "@/lib/utils";
-import type { AppMode, BackgroundColor, BatchProgressEvent, ImageItem, ProcessOptions } from "@/types";
+import type {
+  AppError, AppMode, BackgroundColor, BatchProgressEvent, ImageItem, JobEvent, LocaleInfo,
+  ProcessedResult, ProcessOptions, ScanReport, Stage,
+} from "@/types";
 
 // ─── Types locaux ────────────────────────────────────────────────────────────
 
 interface SingleState {
   sourceDataUrl: string;
   sourcePath: string;
-  resultDataUrl: string;
+  /** Aperçu ; le résultat complet reste côté Rust sous `resultId` */
+  resultUrl: string;
+  resultId?: number;
   isProcessing: boolean;
 }
 
+interface BatchJobState {
+  id: number;
+  paused: boolean;
+  etaMs?: number;
+  throughput?: number;
+}
+
 // ─── Utilitaire : sérialise n'importe quelle erreur en string lisible ─────────
 function toMsg(e: unknown): string {
   if (typeof e === "string") return e;
   if (e instanceof Error) return e.message;
+  if (e && typeof e === "object" && typeof (e as AppError).message === "string") {
+    return (e as AppError).message;
+  }
   try { return JSON.stringify(e); } catch { return String(e); }
 }
 
@@ -35,20 +52,48 @@ export default function App() {
   const [modelError, setModelError] = useState<string | null>(null);
   const [globalError, setGlobalError] = useState<string | null>(null);
   const [background, setBackground] = useState<BackgroundColor>({ type: "Transparent" });
+  const [batchJob, setBatchJob] = useState<BatchJobState | null>(null);
+  const [singleStage, setSingleStage] = useState<Stage | undefined>(undefined);
+  const [locale, setLocale] = useState<LocaleInfo | null>(null);
 
   // Source originale mémorisée pour retraitement quand le fond change
   const singleSourceRef = useRef<{ path: string; dataUrl: string } | null>(null);
   const bgInitRef = useRef(true); // Évite le retraitement au 1er rendu
   const batchUnlistenRef = useRef<(() => void) | null>(null);
-
-  // ── Vérification modèle au démarrage ────────────────────────────────────
+  const singleResultIdRef = useRef<number | null>(null);
+  // Image clipboard : son premier résultat sert d'« original » et reste en vie
+  const clipboardSourceIdRef = useRef<number | null>(null);
+  // Lot affiché ; les lots abandonnés (remplacés, réinitialisés) sont ignorés
+  const batchJobIdRef = useRef<number | null>(null);
+  const droppedJobIdsRef = useRef(new Set<number>());
+
+  // ── Langue mémorisée puis vérification modèle au démarrage ─────────────
   useEffect(() => {
-    invoke<string>("check_model").catch((e) => setModelError(toMsg(e)));
+    applyStoredLocale()
+      .then(setLocale)
+      .catch(() => invoke<LocaleInfo>("get_locale").then(setLocale))
+      .catch(() => {})
+      .finally(() => {
+        invoke<string>("check_model").catch((e) => setModelError(toMsg(e)));
+      });
   }, []);
 
   // ── Cleanup listener batch ───────────────────────────────────────────────
   useEffect(() => () => { batchUnlistenRef.current?.(); }, []);
 
+  // ── Étape en cours du traitement single / clipboard ─────────────────────
+  useEffect(() => {
+    const unlisten = listen<JobEvent>("job-event", (event) => {
+      const { kind, event: step, stage } = event.payload;
+      if (kind !== "single" && kind !== "clipboard") return;
+      if (step === "stage_started") setSingleStage(stage ?? undefined);
+      else if (step === "completed" || step === "failed" || step === "cancelled") {
+        setSingleStage(undefined);
+      }
+    });
+    return () => { unlisten.then((f) => f()); };
+  }, []);
+
   // ── Helpers ──────────────────────────────────────────────────────────────
 
   const getOptions = useCallback((): ProcessOptions => ({ background }), [background]);
@@ -58,36 +103,58 @@ export default function App() {
     setTimeout(() => setGlobalError(null), 7000);
   }, []);
 
+  // Un seul résultat vit en mode single : l'ancien est libéré côté Rust
+  const setSingleResult = useCallback((result: ProcessedResult) => {
+    const previous = singleResultIdRef.current;
+    singleResultIdRef.current = result.id;
+    if (previous !== null && previous !== clipboardSourceIdRef.current) {
+      invoke("release_results", { ids: [previous] }).catch(() => {});
+    }
+    setSingle((prev) => prev
+      ? { ...prev, resultUrl: result.url, resultId: result.id, isProcessing: false }
+      : null);
+  }, []);
+
+  // Libère l'« original » clipboard s'il n'est plus le résultat affiché
+  const dropClipboardSource = useCallback(() => {
+    const source = clipboardSourceIdRef.current;
+    clipboardSourceIdRef.current = null;
+    if (source !== null && source !== singleResultIdRef.current) {
+      invoke("release_results", { ids: [source] }).catch(() => {});
+    }
+  }, []);
+
   // ── Process single ────────────────────────────────────────────────────────
 
   const processSinglePath = useCallback(async (path: string, previewDataUrl: string) => {
+    dropClipboardSource();
     singleSourceRef.current = { path, dataUrl: previewDataUrl };
     setMode("single");
-    setSingle({ sourceDataUrl: previewDataUrl, sourcePath: path, resultDataUrl: "", isProcessing: true });
+    setSingle({ sourceDataUrl: previewDataUrl, sourcePath: path, resultUrl: "", isProcessing: true });
 
     try {
-      const result = await invoke<string>("process_single_image", {
+      const result = await invoke<ProcessedResult>("process_single_image", {
         path,
         options: getOptions(),
       });
-      setSingle((prev) => prev ? { ...prev, resultDataUrl: result, isProcessing: false } : null);
+      setSingleResult(result);
     } catch (e) {
       showError(`Erreur de traitement : ${toMsg(e)}`);
       setSingle((prev) => prev ? { ...prev, isProcessing: false } : null);
     }
-  }, [getOptions, showError]);
+  }, [getOptions, showError, setSingleResult, dropClipboardSource]);
 
   // ── Retraitement clipboard quand le fond change ──────────────────────────
   const reprocessClipboard = useCallback(async () => {
     setSingle((prev) => prev ? { ...prev, isProcessing: true } : null);
     try {
-      const result = await invoke<string>("reprocess_clipboard_image", { options: getOptions() });
-      setSingle((prev) => prev ? { ...prev, resultDataUrl: result, isProcessing: false } : null);
+      const result = await invoke<ProcessedResult>("reprocess_clipboard_image", { options: getOptions() });
+      setSingleResult(result);
     } catch (e) {
       showError(`Erreur retraitement clipboard : ${toMsg(e)}`);
       setSingle((prev) => prev ? { ...prev, isProcessing: false } : null);
     }
-  }, [getOptions, showError]);
+  }, [getOptions, showError, setSingleResult]);
 
   // ── Retraitement auto quand le fond change ────────────────────────────────
   useEffect(() => {
@@ -108,50 +175,124 @@ export default function App() {
 
   const processClipboard = useCallback(async () => {
     setMode("single");
-    setSingle({ sourceDataUrl: "", sourcePath: "", resultDataUrl: "", isProcessing: true });
+    setSingle({ sourceDataUrl: "", sourcePath: "", resultUrl: "", isProcessing: true });
     try {
-      const result = await invoke<string>("process_clipboard_image", { options: getOptions() });
-      singleSourceRef.current = { path: "", dataUrl: result };
-      setSingle({ sourceDataUrl: result, sourcePath: "", resultDataUrl: result, isProcessing: false });
+      const result = await invoke<ProcessedResult>("process_clipboard_image", { options: getOptions() });
+      dropClipboardSource();
+      clipboardSourceIdRef.current = result.id;
+      singleSourceRef.current = { path: "", dataUrl: result.url };
+      setSingle({ sourceDataUrl: result.url, sourcePath: "", resultUrl: "", isProcessing: true });
+      setSingleResult(result);
     } catch (e) {
       showError(`Erreur clipboard : ${toMsg(e)}`);
       setMode("idle");
       setSingle(null);
     }
-  }, [getOptions, showError]);
+  }, [getOptions, showError, setSingleResult, dropClipboardSource]);
 
   // ── Batch ─────────────────────────────────────────────────────────────────
 
-  const processBatch = useCallback(async (paths: string[]) => {
+  // Abandonne le lot affiché : annulé côté Rust, ses derniers événements ignorés
+  const dropBatchJob = useCallback(() => {
+    const id = batchJobIdRef.current;
+    batchJobIdRef.current = null;
+    setBatchJob(null);
+    if (id !== null) {
+      droppedJobIdsRef.current.add(id);
+      invoke("cancel_job", { id }).catch(() => {});
+    }
+  }, []);
+
+  // `root` : dossier scanné, dont les sous-dossiers sont recréés à la sauvegarde
+  const processBatch = useCallback(async (paths: string[], names?: string[], root?: string) => {
     singleSourceRef.current = null;
-    const items: ImageItem[] = paths.map((p) => ({
+    dropBatchJob();
+    const items: ImageItem[] = paths.map((p, i) => ({
       id: generateId(),
-      name: p.split(/[\\/]/).pop() ?? p,
+      name: names?.[i] ?? p.split(/[\\/]/).pop() ?? p,
       sourcePath: p,
-      status: "pending",
+      status: i === 0 ? "processing" : "pending",
     }));
     setBatchItems(items);
     setMode("batch");
 
     batchUnlistenRef.current?.();
-    const unlisten = await listen<BatchProgressEvent>("batch-progress", (event) => {
-      const { index, result_data_url, error } = event.payload;
+    const unlistenProgress = await listen<BatchProgressEvent>("batch-progress", (event) => {
+      const { job_id, index, result, error } = event.payload;
+      if (job_id !== batchJobIdRef.current) return;
       setBatchItems((prev) =>
         prev.map((item, i) =>
-          i === index ? { ...item, status: error ? "error" : "done", resultDataUrl: result_data_url, error } : item
+          i === index
+            ? {
+                ...item,
+                status: error ? "error" : "done",
+                resultId: result?.id,
+                resultUrl: result?.preview_url,
+                maskUrl: result?.mask_url ?? undefined,
+                error: error?.message,
+              }
+            : i === index + 1 && item.status === "pending"
+              ? { ...item, status: "processing" }
+              : item
         )
       );
     });
-    batchUnlistenRef.current = unlisten;
 
-    setBatchItems((prev) => prev.map((item) => ({ ...item, status: "processing" })));
+    // `queued` arrive avant tout autre événement du lot : il en donne l'identifiant
+    const unlistenJob = await listen<JobEvent>("job-event", (event) => {
+      const { id, kind, event: step, error } = event.payload;
+      if (kind !== "batch" || droppedJobIdsRef.current.has(id)) return;
+      if (step === "queued" && batchJobIdRef.current === null) {
+        batchJobIdRef.current = id;
+        setBatchJob({ id, paused: false });
+        return;
+      }
+      if (id !== batchJobIdRef.current) return;
+
+      if (step === "progress") {
+        const { eta_ms, throughput } = event.payload;
+        setBatchJob((prev) => prev && {
+          ...prev,
+          etaMs: eta_ms ?? undefined,
+          throughput: throughput ?? undefined,
+        });
+      } else if (step === "paused" || step === "resumed") {
+        setBatchJob((prev) => prev && { ...prev, paused: step === "paused" });
+      } else if (step === "completed" || step === "failed" || step === "cancelled") {
+        setBatchJob(null);
+        setBatchItems((prev) => prev.map((item) =>
+          item.status === "pending" || item.status === "processing"
+            ? { ...item, status: "cancelled" }
+            : item
+        ));
+        if (error) showError(`Erreur batch : ${error.message}`);
+      }
+    });
+    batchUnlistenRef.current = () => { unlistenProgress(); unlistenJob(); };
 
     try {
-      await invoke("process_batch_images", { paths, options: getOptions() });
+      await invoke<number>("process_batch_images", {
+        paths,
+        options: getOptions(),
+        root: root ?? null,
+      });
     } catch (e) {
       showError(`Erreur batch : ${toMsg(e)}`);
+      setBatchItems((prev) => prev.map((item) => ({ ...item, status: "error", error: toMsg(e) })));
     }
-  }, [getOptions, showError]);
+  }, [getOptions, showError, dropBatchJob]);
+
+  const handlePauseBatch = useCallback(() => {
+    if (batchJob) invoke("pause_job", { id: batchJob.id }).catch((e) => showError(toMsg(e)));
+  }, [batchJob, showError]);
+
+  const handleResumeBatch = useCallback(() => {
+    if (batchJob) invoke("resume_job", { id: batchJob.id }).catch((e) => showError(toMsg(e)));
+  }, [batchJob, showError]);
+
+  const handleCancelBatch = useCallback(() => {
+    if (batchJob) invoke("cancel_job", { id: batchJob.id }).catch((e) => showError(toMsg(e)));
+  }, [batchJob, showError]);
 
   // ── Dispatch fichiers (Tauri donne des paths Windows complets) ────────────
 
@@ -165,17 +306,32 @@ export default function App() {
     }
   }, [processSinglePath, processBatch]);
 
+  const processFolder = useCallback(async (folder: string) => {
+    try {
+      const report = await invoke<ScanReport>("scan_folder", { folder, scan: { recursive: true } });
+      if (report.files.length === 0) {
+        showError("Aucune image à traiter dans ce dossier");
+        return;
+      }
+      await processBatch(
+        report.files.map((f) => f.path),
+        report.files.map((f) => f.relative),
+        report.root,
+      );
+    } catch (e) { showError(`Lecture du dossier : ${toMsg(e)}`); }
+  }, [processBatch, showError]);
+
   // ── Actions single ────────────────────────────────────────────────────────
 
   const handleCopy = useCallback(async () => {
-    if (!single?.resultDataUrl) return;
+    if (single?.resultId === undefined) return;
     try {
-      await invoke("copy_result_to_clipboard", { dataUrl: single.resultDataUrl });
+      await invoke("copy_result_to_clipboard", { id: single.resultId });
     } catch (e) { showError(`Copie échouée : ${toMsg(e)}`); }
   }, [single, showError]);
 
   const handleSaveSingle = useCallback(async () => {
-    if (!single?.resultDataUrl) return;
+    if (single?.resultId === undefined) return;
     const baseName = single.sourcePath
       ? (single.sourcePath.split(/[\\/]/).pop()?.replace(/\.[^.]+$/, "") ?? "output")
       : "output";
@@ -188,7 +344,7 @@ export default function App() {
       if (!dest) return; // Annulé par l'utilisateur
 
       await invoke("save_result_to_file", {
-        dataUrl: single.resultDataUrl,
+        id: single.resultId,
         destPath: dest,
       });
     } catch (e) {
@@ -198,29 +354,34 @@ export default function App() {
 
   const handleReset = useCallback(() => {
     batchUnlistenRef.current?.();
+    dropBatchJob();
     singleSourceRef.current = null;
+    singleResultIdRef.current = null;
+    clipboardSourceIdRef.current = null;
+    invoke("clear_results").catch(() => {});
     setMode("idle");
     setSingle(null);
     setBatchItems([]);
-  }, []);
+  }, [dropBatchJob]);
 
   // ── Actions batch ─────────────────────────────────────────────────────────
 
   const handleSaveOne = useCallback(async (item: ImageItem) => {
-    if (!item.resultDataUrl) return;
-    const stem = item.name.replace(/\.[^.]+$/, "");
+    if (item.resultId === undefined) return;
+    // Nom relatif pour un dossier scanné (`sous/dossier/photo.jpg`)
+    const stem = item.name.split("/").pop()!.replace(/\.[^.]+$/, "");
     try {
       const dest = await save({
         defaultPath: `${stem}_nobg.png`,
         filters: [{ name: "PNG Image", extensions: ["png"] }],
       });
       if (!dest) return;
-      await invoke("save_result_to_file", { dataUrl: item.resultDataUrl, destPath: dest });
+      await invoke("save_result_to_file", { id: item.resultId, destPath: dest });
     } catch (e) { showError(`Sauvegarde échouée : ${toMsg(e)}`); }
   }, [showError]);
 
   const handleSaveAll = useCallback(async () => {
-    const doneItems = batchItems.filter((i) => i.status === "done" && i.resultDataUrl);
+    const doneItems = batchItems.filter((i) => i.status === "done" && i.resultId !== undefined);
     if (doneItems.length === 0) return;
 
     try {
@@ -228,8 +389,9 @@ export default function App() {
       if (!folder || typeof folder !== "string") return;
 
       setIsSavingBatch(true);
-      const items: [string, string][] = doneItems.map((i) => [i.name, i.resultDataUrl!]);
-      await invoke("save_batch_to_folder", { items, folder });
+      const ids = doneItems.map((i) => i.resultId!);
+      const masks = doneItems.filter((i) => i.maskUrl).map((i) => i.resultId!);
+      await invoke("save_batch_to_folder", { ids, folder, masks: masks.length ? masks : null });
     } catch (e) {
       showError(`Erreur lors de la sauvegarde : ${toMsg(e)}`);
     } finally {
@@ -251,7 +413,10 @@ export default function App() {
           <span className="text-muted-foreground text-xs bg-secondary px-2 py-0.5 rounded-full">v1.2</span>
         </div>
 
-        <OutputOptions value={background} onChange={setBackground} disabled={single?.isProcessing} />
+        <div className="flex items-end gap-4">
+          <LanguageSelect info={locale} onChange={setLocale} onError={showError} />
+          <OutputOptions value={background} onChange={setBackground} disabled={single?.isProcessing} />
+        </div>
       </header>
 
       {/* ── Bandeau modèle manquant ── */}
@@ -281,16 +446,27 @@ export default function App() {
       {/* ── Zone principale ── */}
       <main className="flex-1 min-h-0 p-4">
         {mode === "idle" && (
-          <DropZone onPaths={handlePaths} onPaste={processClipboard} disabled={!!modelError} />
+          <div className="flex flex-col h-full gap-4">
+            <div className="flex-1 min-h-0">
+              <DropZone
+                onPaths={handlePaths}
+                onFolder={processFolder}
+                onPaste={processClipboard}
+                disabled={!!modelError}
+              />
+            </div>
+            <WatchPanel onError={showError} disabled={!!modelError} />
+          </div>
         )}
         {mode === "single" && single && (
           <SplitPreview
             originalSrc={single.sourceDataUrl}
-            resultSrc={single.resultDataUrl || single.sourceDataUrl}
+            resultSrc={single.resultUrl || single.sourceDataUrl}
             onCopy={handleCopy}
             onSave={handleSaveSingle}
             onReset={handleReset}
             isProcessing={single.isProcessing}
+            stage={singleStage}
           />
         )}
         {mode === "batch" && (
@@ -300,6 +476,12 @@ export default function App() {
             onSaveOne={handleSaveOne}
             onReset={handleReset}
             isSaving={isSavingBatch}
+            jobState={batchJob ? (batchJob.paused ? "paused" : "running") : undefined}
+            etaMs={batchJob?.etaMs}
+            throughput={batchJob?.throughput}
+            onPause={handlePauseBatch}
+            onResume={handleResumeBatch}
+            onCancel={handleCancelBatch}
           />
         )}
       </main>
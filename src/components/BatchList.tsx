@@ -1,19 +1,40 @@
 import { cn } from "@/lib/utils";
 import type { ImageItem } from "@/types";
 
+function formatDuration(ms: number): string {
+  const seconds = Math.round(ms / 1000);
+  if (seconds < 60) return `${seconds} s`;
+  const minutes = Math.round(seconds / 60);
+  if (minutes < 60) return `${minutes} min`;
+  return `${Math.floor(minutes / 60)} h ${String(minutes % 60).padStart(2, "0")}`;
+}
+
 interface BatchListProps {
   items: ImageItem[];
   onSaveAll: () => void;
   onSaveOne: (item: ImageItem) => void;
   onReset: () => void;
   isSaving?: boolean;
+  /** État du lot en file ; absent une fois terminé */
+  jobState?: "running" | "paused";
+  /** Temps restant estimé et débit (images/s), d'après les dernières images */
+  etaMs?: number;
+  throughput?: number;
+  onPause?: () => void;
+  onResume?: () => void;
+  onCancel?: () => void;
 }
 
-export function BatchList({ items, onSaveAll, onSaveOne, onReset, isSaving }: BatchListProps) {
+export function BatchList({
+  items, onSaveAll, onSaveOne, onReset, isSaving, jobState, etaMs, throughput,
+  onPause, onResume, onCancel,
+}: BatchListProps) {
   const done = items.filter((i) => i.status === "done").length;
+  const cancelled = items.filter((i) => i.status === "cancelled").length;
   const total = items.length;
   const progress = total > 0 ? (done / total) * 100 : 0;
-  const allDone = done === total && total > 0;
+  // Un lot annulé se sauvegarde avec ce qui a été traité
+  const allDone = done > 0 && done + cancelled === total;
 
   return (
     <div className="flex flex-col h-full gap-4">
@@ -25,17 +46,41 @@ export function BatchList({ items, onSaveAll, onSaveOne, onReset, isSaving }: Ba
           </h2>
           <p className="text-muted-foreground text-sm">
             {done} / {total} image{total > 1 ? "s" : ""} traitée{done > 1 ? "s" : ""}
+            {jobState === "running" && etaMs !== undefined && (
+              <> · ≈ {formatDuration(etaMs)} restantes</>
+            )}
+            {jobState === "running" && throughput !== undefined && (
+              <> · {(throughput * 60).toFixed(throughput * 60 < 10 ? 1 : 0)} img/min</>
+            )}
           </p>
         </div>
-        <button
-          onClick={onReset}
-          className="text-muted-foreground hover:text-foreground text-sm flex items-center gap-1.5 transition-colors"
-        >
-          <svg className="w-4 h-4" fill="none" viewBox="0 0 24 24" stroke="currentColor" strokeWidth={2}>
-            <path strokeLinecap="round" strokeLinejoin="round" d="M6 18L18 6M6 6l12 12" />
-          </svg>
-          Tout effacer
-        </button>
+        <div className="flex items-center gap-4">
+          {jobState && (
+            <>
+              <button
+                onClick={jobState === "paused" ? onResume : onPause}
+                className="text-muted-foreground hover:text-foreground text-sm transition-colors"
+              >
+                {jobState === "paused" ? "Reprendre" : "Pause"}
+              </button>
+              <button
+                onClick={onCancel}
+                className="text-muted-foreground hover:text-destructive text-sm transition-colors"
+              >
+                Annuler
+              </button>
+            </>
+          )}
+          <button
+            onClick={onReset}
+            className="text-muted-foreground hover:text-foreground text-sm flex items-center gap-1.5 transition-colors"
+          >
+            <svg className="w-4 h-4" fill="none" viewBox="0 0 24 24" stroke="currentColor" strokeWidth={2}>
+              <path strokeLinecap="round" strokeLinejoin="round" d="M6 18L18 6M6 6l12 12" />
+            </svg>
+            Tout effacer
+          </button>
+        </div>
       </div>
 
       {/* Barre de progression globale */}
@@ -55,9 +100,9 @@ export function BatchList({ items, onSaveAll, onSaveOne, onReset, isSaving }: Ba
           >
             {/* Miniature / statut */}
             <div className="w-12 h-12 rounded-lg overflow-hidden flex-shrink-0 bg-muted flex items-center justify-center">
-              {item.status === "done" && item.resultDataUrl ? (
+              {item.status === "done" && item.resultUrl ? (
                 <img
-                  src={item.resultDataUrl}
+                  src={item.resultUrl}
                   alt={item.name}
                   className="w-full h-full object-cover checkerboard"
                 />
@@ -84,12 +129,13 @@ export function BatchList({ items, onSaveAll, onSaveOne, onReset, isSaving }: Ba
                 item.status === "done" && "text-green-400",
                 item.status === "error" && "text-destructive",
                 item.status === "processing" && "text-primary",
-                item.status === "pending" && "text-muted-foreground",
+                (item.status === "pending" || item.status === "cancelled") && "text-muted-foreground",
               )}>
                 {item.status === "done" && "Terminé"}
                 {item.status === "error" && (item.error ?? "Erreur")}
                 {item.status === "processing" && "Traitement…"}
-                {item.status === "pending" && "En attente"}
+                {item.status === "pending" && (jobState === "paused" ? "En pause" : "En attente")}
+                {item.status === "cancelled" && "Annulé"}
               </p>
             </div>
 
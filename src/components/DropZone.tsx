@@ -6,6 +6,8 @@ import { ACCEPTED_EXTENSIONS } from "@/types";
 
 interface DropZoneProps {
   onPaths: (paths: string[]) => void;
+  /** Dossier entier, parcouru côté Rust (`scan_folder`) */
+  onFolder?: (folder: string) => void;
   onPaste: () => void;
   disabled?: boolean;
 }
@@ -15,7 +17,7 @@ function isImagePath(p: string): boolean {
   return (ACCEPTED_EXTENSIONS as readonly string[]).includes(ext);
 }
 
-export function DropZone({ onPaths, onPaste, disabled }: DropZoneProps) {
+export function DropZone({ onPaths, onFolder, onPaste, disabled }: DropZoneProps) {
   const [isDragging, setIsDragging] = useState(false);
   const dragCount = useRef(0);
 
@@ -79,6 +81,18 @@ export function DropZone({ onPaths, onPaste, disabled }: DropZoneProps) {
     }
   }, [disabled, onPaths]);
 
+  // ── Lien "un dossier" : ne déclenche pas le sélecteur de fichiers ─────────
+  const handleBrowseFolder = useCallback(async (e: React.MouseEvent) => {
+    e.stopPropagation();
+    if (disabled || !onFolder) return;
+    try {
+      const folder = await open({ directory: true, title: "Choisir un dossier d'images" });
+      if (typeof folder === "string") onFolder(folder);
+    } catch {
+      // dialog annulé ou erreur
+    }
+  }, [disabled, onFolder]);
+
   // ── Empêche le comportement navigateur par défaut sur drop HTML5 ──────────
   const preventDefaults = useCallback((e: React.DragEvent) => {
     e.preventDefault();
@@ -119,6 +133,14 @@ export function DropZone({ onPaths, onPaste, disabled }: DropZoneProps) {
         <p className="text-muted-foreground text-sm">
           ou{" "}
           <span className="text-primary font-medium">parcourez vos fichiers</span>
+          {onFolder && (
+            <>
+              {" "}ou{" "}
+              <span className="text-primary font-medium hover:underline" onClick={handleBrowseFolder}>
+                un dossier
+              </span>
+            </>
+          )}
           {" "}— ou appuyez{" "}
           <kbd className="px-2 py-0.5 rounded bg-secondary border border-border text-xs font-mono">Ctrl+V</kbd>
         </p>
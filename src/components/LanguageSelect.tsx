@@ -0,0 +1,67 @@
+import { useCallback, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import type { LocaleInfo } from "@/types";
+
+/** Langue choisie, gardée entre deux lancements (absente : langue du système) */
+const STORAGE_KEY = "pureremove.locale";
+
+const NAMES: Record<string, string> = { fr: "Français", en: "English" };
+
+/** `fr-FR` → « Français » ; code brut pour une langue inconnue */
+function localeName(locale: string): string {
+  return NAMES[locale.split("-")[0]] ?? locale;
+}
+
+function storedLocale(): string | null {
+  try {
+    return localStorage.getItem(STORAGE_KEY);
+  } catch {
+    return null;
+  }
+}
+
+/** Applique la langue mémorisée aux messages du backend (au démarrage). */
+export function applyStoredLocale(): Promise<LocaleInfo> {
+  return invoke<LocaleInfo>("set_locale", { locale: storedLocale() });
+}
+
+interface LanguageSelectProps {
+  info: LocaleInfo | null;
+  onChange: (info: LocaleInfo) => void;
+  onError: (message: string) => void;
+}
+
+export function LanguageSelect({ info, onChange, onError }: LanguageSelectProps) {
+  const [choice, setChoice] = useState(() => storedLocale() ?? "");
+
+  const handleChange = useCallback(async (value: string) => {
+    setChoice(value);
+    try {
+      if (value) localStorage.setItem(STORAGE_KEY, value);
+      else localStorage.removeItem(STORAGE_KEY);
+      onChange(await invoke<LocaleInfo>("set_locale", { locale: value || null }));
+    } catch (e) {
+      onError((e as { message?: string }).message ?? String(e));
+    }
+  }, [onChange, onError]);
+
+  if (!info) return null;
+
+  return (
+    <div className="space-y-2">
+      <p className="text-muted-foreground text-xs font-medium uppercase tracking-wider">
+        Langue
+      </p>
+      <select
+        value={choice}
+        onChange={(e) => handleChange(e.target.value)}
+        className="px-3 py-1.5 rounded-lg border border-border bg-secondary text-sm text-foreground"
+      >
+        <option value="">Système ({localeName(info.system)})</option>
+        {info.available.map((locale) => (
+          <option key={locale} value={locale}>{localeName(locale)}</option>
+        ))}
+      </select>
+    </div>
+  );
+}
@@ -1,5 +1,16 @@
 import { useCallback, useEffect, useRef, useState } from "react";
 import { cn } from "@/lib/utils";
+import type { Stage } from "@/types";
+
+const STAGE_LABELS: Record<Stage, string> = {
+  decode: "Lecture de l'image…",
+  preprocess: "Préparation…",
+  inference: "Détourage…",
+  refine: "Affinage du masque…",
+  composite: "Composition…",
+  encode: "Encodage…",
+  save: "Enregistrement…",
+};
 
 interface SplitPreviewProps {
   originalSrc: string;
@@ -8,6 +19,8 @@ interface SplitPreviewProps {
   onSave: () => void;
   onReset: () => void;
   isProcessing?: boolean;
+  /** Étape en cours pendant le traitement */
+  stage?: Stage;
 }
 
 export function SplitPreview({
@@ -17,6 +30,7 @@ export function SplitPreview({
   onSave,
   onReset,
   isProcessing,
+  stage,
 }: SplitPreviewProps) {
   const [sliderPos, setSliderPos] = useState(50); // % horizontal
   const containerRef = useRef<HTMLDivElement>(null);
@@ -120,7 +134,9 @@ export function SplitPreview({
         {isProcessing && (
           <div className="absolute inset-0 bg-background/70 backdrop-blur-sm flex flex-col items-center justify-center gap-3 z-30">
             <div className="w-10 h-10 border-4 border-primary border-t-transparent rounded-full animate-spin" />
-            <span className="text-sm text-muted-foreground">Traitement en cours…</span>
+            <span className="text-sm text-muted-foreground">
+              {stage ? STAGE_LABELS[stage] : "Traitement en cours…"}
+            </span>
           </div>
         )}
       </div>
@@ -0,0 +1,139 @@
+import { useCallback, useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { open } from "@tauri-apps/plugin-dialog";
+import { cn } from "@/lib/utils";
+import type { WatchConfig, WatchEvent, WatchInfo, WatchLogEntry } from "@/types";
+
+/** Entrées du journal gardées à l'écran */
+const LOG_LIMIT = 50;
+
+interface WatchPanelProps {
+  onError: (message: string) => void;
+  disabled?: boolean;
+}
+
+function baseName(path: string): string {
+  return path.split(/[\\/]/).pop() ?? path;
+}
+
+function formatTime(ms: number): string {
+  return new Date(ms).toLocaleTimeString([], { hour: "2-digit", minute: "2-digit" });
+}
+
+export function WatchPanel({ onError, disabled }: WatchPanelProps) {
+  const [watches, setWatches] = useState<WatchInfo[]>([]);
+  const [log, setLog] = useState<WatchLogEntry[]>([]);
+  // Dernière erreur de relevé par dossier (effacée au prochain fichier traité)
+  const [errors, setErrors] = useState<Record<string, string>>({});
+
+  const refresh = useCallback(() => {
+    invoke<WatchInfo[]>("list_watches").then(setWatches).catch(() => {});
+  }, []);
+
+  useEffect(() => {
+    refresh();
+    let unlisten: (() => void) | undefined;
+    listen<WatchEvent>("watch-event", (event) => {
+      const { input, event: step, entry, error } = event.payload;
+      if (step === "error" && error) {
+        setErrors((prev) => ({ ...prev, [input]: error }));
+      } else if ((step === "done" || step === "failed") && entry) {
+        setLog((prev) => [entry, ...prev].slice(0, LOG_LIMIT));
+        setErrors((prev) => {
+          const next = { ...prev };
+          delete next[input];
+          return next;
+        });
+      }
+      refresh();
+    }).then((fn) => { unlisten = fn; });
+    return () => { unlisten?.(); };
+  }, [refresh]);
+
+  // Dossier surveillé, réglages JSON puis dossier de sortie
+  const handleAdd = useCallback(async () => {
+    try {
+      const input = await open({ directory: true, title: "Dossier à surveiller" });
+      if (typeof input !== "string") return;
+      const optionsFile = await open({
+        title: "Réglages de traitement (JSON)",
+        filters: [{ name: "JSON", extensions: ["json"] }],
+      });
+      if (typeof optionsFile !== "string") return;
+      const output = await open({ directory: true, title: "Dossier de sortie" });
+      if (typeof output !== "string") return;
+
+      const config: WatchConfig = { input, output, options_file: optionsFile };
+      await invoke<WatchInfo>("start_watch", { config });
+      const history = await invoke<WatchLogEntry[]>("read_watch_log", { input, limit: LOG_LIMIT });
+      setLog((prev) =>
+        [...prev, ...history]
+          .sort((a, b) => b.time_ms - a.time_ms)
+          .slice(0, LOG_LIMIT)
+      );
+      refresh();
+    } catch (e) {
+      onError(`Surveillance impossible : ${(e as { message?: string }).message ?? String(e)}`);
+    }
+  }, [onError, refresh]);
+
+  const handleStop = useCallback(async (input: string) => {
+    try {
+      await invoke("stop_watch", { input });
+    } catch (e) {
+      onError((e as { message?: string }).message ?? String(e));
+    }
+    refresh();
+  }, [onError, refresh]);
+
+  return (
+    <div className="rounded-xl border border-border bg-secondary/30 p-3 space-y-2">
+      <div className="flex items-center justify-between">
+        <h3 className="text-foreground text-sm font-semibold">Dossiers surveillés</h3>
+        <button
+          onClick={handleAdd}
+          disabled={disabled}
+          className="text-primary text-sm font-medium hover:underline disabled:opacity-50"
+        >
+          Surveiller un dossier…
+        </button>
+      </div>
+
+      {watches.map(({ config, in_flight }) => (
+        <div key={config.input} className="flex items-center gap-3 text-xs">
+          <div className="flex-1 min-w-0">
+            <p className="text-foreground truncate" title={config.input}>
+              {config.input} → {baseName(config.output)}
+            </p>
+            <p className={cn("truncate", errors[config.input] ? "text-destructive" : "text-muted-foreground")}>
+              {errors[config.input] ??
+                `${baseName(config.options_file)} · ${in_flight > 0 ? `${in_flight} en cours` : "en attente de fichiers"}`}
+            </p>
+          </div>
+          <button
+            onClick={() => handleStop(config.input)}
+            className="text-muted-foreground hover:text-destructive transition-colors"
+          >
+            Arrêter
+          </button>
+        </div>
+      ))}
+
+      {log.length > 0 && (
+        <div className="max-h-32 overflow-y-auto scrollbar-thin space-y-0.5 pt-1 border-t border-border">
+          {log.map((entry) => (
+            <p key={`${entry.time_ms}-${entry.file}`} className="text-xs truncate" title={entry.error ?? entry.output ?? ""}>
+              <span className="text-muted-foreground">{formatTime(entry.time_ms)}</span>{" "}
+              <span className={entry.status === "done" ? "text-green-400" : "text-destructive"}>
+                {entry.status === "done" ? "✓" : "✕"}
+              </span>{" "}
+              <span className="text-foreground">{baseName(entry.file)}</span>
+              {entry.error && <span className="text-destructive"> — {entry.error}</span>}
+            </p>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
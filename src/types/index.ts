@@ -9,7 +9,8 @@ export const ACCEPTED_EXTENSIONS = [
   "png", "jpg", "jpeg", "webp", "svg",
   "bmp", "gif", "tif", "tiff", "ico",
   "tga", "pnm", "pbm", "pgm", "ppm",
-  "hdr", "ff", "qoi",
+  "hdr", "exr", "ff", "qoi",
+  "jxl", "avif",          // selon les features du backend
 ] as const;
 
 export const ACCEPTED_MIME_TYPES = [
@@ -20,14 +21,108 @@ export const ACCEPTED_MIME_TYPES = [
   "image/x-portable-anymap", "image/x-portable-bitmap",
   "image/x-portable-graymap", "image/x-portable-pixmap",
   "image/vnd.radiance",   // HDR
+  "image/x-exr",
   "image/avif",           // bonus si dispo
+  "image/jxl",
 ] as const;
 
+export type MaskBitDepth = "Eight" | "Sixteen";
+
+export interface SvgExportOptions {
+  threshold?: number;
+  tolerance?: number;
+  smoothing?: number;
+  embed_image?: boolean;
+}
+
+export type PngCompression = "Fast" | "Default" | "Best";
+
+/** Format de sortie de l'enregistrement (PNG par défaut) */
+export type OutputFormat =
+  | { type: "Png"; compression?: PngCompression }
+  | { type: "WebP"; lossless?: boolean; quality?: number }
+  | { type: "Tiff" }
+  | { type: "Jpeg"; quality?: number }
+  | { type: "Qoi" }
+  | { type: "Avif"; quality?: number; speed?: number }
+  | { type: "Exr" };
+
+/** Espace de mélange avec le fond ("Gamma" = ancien comportement) */
+export type CompositingSpace = "Linear" | "Gamma";
+
 export interface ProcessOptions {
   background: BackgroundColor;
+  /** Joint un aperçu du masque affiné aux résultats */
+  include_mask?: boolean;
+  /** Taille max acceptée au chargement (défaut 100 MP) */
+  max_megapixels?: number;
+  /** Upscale du masque guidé par l'image pleine résolution */
+  refine_edges?: boolean;
+  /** Retire EXIF/XMP des sorties (le profil ICC est toujours conservé) */
+  strip_metadata?: boolean;
+  /** Défaut "Linear" */
+  compositing?: CompositingSpace;
+  /** Taille de rasterisation des sources SVG */
+  svg?: SvgRenderOptions;
+}
+
+/** Sans rien : 2048 px de large minimum ; plus grand côté plafonné à 8192 px */
+export interface SvgRenderOptions {
+  width?: number;
+  height?: number;
+  /** 96 = taille nominale du SVG */
+  dpi?: number;
 }
 
-export type ItemStatus = "pending" | "processing" | "done" | "error";
+export type AnimationFormat = "Gif" | "WebP";
+
+export interface AnimationOptions {
+  /** Défaut : format de la source */
+  format?: AnimationFormat;
+  /** Lissage temporel des masques, 0 (aucun) … 1 */
+  temporal_smoothing?: number;
+  /** WebP uniquement */
+  lossless?: boolean;
+  quality?: number;
+}
+
+export interface VideoOptions {
+  /** Lissage temporel des masques, 0 … 1 (défaut 0.5) */
+  temporal_smoothing?: number;
+  /** Cadence d'un dossier d'images (défaut 25) */
+  fps?: number;
+  y4m?: boolean;
+  apng?: boolean;
+}
+
+export interface VideoSummary {
+  frames: number;
+  output_dir: string;
+  y4m_path?: string;
+  apng_path?: string;
+  cancelled: boolean;
+}
+
+export type PageOutput = "Separate" | "MultiPage";
+
+export interface PageOptions {
+  /** Un fichier par page (défaut) ou un TIFF/ICO multipage */
+  output?: PageOutput;
+  /** Format des fichiers séparés (défaut PNG) */
+  format?: OutputFormat;
+}
+
+export type LayerFormat = "Ora" | "Tiff" | "Psd";
+
+export interface LayerOptions {
+  /** OpenRaster (défaut), TIFF multipage (un calque par page) ou PSD avec masque de fusion */
+  format?: LayerFormat;
+  /** Calque de fond ; aucun si transparent */
+  background?: BackgroundColor;
+  compositing?: CompositingSpace;
+}
+
+export type ItemStatus = "pending" | "processing" | "done" | "error" | "cancelled";
 
 export interface ImageItem {
   id: string;
@@ -36,16 +131,203 @@ export interface ImageItem {
   sourcePath?: string;
   sourceDataUrl?: string;
   status: ItemStatus;
-  resultDataUrl?: string;
+  /** Identifiant du résultat gardé côté Rust (sauvegarde, copie) */
+  resultId?: number;
+  /** Aperçu réduit du résultat (URL pureremove://) */
+  resultUrl?: string;
+  maskUrl?: string;
   error?: string;
 }
 
 export type AppMode = "idle" | "single" | "batch";
 
+/** Erreur renvoyée par toutes les commandes : `code` stable, `message` lisible. */
+export interface AppError {
+  code: string;
+  message: string;
+  details?: Record<string, unknown> | null;
+}
+
+/** Résultat gardé côté Rust : les commandes de sauvegarde/copie prennent `id` */
+export interface ProcessedResult {
+  id: number;
+  name: string;
+  /** Taille réelle du résultat */
+  width: number;
+  height: number;
+  /** URL `pureremove://` de l'image à taille réelle */
+  url: string;
+  /** Même image réduite (1024 px max), pour les listes */
+  preview_url: string;
+  /** Masque affiné, avec `include_mask` */
+  mask_url?: string | null;
+}
+
 export interface BatchProgressEvent {
+  /** Lot retourné par `process_batch_images` */
+  job_id: number;
   index: number;
   total: number;
   name: string;
-  result_data_url?: string;
-  error?: string;
+  result?: ProcessedResult;
+  error?: AppError;
+  /** Durée de chaque étape pour cette image */
+  timings: StageTiming[];
+}
+
+export interface AnimationProgressEvent {
+  frame: number;
+  total: number;
+  name: string;
+}
+
+export interface VideoProgressEvent {
+  frame: number;
+  total?: number;
+  name: string;
+}
+
+export interface PageProgressEvent {
+  page: number;
+  total: number;
+  name: string;
+}
+
+// ─── File de traitements (jobs.rs) ───────────────────────────────────────────
+
+export type JobKind = "single" | "clipboard" | "batch" | "animation" | "pages" | "video" | "watch";
+
+export type JobEventKind =
+  | "queued" | "started" | "progress" | "paused" | "resumed"
+  | "stage_started" | "stage_finished"
+  | "completed" | "failed" | "cancelled";
+
+/** Étapes de calcul d'une image, dans l'ordre du pipeline */
+export type Stage =
+  | "decode" | "preprocess" | "inference" | "refine" | "composite" | "encode" | "save";
+
+export interface StageTiming {
+  stage: Stage;
+  duration_ms: number;
+}
+
+/** Événement `job-event` : cycle de vie d'un traitement en file */
+export interface JobEvent {
+  id: number;
+  kind: JobKind;
+  name: string;
+  event: JobEventKind;
+  /** Unités terminées (fichiers, images, pages) */
+  done: number;
+  total?: number | null;
+  /** Avec `stage_started` / `stage_finished` */
+  stage?: Stage | null;
+  /** Durée de l'étape, avec `stage_finished` */
+  duration_ms?: number | null;
+  /** Unités par seconde sur les dernières unités, avec `progress` */
+  throughput?: number | null;
+  /** Temps restant estimé, avec `progress` */
+  eta_ms?: number | null;
+  /** Avec `failed` */
+  error?: AppError | null;
+}
+
+/** Retour de `list_jobs` */
+export interface JobInfo {
+  id: number;
+  kind: JobKind;
+  name: string;
+  priority: "interactive" | "background";
+  state: "queued" | "running" | "paused";
+  done: number;
+  total?: number | null;
+}
+
+/** Sélection des images d'un dossier (`scan_folder`) ; tout est optionnel. */
+export interface ScanOptions {
+  recursive?: boolean;
+  /** Motifs glob sur le chemin relatif, sans casse (`*.jpg`, `clients/**`) */
+  include?: string[];
+  /** Motifs glob écartant des fichiers ou des dossiers entiers */
+  exclude?: string[];
+  /** Poids du fichier, en octets */
+  min_file_size?: number;
+  max_file_size?: number;
+  min_width?: number;
+  min_height?: number;
+  max_width?: number;
+  max_height?: number;
+  /** Écarte les `_nobg` / `_mask` / `_layers` et ce qui est déjà dans `output_folder` (défaut : true) */
+  skip_processed?: boolean;
+  output_folder?: string;
+}
+
+export interface ScannedFile {
+  path: string;
+  /** Relatif au dossier scanné, séparateurs `/` */
+  relative: string;
+}
+
+export interface ScanReport {
+  /** À repasser à `process_batch_images` pour conserver les sous-dossiers */
+  root: string;
+  files: ScannedFile[];
+  skipped: {
+    excluded: number;
+    processed: number;
+    size: number;
+    unsupported: number;
+    dimensions: number;
+  };
+}
+
+/** Langue des messages du backend (`get_locale` / `set_locale`). */
+export interface LocaleInfo {
+  locale: string;
+  system: string;
+  available: string[];
+}
+
+/**
+ * Dossier surveillé (`start_watch`). `options_file` : JSON d'un `ProcessOptions`,
+ * plus un `format` de sortie facultatif (`OutputFormat`, PNG par défaut).
+ */
+export interface WatchConfig {
+  input: string;
+  output: string;
+  options_file: string;
+  /** Défaut : `input/done` et `input/failed` */
+  done_folder?: string | null;
+  failed_folder?: string | null;
+  poll_interval_ms?: number;
+  /** Délai sans changement avant de prendre un fichier (défaut 3000) */
+  settle_ms?: number;
+  /** `recursive` et `output_folder` sont ignorés */
+  scan?: ScanOptions;
+}
+
+export interface WatchInfo {
+  config: WatchConfig;
+  /** Fichiers en file ou en cours */
+  in_flight: number;
+}
+
+/** Ligne du journal `pureremove-watch.jsonl` du dossier surveillé */
+export interface WatchLogEntry {
+  time_ms: number;
+  file: string;
+  status: "done" | "failed";
+  output?: string | null;
+  moved_to?: string | null;
+  error?: string | null;
+  duration_ms: number;
+}
+
+/** Événement `watch-event` */
+export interface WatchEvent {
+  input: string;
+  event: "started" | "stopped" | "queued" | "done" | "failed" | "options_reloaded" | "error";
+  file?: string | null;
+  entry?: WatchLogEntry | null;
+  error?: string | null;
 }